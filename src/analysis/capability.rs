@@ -0,0 +1,459 @@
+//! The flow of capabilities between protocols: which protocols can hand out channels to other
+//! protocols, and which handles each method transfers.
+//!
+//! Capabilities are found in method parameters and, transitively, in the structs, tables, and
+//! unions those parameters contain. A client of protocol `A` obtains:
+//!
+//! * the handles in the responses and events of `A`,
+//! * a connection to `B` when a request of `A` takes a `request<B>` (the client keeps the other
+//!   end) or a response or event of `A` carries a `B`.
+//!
+//! Following these grants answers questions like "which protocols can lead to a `job` handle?".
+
+use {
+    crate::{
+        visit::{Decl, DeclLookup, Direction},
+        DeclPath, HandleSubtype, Library, TableMemberType, Type, TypeKind,
+    },
+    std::{collections::HashSet, fmt::Write},
+};
+
+/// Which end of a channel is transferred.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum End {
+    /// A client end, written `P`.
+    Client,
+    /// A server end, written `request<P>`.
+    Server,
+}
+
+/// An end of a channel to protocol `to` that a method of protocol `from` transfers.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CapabilityEdge {
+    pub from: DeclPath,
+    pub to: DeclPath,
+    pub method: String,
+    pub direction: Direction,
+    pub end: End,
+}
+
+impl CapabilityEdge {
+    /// Returns `true` if a client of `from` ends up with a connection to `to`.
+    pub fn grants_client(&self) -> bool {
+        match (self.direction, self.end) {
+            (Direction::Request, End::Server) | (Direction::Response, End::Client) => true,
+            (Direction::Request, End::Client) | (Direction::Response, End::Server) => false,
+        }
+    }
+}
+
+/// A handle that a method of `protocol` transfers.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct HandleTransfer {
+    pub protocol: DeclPath,
+    pub method: String,
+    pub direction: Direction,
+    pub subtype: HandleSubtype,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CapabilityGraph {
+    /// Every protocol declared in the libraries, followed by any other protocols transferred.
+    pub protocols: Vec<DeclPath>,
+    pub edges: Vec<CapabilityEdge>,
+    pub handles: Vec<HandleTransfer>,
+}
+
+enum Found {
+    Protocol(DeclPath, End),
+    Handle(HandleSubtype),
+}
+
+/// Collects the capabilities in a type, looking through the declarations it contains.
+struct Collector<'a, L: ?Sized> {
+    lookup: &'a L,
+    visited: HashSet<DeclPath>,
+    found: Vec<Found>,
+}
+
+impl<L: DeclLookup + ?Sized> Collector<'_, L> {
+    fn r#type(&mut self, r#type: &Type) {
+        match &*r#type.kind {
+            TypeKind::Array { element_type, .. } | TypeKind::Vector { element_type, .. } => {
+                self.r#type(element_type)
+            }
+            TypeKind::Handle { subtype, .. } => self.found.push(Found::Handle(*subtype)),
+            TypeKind::Request { subtype, .. } => {
+                self.found.push(Found::Protocol(subtype.clone(), End::Server))
+            }
+            TypeKind::Identifier { identifier, .. } => self.decl(identifier),
+            _ => {}
+        }
+    }
+
+    fn decl(&mut self, path: &DeclPath) {
+        let decl = match self.lookup.lookup_decl(path) {
+            Some(Decl::Protocol(_)) => {
+                self.found.push(Found::Protocol(path.clone(), End::Client));
+                return;
+            }
+            Some(decl) if self.visited.insert(path.clone()) => decl,
+            _ => return,
+        };
+        match decl {
+            Decl::Struct(decl) => {
+                decl.members.iter().for_each(|member| self.r#type(&member.r#type));
+            }
+            Decl::Table(decl) => {
+                for member in &decl.members {
+                    if let TableMemberType::Field { r#type, .. } = &member.member_type {
+                        self.r#type(r#type);
+                    }
+                }
+            }
+            Decl::Union(decl) => decl.members.iter().for_each(|member| self.r#type(&member.r#type)),
+            Decl::XUnion(decl) => {
+                decl.members.iter().for_each(|member| self.r#type(&member.r#type));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl CapabilityGraph {
+    pub fn new(libraries: &[Library]) -> Self {
+        let mut graph = CapabilityGraph::default();
+        for library in libraries {
+            graph.protocols.extend(library.protocols.iter().map(|decl| decl.name.inner.clone()));
+        }
+        for decl in libraries.iter().flat_map(|library| &library.protocols) {
+            for method in &decl.methods {
+                for (direction, message) in method.messages() {
+                    let mut collector =
+                        Collector { lookup: libraries, visited: HashSet::new(), found: Vec::new() };
+                    for param in &message.parameters {
+                        collector.r#type(&param.r#type);
+                    }
+                    for found in collector.found {
+                        match found {
+                            Found::Protocol(to, end) => {
+                                let edge = CapabilityEdge {
+                                    from: decl.name.inner.clone(),
+                                    to,
+                                    method: method.name.inner.clone(),
+                                    direction,
+                                    end,
+                                };
+                                if !graph.protocols.contains(&edge.to) {
+                                    graph.protocols.push(edge.to.clone());
+                                }
+                                if !graph.edges.contains(&edge) {
+                                    graph.edges.push(edge);
+                                }
+                            }
+                            Found::Handle(subtype) => {
+                                let transfer = HandleTransfer {
+                                    protocol: decl.name.inner.clone(),
+                                    method: method.name.inner.clone(),
+                                    direction,
+                                    subtype,
+                                };
+                                if !graph.handles.contains(&transfer) {
+                                    graph.handles.push(transfer);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    /// Returns the protocols a client of `protocol` can connect to, directly or through other
+    /// protocols, starting with `protocol` itself.
+    pub fn reachable_protocols(&self, protocol: &DeclPath) -> Vec<DeclPath> {
+        let mut reachable = vec![protocol.clone()];
+        let mut next = 0;
+        while next < reachable.len() {
+            let from = reachable[next].clone();
+            for edge in self.edges.iter().filter(|edge| edge.from == from && edge.grants_client()) {
+                if !reachable.contains(&edge.to) {
+                    reachable.push(edge.to.clone());
+                }
+            }
+            next += 1;
+        }
+        reachable
+    }
+
+    /// Returns the kinds of handles a client of `protocol` can obtain, directly or through the
+    /// protocols it can connect to.
+    pub fn obtainable_handles(&self, protocol: &DeclPath) -> Vec<HandleSubtype> {
+        let mut subtypes = Vec::new();
+        for reachable in self.reachable_protocols(protocol) {
+            for transfer in &self.handles {
+                if transfer.protocol == reachable
+                    && transfer.direction == Direction::Response
+                    && !subtypes.contains(&transfer.subtype)
+                {
+                    subtypes.push(transfer.subtype);
+                }
+            }
+        }
+        subtypes
+    }
+
+    /// Returns the handle subtypes transferred by the graph, in order of first appearance.
+    fn subtypes(&self) -> Vec<HandleSubtype> {
+        let mut subtypes = Vec::new();
+        for transfer in &self.handles {
+            if !subtypes.contains(&transfer.subtype) {
+                subtypes.push(transfer.subtype);
+            }
+        }
+        subtypes
+    }
+
+    fn protocol_index(&self, path: &DeclPath) -> usize {
+        self.protocols.iter().position(|protocol| protocol == path).unwrap_or_default()
+    }
+
+    /// Writes the graph in Graphviz DOT format. Protocols are boxes and handle subtypes are
+    /// ellipses; edges that give the client a connection are solid, others are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph capabilities {\n");
+        for protocol in &self.protocols {
+            writeln!(out, "    \"{}\" [shape=box];", protocol).unwrap();
+        }
+        for subtype in self.subtypes() {
+            writeln!(out, "    \"handle<{}>\" [shape=ellipse];", subtype).unwrap();
+        }
+        for edge in &self.edges {
+            let style = if edge.grants_client() { "solid" } else { "dashed" };
+            writeln!(
+                out,
+                "    \"{}\" -> \"{}\" [label=\"{} ({})\", style={}];",
+                edge.from, edge.to, edge.method, edge.direction, style
+            )
+            .unwrap();
+        }
+        for transfer in &self.handles {
+            let style = if transfer.direction == Direction::Response { "solid" } else { "dashed" };
+            writeln!(
+                out,
+                "    \"{}\" -> \"handle<{}>\" [label=\"{} ({})\", style={}];",
+                transfer.protocol, transfer.subtype, transfer.method, transfer.direction, style
+            )
+            .unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Writes the graph as a Mermaid flowchart, styled like [`to_dot`](Self::to_dot).
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from("flowchart LR\n");
+        for (i, protocol) in self.protocols.iter().enumerate() {
+            writeln!(out, "    p{}[\"{}\"]", i, protocol).unwrap();
+        }
+        for subtype in self.subtypes() {
+            writeln!(out, "    h_{}([\"handle&lt;{}&gt;\"])", subtype, subtype).unwrap();
+        }
+        for edge in &self.edges {
+            let arrow = if edge.grants_client() { "-->" } else { "-.->" };
+            writeln!(
+                out,
+                "    p{} {}|\"{} ({})\"| p{}",
+                self.protocol_index(&edge.from),
+                arrow,
+                edge.method,
+                edge.direction,
+                self.protocol_index(&edge.to)
+            )
+            .unwrap();
+        }
+        for transfer in &self.handles {
+            let arrow = if transfer.direction == Direction::Response { "-->" } else { "-.->" };
+            writeln!(
+                out,
+                "    p{} {}|\"{} ({})\"| h_{}",
+                self.protocol_index(&transfer.protocol),
+                arrow,
+                transfer.method,
+                transfer.direction,
+                transfer.subtype
+            )
+            .unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::fixtures::{identifier, library_json, struct_json},
+        serde_json::{json, Value},
+    };
+
+    fn path(decl_name: &str) -> DeclPath {
+        DeclPath { library_name: "test.cap".to_string(), decl_name: decl_name.to_string() }
+    }
+
+    fn handle(subtype: &str) -> Value {
+        json!({"kind": "handle", "subtype": subtype})
+    }
+
+    fn parameters(params: &[(&str, Value)]) -> Value {
+        params
+            .iter()
+            .map(|(name, r#type)| {
+                json!({
+                    "type": r#type,
+                    "name": name,
+                    "offset": 16,
+                    "max_handles": 0,
+                    "max_out_of_line": 0,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the JSON IR of a method; `request` is `None` for events, and `response` is
+    /// `None` for one-way methods.
+    fn method(
+        name: &str,
+        request: Option<&[(&str, Value)]>,
+        response: Option<&[(&str, Value)]>,
+    ) -> Value {
+        let mut method = json!({
+            "attributes": [],
+            "ordinal": 1,
+            "generated_ordinal": 1,
+            "name": name,
+            "has_request": request.is_some(),
+            "has_response": response.is_some(),
+        });
+        if let Some(request) = request {
+            method["maybe_request"] = parameters(request);
+            method["maybe_request_size"] = json!(24);
+        }
+        if let Some(response) = response {
+            method["maybe_response"] = parameters(response);
+            method["maybe_response_size"] = json!(24);
+        }
+        method
+    }
+
+    fn protocol(name: &str, methods: Vec<Value>) -> Value {
+        json!({"name": format!("test.cap/{}", name), "attributes": [], "methods": methods})
+    }
+
+    /// `Launcher` hands out `Process` through a server end and `Controller` through an event,
+    /// and a job through a struct. `Process` returns a vmo and takes a process handle.
+    /// `Controller` only takes a client end of `Launcher`, and `Isolated` is unreachable.
+    fn graph() -> CapabilityGraph {
+        let library = library_json(json!({
+            "name": "test.cap",
+            "interface_declarations": [
+                protocol("Launcher", vec![
+                    method("Launch", Some(&[("process", json!({"kind": "request", "subtype": "test.cap/Process"}))]), None),
+                    method("GetJob", Some(&[]), Some(&[("holder", identifier("test.cap/Holder"))])),
+                    method("OnReady", None, Some(&[("controller", identifier("test.cap/Controller"))])),
+                ]),
+                protocol("Process", vec![
+                    method("GetVmo", Some(&[]), Some(&[("vmo", handle("vmo"))])),
+                    method("Give", Some(&[("process", handle("process"))]), None),
+                ]),
+                protocol("Controller", vec![
+                    method("Spawn", Some(&[("launcher", identifier("test.cap/Launcher"))]), None),
+                ]),
+                protocol("Isolated", vec![
+                    method("GetThread", Some(&[]), Some(&[("thread", handle("thread"))])),
+                ]),
+            ],
+            "struct_declarations": [struct_json("test.cap/Holder", &[("job", handle("job"))])],
+        }));
+        CapabilityGraph::new(&[library])
+    }
+
+    #[test]
+    fn grants_client_by_direction_and_end() {
+        let edge = |direction, end| CapabilityEdge {
+            from: path("A"),
+            to: path("B"),
+            method: "M".to_string(),
+            direction,
+            end,
+        };
+        assert!(edge(Direction::Request, End::Server).grants_client());
+        assert!(edge(Direction::Response, End::Client).grants_client());
+        assert!(!edge(Direction::Request, End::Client).grants_client());
+        assert!(!edge(Direction::Response, End::Server).grants_client());
+    }
+
+    #[test]
+    fn follows_grants_transitively() {
+        let graph = graph();
+        assert_eq!(graph.edges.len(), 3);
+        assert_eq!(
+            graph.reachable_protocols(&path("Launcher")),
+            [path("Launcher"), path("Process"), path("Controller")]
+        );
+        assert_eq!(
+            graph.obtainable_handles(&path("Launcher")),
+            [HandleSubtype::Job, HandleSubtype::Vmo]
+        );
+        assert_eq!(graph.reachable_protocols(&path("Controller")), [path("Controller")]);
+        assert!(graph.obtainable_handles(&path("Controller")).is_empty());
+        assert_eq!(graph.obtainable_handles(&path("Process")), [HandleSubtype::Vmo]);
+    }
+
+    #[test]
+    fn writes_dot_and_mermaid() {
+        let graph = graph();
+        assert_eq!(
+            graph.to_dot(),
+            r#"digraph capabilities {
+    "test.cap/Launcher" [shape=box];
+    "test.cap/Process" [shape=box];
+    "test.cap/Controller" [shape=box];
+    "test.cap/Isolated" [shape=box];
+    "handle<job>" [shape=ellipse];
+    "handle<vmo>" [shape=ellipse];
+    "handle<process>" [shape=ellipse];
+    "handle<thread>" [shape=ellipse];
+    "test.cap/Launcher" -> "test.cap/Process" [label="Launch (request)", style=solid];
+    "test.cap/Launcher" -> "test.cap/Controller" [label="OnReady (response)", style=solid];
+    "test.cap/Controller" -> "test.cap/Launcher" [label="Spawn (request)", style=dashed];
+    "test.cap/Launcher" -> "handle<job>" [label="GetJob (response)", style=solid];
+    "test.cap/Process" -> "handle<vmo>" [label="GetVmo (response)", style=solid];
+    "test.cap/Process" -> "handle<process>" [label="Give (request)", style=dashed];
+    "test.cap/Isolated" -> "handle<thread>" [label="GetThread (response)", style=solid];
+}
+"#
+        );
+        assert_eq!(
+            graph.to_mermaid(),
+            r#"flowchart LR
+    p0["test.cap/Launcher"]
+    p1["test.cap/Process"]
+    p2["test.cap/Controller"]
+    p3["test.cap/Isolated"]
+    h_job(["handle&lt;job&gt;"])
+    h_vmo(["handle&lt;vmo&gt;"])
+    h_process(["handle&lt;process&gt;"])
+    h_thread(["handle&lt;thread&gt;"])
+    p0 -->|"Launch (request)"| p1
+    p0 -->|"OnReady (response)"| p2
+    p2 -.->|"Spawn (request)"| p0
+    p0 -->|"GetJob (response)"| h_job
+    p1 -->|"GetVmo (response)"| h_vmo
+    p1 -.->|"Give (request)"| h_process
+    p3 -->|"GetThread (response)"| h_thread
+"#
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! Detection of recursive declarations that would have infinite size, and computation of how
+//! deeply values of each declaration can nest out-of-line objects.
+
+use {
+    crate::{visit::Decl, DeclPath, Diagnostic, Library, Spanned, TableMemberType, Type, TypeKind},
+    indexmap::IndexMap,
+    std::{collections::HashSet, fmt},
+};
+
+/// The maximum out-of-line nesting depth accepted by FIDL decoders.
+pub const DEFAULT_DEPTH_LIMIT: u32 = 32;
+
+/// One step along a cycle: `member` of the declaration `from` holds the next declaration inline.
+#[derive(Debug, Clone)]
+pub struct CycleEdge {
+    pub from: Spanned<DeclPath>,
+    pub member: Spanned<String>,
+    pub r#type: Spanned<Type>,
+}
+
+/// A cycle of declarations that each contain the next inline, so none of them has a finite size.
+/// The last edge leads back to the `from` of the first.
+#[derive(Debug, Clone)]
+pub struct Cycle {
+    pub edges: Vec<CycleEdge>,
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for edge in &self.edges {
+            write!(f, "{}.{} -> ", edge.from.decl_name, *edge.member)?;
+        }
+        match self.edges.first() {
+            Some(first) => write!(f, "{}", first.from.decl_name),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Cycle {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let first = &self.edges[0];
+        let mut diagnostic = Diagnostic::error(
+            "infinite-size-cycle",
+            format!("`{}` contains itself and has infinite size: {}", *first.from, self),
+            first.from.span,
+        );
+        for edge in &self.edges {
+            diagnostic = diagnostic.with_note(
+                format!("`{}` holds `{}` inline", *edge.from, *edge.member),
+                edge.member.span.or(edge.r#type.span),
+            );
+        }
+        diagnostic.with_note(
+            "make one of these members nullable or wrap it in a vector to break the cycle",
+            None,
+        )
+    }
+}
+
+/// Returns the struct or union that `r#type` stores inline, looking through arrays.
+fn inline_decl<'a>(library: &'a Library, r#type: &Type) -> Option<&'a Spanned<DeclPath>> {
+    match &*r#type.kind {
+        TypeKind::Array { element_type, .. } => inline_decl(library, element_type),
+        TypeKind::Identifier { identifier, .. } if !*r#type.nullable => {
+            match library.lookup(identifier)? {
+                decl @ Decl::Struct(_) | decl @ Decl::Union(_) => Some(decl.name()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns the members of a struct or union along with their types.
+fn inline_members(decl: Decl<'_>) -> Vec<(&Spanned<String>, &Spanned<Type>)> {
+    match decl {
+        Decl::Struct(decl) => {
+            decl.members.iter().map(|member| (&member.name, &member.r#type)).collect()
+        }
+        Decl::Union(decl) => {
+            decl.members.iter().map(|member| (&member.name, &member.r#type)).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Finds every cycle of structs and unions that contain each other inline, through members
+/// that are neither nullable nor behind a vector.
+pub fn find_infinite_cycles(library: &Library) -> Vec<Cycle> {
+    // Each cycle is found only from its earliest declaration in `order`, so it's reported once.
+    fn visit(
+        library: &Library,
+        order: &[&Spanned<DeclPath>],
+        root: usize,
+        decl: &Spanned<DeclPath>,
+        stack: &mut Vec<CycleEdge>,
+        cycles: &mut Vec<Cycle>,
+    ) {
+        let found = match library.lookup(decl) {
+            Some(found) => found,
+            None => return,
+        };
+        for (member, r#type) in inline_members(found) {
+            let next = match inline_decl(library, r#type) {
+                Some(next) => next,
+                None => continue,
+            };
+            let index = match order.iter().position(|name| *name == next) {
+                Some(index) if index >= root => index,
+                _ => continue,
+            };
+            stack.push(CycleEdge {
+                from: decl.clone(),
+                member: member.clone(),
+                r#type: r#type.clone(),
+            });
+            if index == root {
+                cycles.push(Cycle { edges: stack.clone() });
+            } else if stack.iter().all(|edge| edge.from != *next) {
+                visit(library, order, root, next, stack, cycles);
+            }
+            stack.pop();
+        }
+    }
+
+    let order: Vec<&Spanned<DeclPath>> = library
+        .structs
+        .iter()
+        .map(|decl| &decl.name)
+        .chain(library.unions.iter().map(|decl| &decl.name))
+        .collect();
+    let mut cycles = Vec::new();
+    for (root, name) in order.iter().enumerate() {
+        visit(library, &order, root, name, &mut Vec::new(), &mut cycles);
+    }
+    cycles
+}
+
+/// How many out-of-line objects a value can nest.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Depth {
+    Bounded(u32),
+    /// The declaration is recursive, so values can nest arbitrarily deeply.
+    Unbounded,
+}
+
+impl Depth {
+    fn plus(self, n: u32) -> Depth {
+        match self {
+            Depth::Bounded(depth) => Depth::Bounded(depth + n),
+            Depth::Unbounded => Depth::Unbounded,
+        }
+    }
+}
+
+struct DepthCalculator<'a> {
+    library: &'a Library,
+    depths: IndexMap<DeclPath, Depth>,
+    in_progress: HashSet<DeclPath>,
+}
+
+impl<'a> DepthCalculator<'a> {
+    fn type_depth(&mut self, r#type: &Type) -> Depth {
+        match &*r#type.kind {
+            TypeKind::String { .. } => Depth::Bounded(1),
+            TypeKind::Vector { element_type, .. } => self.type_depth(element_type).plus(1),
+            TypeKind::Array { element_type, .. } => self.type_depth(element_type),
+            TypeKind::Identifier { identifier, .. } => {
+                let depth = self.decl_depth(identifier);
+                let boxed = match self.library.lookup(identifier) {
+                    Some(Decl::Struct(_)) | Some(Decl::Union(_)) => *r#type.nullable,
+                    _ => false,
+                };
+                if boxed {
+                    depth.plus(1)
+                } else {
+                    depth
+                }
+            }
+            _ => Depth::Bounded(0),
+        }
+    }
+
+    fn max_depth<'b>(&mut self, types: impl Iterator<Item = &'b Spanned<Type>>) -> Depth {
+        types.map(|r#type| self.type_depth(r#type)).max().unwrap_or(Depth::Bounded(0))
+    }
+
+    fn decl_depth(&mut self, path: &DeclPath) -> Depth {
+        if let Some(depth) = self.depths.get(path) {
+            return *depth;
+        }
+        if !self.in_progress.insert(path.clone()) {
+            return Depth::Unbounded;
+        }
+        let depth = match self.library.lookup(path) {
+            Some(Decl::Struct(decl)) => {
+                self.max_depth(decl.members.iter().map(|member| &member.r#type))
+            }
+            Some(Decl::Union(decl)) => {
+                self.max_depth(decl.members.iter().map(|member| &member.r#type))
+            }
+            // Each member is stored out-of-line in an envelope.
+            Some(Decl::XUnion(decl)) => {
+                self.max_depth(decl.members.iter().map(|member| &member.r#type)).plus(1)
+            }
+            // The envelopes are stored out-of-line, and each member is out-of-line from those.
+            Some(Decl::Table(decl)) => {
+                let types = decl.members.iter().filter_map(|member| match &member.member_type {
+                    TableMemberType::Field { r#type, .. } => Some(r#type),
+                    TableMemberType::Reserved => None,
+                });
+                self.max_depth(types).plus(2)
+            }
+            _ => Depth::Bounded(0),
+        };
+        self.in_progress.remove(path);
+        self.depths.insert(path.clone(), depth);
+        depth
+    }
+}
+
+/// Computes the maximum out-of-line nesting depth of every struct, table, union, and xunion.
+/// Types declared in other libraries are treated as having no out-of-line objects.
+pub fn max_depths(library: &Library) -> IndexMap<DeclPath, Depth> {
+    let mut calculator =
+        DepthCalculator { library, depths: IndexMap::new(), in_progress: HashSet::new() };
+    let names = library.structs.iter().map(|decl| &decl.name);
+    let names = names.chain(library.tables.iter().map(|decl| &decl.name));
+    let names = names.chain(library.unions.iter().map(|decl| &decl.name));
+    let names = names.chain(library.xunions.iter().map(|decl| &decl.name));
+    let names: Vec<&Spanned<DeclPath>> = names.collect();
+    names.into_iter().map(|name| (name.inner.clone(), calculator.decl_depth(name))).collect()
+}
+
+/// Reports infinite-size cycles as errors, and declarations whose values can nest deeper than
+/// `depth_limit` as warnings.
+pub fn check(library: &Library, depth_limit: u32) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> =
+        find_infinite_cycles(library).iter().map(Cycle::to_diagnostic).collect();
+    let depths = max_depths(library);
+    for name in library.decl_names() {
+        let message = match depths.get(&**name) {
+            Some(Depth::Unbounded) => format!(
+                "`{}` is recursive and values may exceed the decoder's depth limit of {}",
+                **name, depth_limit
+            ),
+            Some(Depth::Bounded(depth)) if *depth > depth_limit => format!(
+                "`{}` can nest {} out-of-line objects, exceeding the decoder's depth limit of {}",
+                **name, depth, depth_limit
+            ),
+            _ => continue,
+        };
+        diagnostics.push(Diagnostic::warning("max-depth", message, name.span));
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::fixtures};
+
+    fn depth(depths: &IndexMap<DeclPath, Depth>, library: &Library, decl_name: &str) -> Depth {
+        let path =
+            DeclPath { library_name: library.name.to_string(), decl_name: decl_name.to_string() };
+        depths[&path]
+    }
+
+    #[test]
+    fn finds_inline_cycle_through_array() {
+        let library = fixtures::library("cycles");
+        let cycles = find_infinite_cycles(&library);
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].to_string(), "Bad.other -> Bad2.back -> Bad");
+    }
+
+    #[test]
+    fn vectors_and_nullable_members_break_cycles() {
+        let library = fixtures::library("example");
+        assert!(find_infinite_cycles(&library).is_empty());
+        let library = fixtures::library("cycles");
+        let depths = max_depths(&library);
+        assert_eq!(depth(&depths, &library, "Node"), Depth::Unbounded);
+        assert_eq!(depth(&depths, &library, "Linked"), Depth::Unbounded);
+    }
+
+    #[test]
+    fn computes_bounded_depths() {
+        let library = fixtures::library("example");
+        let depths = max_depths(&library);
+        assert_eq!(depth(&depths, &library, "Point"), Depth::Bounded(0));
+        // The envelope vector, then the envelope body, then the string.
+        assert_eq!(depth(&depths, &library, "Settings"), Depth::Bounded(3));
+        assert_eq!(depth(&depths, &library, "Value"), Depth::Bounded(1));
+    }
+
+    #[test]
+    fn reports_cycles_and_depths() {
+        let library = fixtures::library("cycles");
+        let codes: Vec<_> = check(&library, DEFAULT_DEPTH_LIMIT)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.severity))
+            .collect();
+        assert_eq!(codes[0], ("infinite-size-cycle".to_string(), crate::Severity::Error));
+        assert_eq!(codes.len(), 5);
+        assert!(codes[1..].iter().all(|(code, _)| code == "max-depth"));
+    }
+}
@@ -0,0 +1,394 @@
+//! The dependency graph between libraries, built both from their declared
+//! `library_dependencies` and from the references their declarations actually make, with
+//! detection of dependency cycles and undeclared dependencies.
+
+use {
+    crate::{DeclPath, Diagnostic, Library, Span},
+    serde::Serialize,
+    std::{
+        collections::{HashMap, VecDeque},
+        fmt::Write,
+    },
+};
+
+/// A dependency of one library on another.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct DependencyEdge {
+    pub from: String,
+    pub to: String,
+    /// Whether `to` is listed in the `library_dependencies` of `from`.
+    pub declared: bool,
+    /// The declarations of `to` that declarations of `from` refer to, in order of first
+    /// reference. Empty if the dependency is declared but unused.
+    pub references: Vec<DeclPath>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DependencyGraph {
+    /// The libraries given, in order, followed by any other libraries they depend on.
+    pub libraries: Vec<String>,
+    pub edges: Vec<DependencyEdge>,
+    #[serde(skip)]
+    spans: Vec<Option<Span>>,
+}
+
+impl DependencyGraph {
+    pub fn new(libraries: &[Library]) -> Self {
+        let mut graph = DependencyGraph::default();
+        for library in libraries {
+            graph.libraries.push(library.name.inner.clone());
+            graph.spans.push(library.name.span);
+        }
+        for library in libraries {
+            let from = &*library.name;
+            for dep in &library.library_dependencies {
+                graph.edge(from, &dep.name).declared = true;
+            }
+            for name in library.decl_names() {
+                let decl = match library.lookup(name) {
+                    Some(decl) => decl,
+                    None => continue,
+                };
+                for reference in decl.references() {
+                    if reference.library_name != *from {
+                        let edge = graph.edge(from, &reference.library_name);
+                        if !edge.references.contains(&reference) {
+                            edge.references.push(reference);
+                        }
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    /// Returns the edge from `from` to `to`, adding it and any missing library if needed.
+    fn edge(&mut self, from: &str, to: &str) -> &mut DependencyEdge {
+        if !self.libraries.iter().any(|library| library == to) {
+            self.libraries.push(to.to_string());
+            self.spans.push(None);
+        }
+        let index = match self.edges.iter().position(|edge| edge.from == from && edge.to == to) {
+            Some(index) => index,
+            None => {
+                self.edges.push(DependencyEdge {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    declared: false,
+                    references: Vec::new(),
+                });
+                self.edges.len() - 1
+            }
+        };
+        &mut self.edges[index]
+    }
+
+    fn span(&self, library: &str) -> Option<Span> {
+        let index = self.libraries.iter().position(|name| name == library)?;
+        self.spans[index]
+    }
+
+    /// Returns the edges for references to libraries that aren't declared as dependencies.
+    pub fn undeclared(&self) -> impl Iterator<Item = &DependencyEdge> {
+        self.edges.iter().filter(|edge| !edge.declared)
+    }
+
+    /// Returns the successors of each library, by index into `libraries`.
+    fn adjacency(&self) -> Vec<Vec<usize>> {
+        let index: HashMap<&str, usize> =
+            self.libraries.iter().enumerate().map(|(i, name)| (name.as_str(), i)).collect();
+        let mut adjacency = vec![Vec::new(); self.libraries.len()];
+        for edge in &self.edges {
+            adjacency[index[edge.from.as_str()]].push(index[edge.to.as_str()]);
+        }
+        adjacency
+    }
+
+    /// Returns one cycle for each group of libraries that depend on each other, as the list of
+    /// libraries along the cycle starting with the earliest of the group in `libraries`. The
+    /// cycle is a shortest one through that library; others in the same group aren't listed.
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        let adjacency = self.adjacency();
+        let mut cycles = Vec::new();
+        for component in strongly_connected_components(&adjacency) {
+            let root = component[0];
+            if component.len() == 1 && !adjacency[root].contains(&root) {
+                continue;
+            }
+            let cycle = shortest_cycle(&adjacency, &component, root);
+            cycles.push((root, cycle.into_iter().map(|i| self.libraries[i].clone()).collect()));
+        }
+        cycles.sort_by_key(|(root, _)| *root);
+        cycles.into_iter().map(|(_, cycle)| cycle).collect()
+    }
+
+    /// Writes the graph in Graphviz DOT format. Undeclared dependencies are drawn in red and
+    /// declared but unused ones are dashed; edges are labeled with their number of references.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph dependencies {\n");
+        writeln!(out, "    node [shape=box];").unwrap();
+        for library in &self.libraries {
+            writeln!(out, "    {:?};", library).unwrap();
+        }
+        for edge in &self.edges {
+            let mut attributes = vec![format!("label=\"{}\"", edge.references.len())];
+            if !edge.declared {
+                attributes.push("color=red".to_string());
+            } else if edge.references.is_empty() {
+                attributes.push("style=dashed".to_string());
+            }
+            writeln!(out, "    {:?} -> {:?} [{}];", edge.from, edge.to, attributes.join(", "))
+                .unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Returns the graph as a JSON object with `libraries`, `edges`, and `cycles`.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "libraries": self.libraries,
+            "edges": self.edges,
+            "cycles": self.cycles(),
+        })
+    }
+}
+
+/// Returns the strongly connected components of the graph, using Tarjan's algorithm. Each
+/// component's vertices are sorted, so the earliest comes first.
+fn strongly_connected_components(adjacency: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    struct Tarjan<'a> {
+        adjacency: &'a [Vec<usize>],
+        next_index: usize,
+        index: Vec<Option<usize>>,
+        low_link: Vec<usize>,
+        stack: Vec<usize>,
+        on_stack: Vec<bool>,
+        components: Vec<Vec<usize>>,
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, vertex: usize) {
+            self.index[vertex] = Some(self.next_index);
+            self.low_link[vertex] = self.next_index;
+            self.next_index += 1;
+            self.stack.push(vertex);
+            self.on_stack[vertex] = true;
+            for &next in &self.adjacency[vertex] {
+                match self.index[next] {
+                    None => {
+                        self.visit(next);
+                        self.low_link[vertex] = self.low_link[vertex].min(self.low_link[next]);
+                    }
+                    Some(index) if self.on_stack[next] => {
+                        self.low_link[vertex] = self.low_link[vertex].min(index);
+                    }
+                    Some(_) => {}
+                }
+            }
+            if Some(self.low_link[vertex]) == self.index[vertex] {
+                let mut component = Vec::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack[member] = false;
+                    component.push(member);
+                    if member == vertex {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                self.components.push(component);
+            }
+        }
+    }
+
+    let count = adjacency.len();
+    let mut tarjan = Tarjan {
+        adjacency,
+        next_index: 0,
+        index: vec![None; count],
+        low_link: vec![0; count],
+        stack: Vec::new(),
+        on_stack: vec![false; count],
+        components: Vec::new(),
+    };
+    for vertex in 0..count {
+        if tarjan.index[vertex].is_none() {
+            tarjan.visit(vertex);
+        }
+    }
+    tarjan.components
+}
+
+/// Returns a shortest cycle through `root` that stays within `component`, found by a
+/// breadth-first search back to `root`.
+fn shortest_cycle(adjacency: &[Vec<usize>], component: &[usize], root: usize) -> Vec<usize> {
+    let mut parent: HashMap<usize, usize> = HashMap::new();
+    let mut queue = VecDeque::from(vec![root]);
+    while let Some(vertex) = queue.pop_front() {
+        for &next in &adjacency[vertex] {
+            if next == root {
+                let mut cycle = vec![vertex];
+                while let Some(&previous) = parent.get(cycle.last().unwrap()) {
+                    cycle.push(previous);
+                }
+                cycle.reverse();
+                return cycle;
+            }
+            if component.binary_search(&next).is_ok() && !parent.contains_key(&next) {
+                parent.insert(next, vertex);
+                queue.push_back(next);
+            }
+        }
+    }
+    unreachable!("every vertex of a strongly connected component lies on a cycle")
+}
+
+/// Reports dependency cycles and references to libraries that aren't declared as dependencies.
+pub fn check(libraries: &[Library]) -> Vec<Diagnostic> {
+    let graph = DependencyGraph::new(libraries);
+    let mut diagnostics: Vec<Diagnostic> = graph
+        .cycles()
+        .into_iter()
+        .map(|cycle| {
+            let mut path = cycle.join(" -> ");
+            write!(path, " -> {}", cycle[0]).unwrap();
+            Diagnostic::error(
+                "dependency-cycle",
+                format!("libraries depend on each other in a cycle: {}", path),
+                graph.span(&cycle[0]),
+            )
+        })
+        .collect();
+    for edge in graph.undeclared() {
+        let mut diagnostic = Diagnostic::error(
+            "undeclared-dependency",
+            format!(
+                "library `{}` refers to `{}` without declaring it as a dependency",
+                edge.from, edge.to
+            ),
+            graph.span(&edge.from),
+        );
+        for reference in &edge.references {
+            diagnostic = diagnostic.with_note(format!("refers to `{}`", reference), None);
+        }
+        diagnostics.push(diagnostic);
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::fixtures::{identifier, library_json, struct_json},
+        serde_json::json,
+    };
+
+    /// Builds library `name` declaring `deps` as dependencies, with a struct referring to each
+    /// of `references`.
+    fn library(name: &str, deps: &[&str], references: &[&str]) -> Library {
+        let structs: Vec<_> = references
+            .iter()
+            .enumerate()
+            .map(|(index, path)| {
+                struct_json(&format!("{}/S{}", name, index), &[("member", identifier(path))])
+            })
+            .collect();
+        let deps: Vec<_> =
+            deps.iter().map(|dep| json!({"name": dep, "declarations": {}})).collect();
+        library_json(json!({
+            "name": name,
+            "struct_declarations": structs,
+            "library_dependencies": deps,
+        }))
+    }
+
+    #[test]
+    fn reports_one_cycle_per_component() {
+        let libraries = [
+            library("a", &["b"], &[]),
+            library("b", &["a", "c"], &[]),
+            library("c", &["a"], &[]),
+            library("d", &["e"], &[]),
+            library("e", &["d"], &[]),
+            library("f", &["f"], &[]),
+            library("g", &["a"], &[]),
+        ];
+        let graph = DependencyGraph::new(&libraries);
+        assert_eq!(graph.cycles(), vec![vec!["a", "b"], vec!["d", "e"], vec!["f"]]);
+        let diagnostics = check(&libraries);
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(
+            diagnostics[0].message,
+            "libraries depend on each other in a cycle: a -> b -> a"
+        );
+    }
+
+    #[test]
+    fn finds_cycles_in_dense_graphs() {
+        let names: Vec<String> = (0..16).map(|index| format!("lib{}", index)).collect();
+        let libraries: Vec<Library> = names
+            .iter()
+            .map(|name| {
+                let deps: Vec<&str> =
+                    names.iter().filter(|dep| *dep != name).map(String::as_str).collect();
+                library(name, &deps, &[])
+            })
+            .collect();
+        assert_eq!(DependencyGraph::new(&libraries).cycles(), vec![vec!["lib0", "lib1"]]);
+    }
+
+    #[test]
+    fn reports_undeclared_dependencies() {
+        let libraries = [
+            library("a", &["b"], &["b/Used", "c/First", "c/Second", "c/First"]),
+            library("b", &[], &[]),
+        ];
+        let graph = DependencyGraph::new(&libraries);
+        assert_eq!(graph.libraries, vec!["a", "b", "c"]);
+        let undeclared: Vec<_> = graph.undeclared().collect();
+        assert_eq!(undeclared.len(), 1);
+        assert_eq!((&*undeclared[0].from, &*undeclared[0].to), ("a", "c"));
+        let references: Vec<_> = undeclared[0].references.iter().map(ToString::to_string).collect();
+        assert_eq!(references, vec!["c/First", "c/Second"]);
+
+        let diagnostics = check(&libraries);
+        let codes: Vec<_> = diagnostics.iter().map(|diagnostic| &*diagnostic.code).collect();
+        assert_eq!(codes, vec!["undeclared-dependency"]);
+    }
+
+    #[test]
+    fn writes_dot() {
+        let libraries = [library("a", &["b", "c"], &["b/Used"]), library("b", &[], &["d/Missing"])];
+        assert_eq!(
+            DependencyGraph::new(&libraries).to_dot(),
+            r#"digraph dependencies {
+    node [shape=box];
+    "a";
+    "b";
+    "c";
+    "d";
+    "a" -> "b" [label="1"];
+    "a" -> "c" [label="0", style=dashed];
+    "b" -> "d" [label="1", color=red];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn writes_json() {
+        let libraries = [library("a", &["b"], &["b/Used"]), library("b", &[], &["a/Back"])];
+        assert_eq!(
+            DependencyGraph::new(&libraries).to_json(),
+            json!({
+                "libraries": ["a", "b"],
+                "edges": [
+                    {"from": "a", "to": "b", "declared": true, "references": ["b/Used"]},
+                    {"from": "b", "to": "a", "declared": false, "references": ["a/Back"]},
+                ],
+                "cycles": [["a", "b"]],
+            })
+        );
+    }
+}
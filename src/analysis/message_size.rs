@@ -0,0 +1,561 @@
+//! Checks that method requests and responses fit within the limits of a Zircon channel.
+
+use {
+    crate::{
+        layout::{self, align_to_u64, table_fields, type_shape, Bound, OUT_OF_LINE_ALIGNMENT},
+        visit::{Decl, DeclLookup, Direction, WithDeps},
+        DeclPath, Diagnostic, Library, Method, MethodReqRes, Span, Spanned, Type, TypeKind,
+    },
+    std::collections::{HashMap, HashSet},
+};
+
+/// The maximum number of bytes in a channel message, `ZX_CHANNEL_MAX_MSG_BYTES`.
+pub const CHANNEL_MAX_MSG_BYTES: u64 = 65536;
+/// The maximum number of handles in a channel message, `ZX_CHANNEL_MAX_MSG_HANDLES`.
+pub const CHANNEL_MAX_MSG_HANDLES: u64 = 64;
+
+/// The out-of-line bytes and handles of a value, and the member paths contributing the most.
+#[derive(Debug, Clone)]
+struct Cost {
+    bytes: Bound,
+    handles: Bound,
+    byte_chain: Vec<String>,
+    handle_chain: Vec<String>,
+}
+
+impl Cost {
+    const ZERO: Cost = Cost {
+        bytes: Bound::ZERO,
+        handles: Bound::ZERO,
+        byte_chain: Vec::new(),
+        handle_chain: Vec::new(),
+    };
+
+    fn handle() -> Cost {
+        Cost { handles: Bound::Bounded(1), ..Cost::ZERO }
+    }
+
+    fn prefixed(mut self, segment: &str) -> Cost {
+        self.byte_chain.insert(0, segment.to_string());
+        self.handle_chain.insert(0, segment.to_string());
+        self
+    }
+
+    /// Combines the costs of values that are all present, like the members of a struct.
+    fn sum(costs: impl IntoIterator<Item = Cost>) -> Cost {
+        let mut total = Cost::ZERO;
+        let mut max_bytes = Bound::ZERO;
+        let mut max_handles = Bound::ZERO;
+        for cost in costs {
+            total.bytes = total.bytes + cost.bytes;
+            total.handles = total.handles + cost.handles;
+            if cost.bytes > max_bytes {
+                max_bytes = cost.bytes;
+                total.byte_chain = cost.byte_chain;
+            }
+            if cost.handles > max_handles {
+                max_handles = cost.handles;
+                total.handle_chain = cost.handle_chain;
+            }
+        }
+        total
+    }
+
+    /// Combines the costs of alternatives, like the members of a union.
+    fn max(costs: impl IntoIterator<Item = Cost>) -> Cost {
+        let mut total = Cost::ZERO;
+        for cost in costs {
+            if cost.bytes > total.bytes {
+                total.bytes = cost.bytes;
+                total.byte_chain = cost.byte_chain;
+            }
+            if cost.handles > total.handles {
+                total.handles = cost.handles;
+                total.handle_chain = cost.handle_chain;
+            }
+        }
+        total
+    }
+}
+
+struct CostCalculator<'a, L: ?Sized> {
+    lookup: &'a L,
+    memo: HashMap<DeclPath, Cost>,
+    in_progress: HashSet<DeclPath>,
+}
+
+impl<'a, L: DeclLookup + ?Sized> CostCalculator<'a, L> {
+    fn new(lookup: &'a L) -> Self {
+        CostCalculator { lookup, memo: HashMap::new(), in_progress: HashSet::new() }
+    }
+
+    fn inline_size(&self, r#type: &Type) -> u64 {
+        type_shape(self.lookup, r#type).map_or(0, |shape| shape.inline_size.into())
+    }
+
+    /// The cost of storing a value of `r#type` out-of-line, as in a vector or envelope.
+    fn boxed(&mut self, r#type: &Type) -> Cost {
+        let mut cost = self.type_cost(r#type);
+        let inline = align_to_u64(self.inline_size(r#type), OUT_OF_LINE_ALIGNMENT.into());
+        cost.bytes = cost.bytes + Bound::Bounded(inline);
+        cost
+    }
+
+    fn type_cost(&mut self, r#type: &Type) -> Cost {
+        match &*r#type.kind {
+            TypeKind::Handle { .. } | TypeKind::Request { .. } => Cost::handle(),
+            TypeKind::String { maybe_element_count, .. } => {
+                let bytes = match maybe_element_count.as_ref().and_then(|count| count.as_u64()) {
+                    Some(count) => Bound::Bounded(count).aligned(OUT_OF_LINE_ALIGNMENT.into()),
+                    None => Bound::Unbounded,
+                };
+                Cost { bytes, ..Cost::ZERO }
+            }
+            TypeKind::Vector { element_type, maybe_element_count, .. } => {
+                let element = self.type_cost(element_type);
+                let count = maybe_element_count.as_ref().and_then(|count| count.as_u64());
+                let inline = self.inline_size(element_type);
+                let (bytes, handles) = match count {
+                    Some(count) => (
+                        Bound::Bounded(inline).times(count).aligned(OUT_OF_LINE_ALIGNMENT.into())
+                            + element.bytes.times(count),
+                        element.handles.times(count),
+                    ),
+                    None if element.handles == Bound::ZERO => (Bound::Unbounded, Bound::ZERO),
+                    None => (Bound::Unbounded, Bound::Unbounded),
+                };
+                Cost { bytes, handles, ..element }.prefixed("[]")
+            }
+            TypeKind::Array { element_type, element_count, .. } => {
+                let element = self.type_cost(element_type);
+                let count = element_count.as_ref().and_then(|count| count.as_u64()).unwrap_or(0);
+                Cost {
+                    bytes: element.bytes.times(count),
+                    handles: element.handles.times(count),
+                    ..element
+                }
+                .prefixed("[]")
+            }
+            TypeKind::Identifier { identifier, .. } => {
+                let mut cost = self.decl_cost(identifier);
+                if *r#type.nullable {
+                    if let Some(Decl::Struct(_)) | Some(Decl::Union(_)) =
+                        self.lookup.lookup_decl(identifier)
+                    {
+                        let inline = align_to_u64(
+                            self.decl_inline_size(identifier).into(),
+                            OUT_OF_LINE_ALIGNMENT.into(),
+                        );
+                        cost.bytes = cost.bytes + Bound::Bounded(inline);
+                    }
+                }
+                cost
+            }
+            TypeKind::Primitive { .. }
+            | TypeKind::UnresolvedRequest { .. }
+            | TypeKind::UnresolvedIdentifier { .. } => Cost::ZERO,
+        }
+    }
+
+    fn decl_inline_size(&self, path: &DeclPath) -> u32 {
+        layout::decl_shape(self.lookup, path, false).map_or(0, |shape| shape.inline_size)
+    }
+
+    fn decl_cost(&mut self, path: &DeclPath) -> Cost {
+        if let Some(cost) = self.memo.get(path) {
+            return cost.clone();
+        }
+        if !self.in_progress.insert(path.clone()) {
+            // Recursion is only possible through out-of-line objects, which can repeat forever.
+            let handles = if may_contain_handles(self.lookup, path, &mut HashSet::new()) {
+                Bound::Unbounded
+            } else {
+                Bound::ZERO
+            };
+            return Cost { bytes: Bound::Unbounded, handles, ..Cost::ZERO };
+        }
+        let cost = match self.lookup.lookup_decl(path) {
+            Some(Decl::Protocol(_)) => Cost::handle(),
+            Some(Decl::Struct(decl)) => Cost::sum(
+                decl.members
+                    .iter()
+                    .map(|member| self.type_cost(&member.r#type).prefixed(&member.name))
+                    .collect::<Vec<_>>(),
+            ),
+            Some(Decl::Union(decl)) => Cost::max(
+                decl.members
+                    .iter()
+                    .map(|member| self.type_cost(&member.r#type).prefixed(&member.name))
+                    .collect::<Vec<_>>(),
+            ),
+            Some(Decl::XUnion(decl)) => Cost::max(
+                decl.members
+                    .iter()
+                    .map(|member| self.boxed(&member.r#type).prefixed(&member.name))
+                    .collect::<Vec<_>>(),
+            ),
+            Some(Decl::Table(decl)) => {
+                let fields = table_fields(decl);
+                let max_ordinal = fields.iter().map(|(ordinal, _, _)| *ordinal).max().unwrap_or(0);
+                let envelopes = Cost {
+                    bytes: Bound::Bounded(max_ordinal * u64::from(layout::ENVELOPE_SIZE)),
+                    ..Cost::ZERO
+                };
+                let members: Vec<Cost> = fields
+                    .iter()
+                    .map(|(_, name, r#type)| self.boxed(r#type).prefixed(name))
+                    .collect();
+                let mut cost = Cost::sum(members);
+                cost.bytes = cost.bytes + envelopes.bytes;
+                cost
+            }
+            _ => Cost::ZERO,
+        };
+        self.in_progress.remove(path);
+        self.memo.insert(path.clone(), cost.clone());
+        cost
+    }
+}
+
+fn may_contain_handles<L: DeclLookup + ?Sized>(
+    lookup: &L,
+    path: &DeclPath,
+    visited: &mut HashSet<DeclPath>,
+) -> bool {
+    fn type_has_handles<L: DeclLookup + ?Sized>(
+        lookup: &L,
+        r#type: &Type,
+        visited: &mut HashSet<DeclPath>,
+    ) -> bool {
+        let mut found = false;
+        r#type.walk(&mut |nested| match &*nested.kind {
+            TypeKind::Handle { .. } | TypeKind::Request { .. } => found = true,
+            TypeKind::Identifier { identifier, .. } => {
+                found = found || may_contain_handles(lookup, identifier, visited)
+            }
+            _ => {}
+        });
+        found
+    }
+
+    if !visited.insert(path.clone()) {
+        return false;
+    }
+    match lookup.lookup_decl(path) {
+        Some(Decl::Protocol(_)) => true,
+        Some(Decl::Struct(decl)) => {
+            decl.members.iter().any(|member| type_has_handles(lookup, &member.r#type, visited))
+        }
+        Some(Decl::Union(decl)) => {
+            decl.members.iter().any(|member| type_has_handles(lookup, &member.r#type, visited))
+        }
+        Some(Decl::XUnion(decl)) => {
+            decl.members.iter().any(|member| type_has_handles(lookup, &member.r#type, visited))
+        }
+        Some(Decl::Table(decl)) => table_fields(decl)
+            .into_iter()
+            .any(|(_, _, r#type)| type_has_handles(lookup, r#type, visited)),
+        _ => false,
+    }
+}
+
+/// Formats a member path such as `["a", "[]", "b"]` as `a[].b`.
+fn format_chain(chain: &[String]) -> String {
+    let mut out = String::new();
+    for segment in chain {
+        if !out.is_empty() && segment != "[]" {
+            out.push('.');
+        }
+        out.push_str(segment);
+    }
+    out
+}
+
+/// The largest request or response a method can send.
+#[derive(Debug, Clone)]
+pub struct MessageBudget<'a> {
+    pub protocol: &'a Spanned<DeclPath>,
+    pub method: &'a Spanned<Method>,
+    pub direction: Direction,
+    /// The bytes in the message, including the header.
+    pub bytes: Bound,
+    pub handles: Bound,
+    /// The path of the parameter and members contributing the most bytes, e.g. `items[].name`.
+    pub byte_chain: String,
+    /// The path of the parameter and members contributing the most handles.
+    pub handle_chain: String,
+    /// The span of the parameter at the start of `byte_chain`.
+    byte_span: Option<Span>,
+    /// The span of the parameter at the start of `handle_chain`.
+    handle_span: Option<Span>,
+    /// Parameters whose bounds in the IR differ from the ones computed from their types.
+    mismatches: Vec<Mismatch>,
+}
+
+/// A parameter whose bound in the IR differs from the one computed from its type.
+#[derive(Debug, Clone)]
+struct Mismatch {
+    unit: &'static str,
+    note: String,
+    span: Option<Span>,
+}
+
+impl Mismatch {
+    fn new(
+        unit: &'static str,
+        param: &str,
+        ir: Bound,
+        computed: Bound,
+        span: Option<Span>,
+    ) -> Self {
+        let note = format!(
+            "the IR bounds the {} of `{}` at {}, but its type gives {}",
+            unit, param, ir, computed
+        );
+        Mismatch { unit, note, span }
+    }
+}
+
+/// Converts a bound from the IR. The compiler saturates at `u32::MAX`, the same value that marks
+/// unbounded sizes, so a larger `computed` bound is kept rather than treated as unbounded.
+fn bound_from_ir(value: u32, computed: Bound) -> Bound {
+    match computed {
+        Bound::Bounded(computed) if value == u32::MAX && computed >= u64::from(u32::MAX) => {
+            Bound::Bounded(computed)
+        }
+        _ => Bound::from_ir(value),
+    }
+}
+
+fn message_budget<'a, L: DeclLookup + ?Sized>(
+    calculator: &mut CostCalculator<'_, L>,
+    protocol: &'a Spanned<DeclPath>,
+    method: &'a Spanned<Method>,
+    direction: Direction,
+    message: &'a MethodReqRes,
+) -> MessageBudget<'a> {
+    let inline = match *message.size {
+        Some(size) => u64::from(size),
+        None => message
+            .parameters
+            .iter()
+            .map(|param| {
+                let offset = param.offset.unwrap_or(layout::HEADER_SIZE);
+                u64::from(offset).saturating_add(calculator.inline_size(&param.r#type))
+            })
+            .max()
+            .map_or(u64::from(layout::HEADER_SIZE), |end| align_to_u64(end, 8)),
+    };
+    let mut mismatches = Vec::new();
+    let costs: Vec<(Cost, Option<Span>)> = message
+        .parameters
+        .iter()
+        .map(|param| {
+            let mut cost = calculator.type_cost(&param.r#type).prefixed(&param.name);
+            let span = param.name.span.or(param.r#type.span);
+            // Prefer the sizes computed by the compiler when they're available. The member path
+            // only explains our own sizes, so it stops at the parameter when they disagree.
+            if let Some(max_out_of_line) = *param.max_out_of_line {
+                let bytes = bound_from_ir(max_out_of_line, cost.bytes);
+                if bytes != cost.bytes {
+                    mismatches.push(Mismatch::new("bytes", &param.name, bytes, cost.bytes, span));
+                    cost.byte_chain.truncate(1);
+                    cost.bytes = bytes;
+                }
+            }
+            if let Some(max_handles) = *param.max_handles {
+                let handles = bound_from_ir(max_handles, cost.handles);
+                if handles != cost.handles {
+                    mismatches.push(Mismatch::new(
+                        "handles",
+                        &param.name,
+                        handles,
+                        cost.handles,
+                        span,
+                    ));
+                    cost.handle_chain.truncate(1);
+                    cost.handles = handles;
+                }
+            }
+            (cost, span)
+        })
+        .collect();
+    let byte_span = costs.iter().max_by_key(|(cost, _)| cost.bytes).and_then(|(_, span)| *span);
+    let handle_span = costs.iter().max_by_key(|(cost, _)| cost.handles).and_then(|(_, span)| *span);
+    let total = Cost::sum(costs.into_iter().map(|(cost, _)| cost));
+    MessageBudget {
+        protocol,
+        method,
+        direction,
+        bytes: total.bytes + Bound::Bounded(inline),
+        handles: total.handles,
+        byte_chain: format_chain(&total.byte_chain),
+        handle_chain: format_chain(&total.handle_chain),
+        byte_span,
+        handle_span,
+        mismatches,
+    }
+}
+
+/// Computes the largest request and response of every method in `library`. Types from other
+/// libraries are resolved through `deps` when they're not in `library`.
+pub fn message_budgets<'a>(library: &'a Library, deps: &[Library]) -> Vec<MessageBudget<'a>> {
+    let lookup = WithDeps { library, deps };
+    let mut calculator = CostCalculator::new(&lookup);
+    let mut budgets = Vec::new();
+    for protocol in &library.protocols {
+        for method in &protocol.methods {
+            for (direction, message) in method.messages() {
+                budgets.push(message_budget(
+                    &mut calculator,
+                    &protocol.name,
+                    method,
+                    direction,
+                    message,
+                ));
+            }
+        }
+    }
+    budgets
+}
+
+/// Reports requests and responses that can exceed the channel byte or handle limits. Messages
+/// that are only potentially too large, because of unbounded vectors or strings, are reported
+/// as warnings.
+pub fn check(library: &Library, deps: &[Library]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for budget in message_budgets(library, deps) {
+        let name = format!("{}.{}", budget.protocol.decl_name, *budget.method.name);
+        let checks = [
+            ("bytes", budget.bytes, CHANNEL_MAX_MSG_BYTES, &budget.byte_chain, budget.byte_span),
+            (
+                "handles",
+                budget.handles,
+                CHANNEL_MAX_MSG_HANDLES,
+                &budget.handle_chain,
+                budget.handle_span,
+            ),
+        ];
+        for (unit, bound, limit, chain, span) in checks.iter() {
+            let diagnostic = match bound {
+                Bound::Unbounded => Diagnostic::warning(
+                    format!("unbounded-message-{}", unit),
+                    format!(
+                        "the {} of `{}` has an unbounded number of {}, but channels allow at \
+                         most {}",
+                        budget.direction, name, unit, limit
+                    ),
+                    budget.method.name.span,
+                ),
+                Bound::Bounded(value) if value > limit => Diagnostic::error(
+                    format!("message-{}-exceed-limit", unit),
+                    format!(
+                        "the {} of `{}` can have up to {} {}, but channels allow at most {}",
+                        budget.direction, name, value, unit, limit
+                    ),
+                    budget.method.name.span,
+                ),
+                _ => continue,
+            };
+            let mut diagnostic = diagnostic.with_note(format!("mostly from `{}`", chain), *span);
+            for mismatch in budget.mismatches.iter().filter(|mismatch| mismatch.unit == *unit) {
+                diagnostic = diagnostic.with_note(mismatch.note.clone(), mismatch.span);
+            }
+            diagnostics.push(diagnostic);
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::fixtures, serde_json::json};
+
+    /// Returns the example library with the type and IR bounds of the `Echo` response replaced.
+    fn with_response(r#type: serde_json::Value, max_out_of_line: Option<u32>) -> Library {
+        let mut library = fixtures::library("example");
+        let response = library.protocols[0].methods[0].response.as_mut().unwrap();
+        let param = &mut response.parameters[0];
+        param.r#type.inner = fixtures::r#type(r#type);
+        *param.max_out_of_line = max_out_of_line;
+        library
+    }
+
+    fn byte_diagnostics(library: &Library) -> Vec<(String, String, Vec<String>)> {
+        check(library, &[])
+            .into_iter()
+            .filter(|diagnostic| diagnostic.code.contains("bytes"))
+            .map(|diagnostic| {
+                let notes = diagnostic.notes.iter().map(|note| note.inner.clone()).collect();
+                (diagnostic.code, diagnostic.message, notes)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn computes_budgets() {
+        let library = fixtures::library("example");
+        let budgets: Vec<_> = message_budgets(&library, &[])
+            .into_iter()
+            .map(|budget| (budget.bytes, budget.handles, budget.byte_chain))
+            .collect();
+        assert_eq!(
+            budgets,
+            [
+                (Bound::Unbounded, Bound::ZERO, "value".to_string()),
+                (Bound::Bounded(32 + 104), Bound::ZERO, "response".to_string()),
+                (Bound::Bounded(24), Bound::Bounded(1), "".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn costs_huge_strings_and_vectors_without_truncating() {
+        let library = with_response(
+            json!({"kind": "string", "maybe_element_count": 4294967295u32, "nullable": false}),
+            None,
+        );
+        assert_eq!(
+            byte_diagnostics(&library)[1].1,
+            "the response of `EchoProtocol.Echo` can have up to 4294967328 bytes, but channels \
+             allow at most 65536"
+        );
+        let r#type = json!({
+            "kind": "vector",
+            "element_type": {"kind": "primitive", "subtype": "uint64"},
+            "maybe_element_count": 536870912,
+            "nullable": false,
+        });
+        let library = with_response(r#type, None);
+        assert_eq!(
+            byte_diagnostics(&library)[1].1,
+            "the response of `EchoProtocol.Echo` can have up to 4294967328 bytes, but channels \
+             allow at most 65536"
+        );
+    }
+
+    #[test]
+    fn saturated_ir_bound_is_not_unbounded() {
+        let r#type =
+            json!({"kind": "string", "maybe_element_count": 4294967295u32, "nullable": false});
+        let library = with_response(r#type, Some(u32::MAX));
+        let diagnostics = byte_diagnostics(&library);
+        assert_eq!(diagnostics[1].0, "message-bytes-exceed-limit");
+        assert_eq!(diagnostics[1].2, ["mostly from `response`"]);
+    }
+
+    #[test]
+    fn reports_ir_bounds_that_differ_from_types() {
+        let r#type = json!({"kind": "string", "maybe_element_count": 100, "nullable": true});
+        let library = with_response(r#type, Some(70000));
+        let diagnostics = byte_diagnostics(&library);
+        assert_eq!(diagnostics[1].0, "message-bytes-exceed-limit");
+        assert_eq!(
+            diagnostics[1].2,
+            [
+                "mostly from `response`",
+                "the IR bounds the bytes of `response` at 70000, but its type gives 104",
+            ]
+        );
+    }
+}
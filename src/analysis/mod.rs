@@ -0,0 +1,8 @@
+//! Analyses over libraries that report problems as `Diagnostic`s.
+
+pub mod capability;
+pub mod cycles;
+pub mod dep_graph;
+pub mod message_size;
+pub mod padding;
+pub mod unused;
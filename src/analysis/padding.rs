@@ -0,0 +1,267 @@
+//! Measurement of the padding in structs and method parameter lists, with suggestions for member
+//! orders that need less of it.
+
+use {
+    crate::{
+        layout::{align_to, type_shape, TypeShape, HEADER_SIZE},
+        visit::{DeclLookup, WithDeps},
+        Diagnostic, Library, Span, Spanned, Type,
+    },
+    serde::Serialize,
+};
+
+/// A run of padding bytes within a layout.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct Gap {
+    pub offset: u32,
+    pub size: u32,
+    /// The member the padding follows, or `None` for padding before the first member.
+    pub after: Option<String>,
+}
+
+/// The padding in one struct or parameter list.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct PaddingReport {
+    /// The struct's path, or `library/Protocol.Method.request` for parameter lists.
+    pub name: String,
+    pub size: u32,
+    /// The total number of padding bytes.
+    pub padding: u32,
+    pub gaps: Vec<Gap>,
+    /// A member order that reduces `size`, if one exists.
+    pub suggested_order: Option<Vec<String>>,
+    /// The size with `suggested_order`.
+    pub suggested_size: Option<u32>,
+    #[serde(skip)]
+    pub span: Option<Span>,
+}
+
+struct Member<'a> {
+    name: &'a str,
+    offset: u32,
+    shape: TypeShape,
+}
+
+/// Returns the members' offsets and shapes, or `None` if any of them is unknown.
+fn members<'a, L: DeclLookup + ?Sized>(
+    lookup: &L,
+    members: impl Iterator<Item = (&'a Spanned<String>, Option<u32>, &'a Spanned<Type>)>,
+) -> Option<Vec<Member<'a>>> {
+    members
+        .map(|(name, offset, r#type)| {
+            Some(Member {
+                name: name.as_str(),
+                offset: offset?,
+                shape: type_shape(lookup, r#type)?,
+            })
+        })
+        .collect()
+}
+
+/// Lays out `shapes` in order starting at `start`, returning the end rounded to `alignment`.
+fn layout_size(shapes: &[&TypeShape], start: u32, alignment: u32) -> u32 {
+    let end = shapes.iter().fold(start, |offset, shape| {
+        align_to(offset, shape.alignment).saturating_add(shape.inline_size)
+    });
+    align_to(end, alignment)
+}
+
+fn report(
+    name: String,
+    span: Option<Span>,
+    mut members: Vec<Member<'_>>,
+    start: u32,
+    size: u32,
+    alignment: u32,
+) -> PaddingReport {
+    members.sort_by_key(|member| member.offset);
+    let mut gaps = Vec::new();
+    let mut offset = start;
+    let mut after = None;
+    for member in members.iter().map(Some).chain(Some(None)) {
+        let next = member.map_or(size, |member| member.offset);
+        if next > offset {
+            gaps.push(Gap { offset, size: next - offset, after: after.map(str::to_string) });
+        }
+        if let Some(member) = member {
+            offset = member.offset.saturating_add(member.shape.inline_size);
+            after = Some(member.name);
+        }
+    }
+    let padding = gaps.iter().map(|gap| gap.size).sum();
+
+    // Placing members in order of decreasing alignment minimizes the padding between them. Like
+    // an empty struct, no layout is smaller than one byte.
+    let mut sorted: Vec<&Member<'_>> = members.iter().collect();
+    sorted.sort_by_key(|member| std::cmp::Reverse(member.shape.alignment));
+    let shapes: Vec<&TypeShape> = sorted.iter().map(|member| &member.shape).collect();
+    let sorted_size = std::cmp::max(layout_size(&shapes, start, alignment), start).max(1);
+    let (suggested_order, suggested_size) = if sorted_size < size {
+        (Some(sorted.iter().map(|member| member.name.to_string()).collect()), Some(sorted_size))
+    } else {
+        (None, None)
+    };
+
+    PaddingReport { name, size, padding, gaps, suggested_order, suggested_size, span }
+}
+
+/// Measures the padding of every struct and method parameter list in `library` whose layout is
+/// known. Types from other libraries are resolved through `deps`.
+pub fn analyze(library: &Library, deps: &[Library]) -> Vec<PaddingReport> {
+    let lookup = WithDeps { library, deps };
+    let mut reports = Vec::new();
+    for decl in &library.structs {
+        let (size, alignment) = match (*decl.size, *decl.alignment) {
+            (Some(size), Some(alignment)) => (size, alignment),
+            _ => continue,
+        };
+        let found = members(
+            &lookup,
+            decl.members.iter().map(|member| (&member.name, *member.offset, &member.r#type)),
+        );
+        if let Some(found) = found {
+            reports.push(report(decl.name.to_string(), decl.name.span, found, 0, size, alignment));
+        }
+    }
+    for protocol in &library.protocols {
+        for method in &protocol.methods {
+            for (direction, message) in method.messages() {
+                let size = match *message.size {
+                    Some(size) => size,
+                    None => continue,
+                };
+                let found = members(
+                    &lookup,
+                    message
+                        .parameters
+                        .iter()
+                        .map(|param| (&param.name, *param.offset, &param.r#type)),
+                );
+                if let Some(found) = found {
+                    let name = format!("{}.{}.{}", *protocol.name, *method.name, direction);
+                    let span = message.span.or(method.name.span);
+                    reports.push(report(name, span, found, HEADER_SIZE, size, 8));
+                }
+            }
+        }
+    }
+    reports
+}
+
+/// Reports declarations and parameter lists that could be made smaller by reordering.
+pub fn check(library: &Library, deps: &[Library]) -> Vec<Diagnostic> {
+    analyze(library, deps)
+        .into_iter()
+        .filter_map(|report| {
+            let order = report.suggested_order?;
+            let suggested_size = report.suggested_size?;
+            Some(
+                Diagnostic::warning(
+                    "struct-padding",
+                    format!(
+                        "`{}` has {} bytes of padding; reordering its members would reduce its \
+                         size from {} to {} bytes",
+                        report.name, report.padding, report.size, suggested_size
+                    ),
+                    report.span,
+                )
+                .with_note(format!("suggested order: {}", order.join(", ")), None),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::fixtures::{self, library_json, struct_json},
+        serde_json::json,
+    };
+
+    fn primitive(subtype: &str) -> serde_json::Value {
+        json!({"kind": "primitive", "subtype": subtype})
+    }
+
+    fn find<'a>(reports: &'a [PaddingReport], name: &str) -> &'a PaddingReport {
+        reports.iter().find(|report| report.name == name).unwrap()
+    }
+
+    #[test]
+    fn finds_gaps_after_members() {
+        let library = fixtures::library("example");
+        let reports = analyze(&library, &[]);
+        let node = find(&reports, "test.example/Node");
+        assert_eq!(node.padding, 9);
+        assert_eq!(
+            node.gaps,
+            vec![
+                Gap { offset: 1, size: 7, after: Some("a".to_string()) },
+                Gap { offset: 26, size: 2, after: Some("b".to_string()) },
+            ]
+        );
+    }
+
+    #[test]
+    fn leaves_optimal_layouts_alone() {
+        let library = fixtures::library("example");
+        let reports = analyze(&library, &[]);
+        for name in &["test.example/Point", "test.example/EchoProtocol.Echo.request"] {
+            let report = find(&reports, name);
+            assert_eq!((&report.suggested_order, report.suggested_size), (&None, None), "{}", name);
+        }
+    }
+
+    #[test]
+    fn suggests_reordering() {
+        let library = fixtures::library("example");
+        let reports = analyze(&library, &[]);
+        let node = find(&reports, "test.example/Node");
+        assert_eq!(
+            node.suggested_order,
+            Some(vec![
+                "children".to_string(),
+                "color".to_string(),
+                "b".to_string(),
+                "a".to_string()
+            ])
+        );
+        assert_eq!(node.suggested_size, Some(24));
+        let diagnostics = check(&library, &[]);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.starts_with("`test.example/Node` has 9 bytes"));
+
+        let library = library_json(json!({
+            "name": "test.padding",
+            "struct_declarations": [struct_json(
+                "test.padding/Loose",
+                &[("a", primitive("uint8")), ("b", primitive("uint64")), ("c", primitive("uint8"))],
+            )],
+        }));
+        let reports = analyze(&library, &[]);
+        let loose = find(&reports, "test.padding/Loose");
+        assert_eq!((loose.size, loose.padding), (24, 14));
+        assert_eq!(
+            loose.suggested_order,
+            Some(vec!["b".to_string(), "a".to_string(), "c".to_string()])
+        );
+        assert_eq!(loose.suggested_size, Some(16));
+
+        let diagnostics = check(&library, &[]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, "struct-padding");
+    }
+
+    #[test]
+    fn empty_structs_keep_their_byte() {
+        let library = library_json(json!({
+            "name": "test.padding",
+            "struct_declarations": [struct_json("test.padding/Empty", &[])],
+        }));
+        let reports = analyze(&library, &[]);
+        let empty = find(&reports, "test.padding/Empty");
+        assert_eq!(empty.size, 1);
+        assert_eq!(empty.suggested_size, None);
+        assert!(check(&library, &[]).is_empty());
+    }
+}
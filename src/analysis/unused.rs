@@ -0,0 +1,178 @@
+//! Detection of declarations and library dependencies that nothing uses, across a set of
+//! libraries that depend on each other.
+
+use {
+    crate::{visit::DeclLookup, DeclPath, Diagnostic, Library, Spanned},
+    serde::Serialize,
+    std::collections::HashSet,
+};
+
+/// A library dependency none of whose declarations the library refers to.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct UnusedDependency {
+    pub library: Spanned<String>,
+    pub dependency: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UnusedReport {
+    /// Declarations that no protocol or const reaches, in library and declaration-list order.
+    pub declarations: Vec<Spanned<DeclPath>>,
+    pub dependencies: Vec<UnusedDependency>,
+}
+
+/// Returns every declaration in `libraries` reachable from a protocol or const.
+fn reachable(libraries: &[Library]) -> HashSet<DeclPath> {
+    let mut reachable = HashSet::new();
+    let mut stack = Vec::new();
+    for library in libraries {
+        let roots = library.protocols.iter().map(|decl| &decl.name);
+        for root in roots.chain(library.consts.iter().map(|decl| &decl.name)) {
+            if reachable.insert(root.inner.clone()) {
+                stack.push(root.inner.clone());
+            }
+        }
+    }
+    while let Some(path) = stack.pop() {
+        let decl = match libraries.lookup_decl(&path) {
+            Some(decl) => decl,
+            None => continue,
+        };
+        for reference in decl.references() {
+            if reachable.insert(reference.clone()) {
+                stack.push(reference);
+            }
+        }
+    }
+    reachable
+}
+
+/// Finds the declarations in `libraries` that no protocol or const uses, directly or through
+/// other declarations, and the dependencies each library lists but never refers to.
+///
+/// Libraries outside `libraries` are treated as opaque: declarations they might use are not
+/// known, so pass every library that could refer to the ones being checked.
+///
+/// A dependency counts as used if any declaration refers to it, reachable or not. The library
+/// can't be built without it until those declarations are removed, and they are reported on
+/// their own, so the dependency is only reported once nothing refers to it at all.
+pub fn analyze(libraries: &[Library]) -> UnusedReport {
+    let reachable = reachable(libraries);
+    let mut report = UnusedReport::default();
+    for library in libraries {
+        report.declarations.extend(
+            library.decl_names().into_iter().filter(|name| !reachable.contains(name)).cloned(),
+        );
+        let mut referenced = HashSet::new();
+        for name in library.decl_names() {
+            if let Some(decl) = library.lookup(name) {
+                referenced.extend(decl.references().into_iter().map(|path| path.library_name));
+            }
+        }
+        report.dependencies.extend(
+            library.library_dependencies.iter().filter(|dep| !referenced.contains(&dep.name)).map(
+                |dep| UnusedDependency {
+                    library: library.name.clone(),
+                    dependency: dep.name.clone(),
+                },
+            ),
+        );
+    }
+    report
+}
+
+/// Reports unused declarations and dependencies as warnings.
+pub fn check(libraries: &[Library]) -> Vec<Diagnostic> {
+    let report = analyze(libraries);
+    let declarations = report.declarations.iter().map(|name| {
+        Diagnostic::warning(
+            "unused-declaration",
+            format!("`{}` is not used by any protocol or const", **name),
+            name.span,
+        )
+    });
+    let dependencies = report.dependencies.iter().map(|unused| {
+        Diagnostic::warning(
+            "unused-dependency",
+            format!(
+                "library `{}` depends on `{}` but never refers to it",
+                *unused.library, unused.dependency
+            ),
+            unused.library.span,
+        )
+    });
+    declarations.chain(dependencies).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::fixtures::{self, identifier, library_json, struct_json},
+        serde_json::json,
+    };
+
+    fn names(report: &UnusedReport) -> Vec<String> {
+        report.declarations.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn finds_declarations_no_protocol_or_const_reaches() {
+        let report = analyze(&[fixtures::library("example")]);
+        assert_eq!(
+            names(&report),
+            [
+                "test.example/Color",
+                "test.example/Node",
+                "test.example/Settings",
+                "test.example/Value"
+            ]
+        );
+        assert!(report.dependencies.is_empty());
+    }
+
+    #[test]
+    fn follows_references_across_libraries() {
+        let dep = library_json(json!({
+            "name": "test.dep",
+            "struct_declarations": [
+                struct_json("test.dep/Shared", &[]),
+                struct_json("test.dep/Private", &[]),
+            ],
+        }));
+        let mut example = fixtures::library("example");
+        example.structs[0].members[0].r#type.inner =
+            fixtures::r#type(identifier("test.dep/Shared"));
+        let report = analyze(&[dep, example]);
+        assert_eq!(names(&report)[0], "test.dep/Private");
+        assert!(!names(&report).contains(&"test.dep/Shared".to_string()));
+    }
+
+    /// A library whose only declaration is unused and refers to `test.used`, which it depends
+    /// on along with `test.stale`.
+    fn orphan_library() -> Library {
+        library_json(json!({
+            "name": "test.unused",
+            "struct_declarations": [
+                struct_json("test.unused/Orphan", &[("ext", identifier("test.used/Ext"))]),
+            ],
+            "library_dependencies": [
+                {"name": "test.used", "declarations": {}},
+                {"name": "test.stale", "declarations": {}},
+            ],
+        }))
+    }
+
+    #[test]
+    fn counts_dependencies_used_by_unused_declarations() {
+        let report = analyze(&[orphan_library()]);
+        assert_eq!(names(&report), ["test.unused/Orphan"]);
+        let dependencies: Vec<_> =
+            report.dependencies.iter().map(|unused| unused.dependency.as_str()).collect();
+        assert_eq!(dependencies, ["test.stale"]);
+
+        let codes: Vec<_> =
+            check(&[orphan_library()]).iter().map(|diagnostic| diagnostic.code.clone()).collect();
+        assert_eq!(codes, ["unused-declaration", "unused-dependency"]);
+    }
+}
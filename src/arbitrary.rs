@@ -0,0 +1,363 @@
+//! Proptest strategies that generate random values conforming to IR types, in the JSON
+//! representation used by [`wire`](crate::wire).
+//!
+//! Generated values are seedable and shrinkable through proptest's usual machinery, and are
+//! always valid for their type, so they can be used to check that encoding round-trips:
+//!
+//! ```ignore
+//! let strategy = decl_strategy(&libraries[..], &path, &Options::default())?;
+//! proptest!(|(value in strategy)| {
+//!     let message = encode_value(&libraries[..], &r#type, &value)?;
+//!     prop_assert_eq!(decode_value(&libraries[..], &r#type, &message.bytes, &message.handles)?.value, value);
+//! });
+//! ```
+
+use {
+    crate::{
+        layout::table_fields,
+        visit::{Decl, DeclLookup},
+        wire::{self, ErrorKind},
+        DeclPath, MethodReqRes, PrimitiveSubtype, Type, TypeKind,
+    },
+    proptest::{
+        collection,
+        prelude::*,
+        sample::{select, subsequence},
+        strategy::{BoxedStrategy, Just, Union},
+    },
+    serde_json::{Map, Value},
+};
+
+/// Limits on the size of generated values.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Options {
+    /// The maximum number of elements in vectors and bytes in strings, in addition to any bound
+    /// declared on the type.
+    pub max_length: usize,
+    /// The maximum nesting of declarations, vectors, and nullable values. Beyond it, vectors are
+    /// empty and nullable values are absent, which keeps recursive types finite.
+    pub max_depth: u32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options { max_length: 8, max_depth: 4 }
+    }
+}
+
+type Result<T> = std::result::Result<T, wire::Error>;
+
+fn unknown(what: impl ToString) -> wire::Error {
+    wire::Error {
+        kind: ErrorKind::UnknownLayout { what: what.to_string() },
+        path: String::new(),
+        offset: None,
+    }
+}
+
+fn object(entries: Vec<(String, Value)>) -> Value {
+    Value::Object(entries.into_iter().collect::<Map<_, _>>())
+}
+
+/// Combines the strategies for named members into a strategy for an object.
+fn members(strategies: Vec<(String, BoxedStrategy<Value>)>) -> BoxedStrategy<Value> {
+    let (names, strategies): (Vec<_>, Vec<_>) = strategies.into_iter().unzip();
+    strategies.prop_map(move |values| object(names.iter().cloned().zip(values).collect())).boxed()
+}
+
+/// A strategy for an object with exactly one of the named members set.
+fn one_of(strategies: Vec<(String, BoxedStrategy<Value>)>) -> BoxedStrategy<Value> {
+    Union::new(strategies.into_iter().map(|(name, strategy)| {
+        strategy.prop_map(move |value| object(vec![(name.clone(), value)])).boxed()
+    }))
+    .boxed()
+}
+
+fn primitive(subtype: &PrimitiveSubtype) -> BoxedStrategy<Value> {
+    match subtype {
+        PrimitiveSubtype::Bool => any::<bool>().prop_map(Value::from).boxed(),
+        PrimitiveSubtype::Int8 => any::<i8>().prop_map(Value::from).boxed(),
+        PrimitiveSubtype::Int16 => any::<i16>().prop_map(Value::from).boxed(),
+        PrimitiveSubtype::Int32 => any::<i32>().prop_map(Value::from).boxed(),
+        PrimitiveSubtype::Int64 => any::<i64>().prop_map(Value::from).boxed(),
+        PrimitiveSubtype::UInt8 => any::<u8>().prop_map(Value::from).boxed(),
+        PrimitiveSubtype::UInt16 => any::<u16>().prop_map(Value::from).boxed(),
+        PrimitiveSubtype::UInt32 => any::<u32>().prop_map(Value::from).boxed(),
+        PrimitiveSubtype::UInt64 => any::<u64>().prop_map(Value::from).boxed(),
+        // JSON can't represent NaN or infinities.
+        PrimitiveSubtype::Float32 => {
+            use proptest::num::f32::{NORMAL, SUBNORMAL, ZERO};
+            (NORMAL | SUBNORMAL | ZERO).prop_map(|value| Value::from(f64::from(value))).boxed()
+        }
+        PrimitiveSubtype::Float64 => {
+            use proptest::num::f64::{NORMAL, SUBNORMAL, ZERO};
+            (NORMAL | SUBNORMAL | ZERO).prop_map(Value::from).boxed()
+        }
+    }
+}
+
+fn handle() -> BoxedStrategy<Value> {
+    (1..=u32::MAX).prop_map(Value::from).boxed()
+}
+
+struct Builder<'a, L: ?Sized> {
+    lookup: &'a L,
+    options: Options,
+}
+
+impl<L: DeclLookup + ?Sized> Builder<'_, L> {
+    /// Returns the maximum length allowed by `bound` and the options.
+    fn max_length(&self, bound: Option<u64>) -> usize {
+        std::cmp::min(bound.unwrap_or(u64::MAX), self.options.max_length as u64) as usize
+    }
+
+    fn value(&self, r#type: &Type, depth: u32) -> Result<BoxedStrategy<Value>> {
+        if !*r#type.nullable {
+            return self.non_null(r#type, depth);
+        }
+        if depth >= self.options.max_depth {
+            return Ok(Just(Value::Null).boxed());
+        }
+        Ok(prop_oneof![Just(Value::Null), self.non_null(r#type, depth + 1)?].boxed())
+    }
+
+    fn non_null(&self, r#type: &Type, depth: u32) -> Result<BoxedStrategy<Value>> {
+        Ok(match &*r#type.kind {
+            TypeKind::Primitive { subtype } => primitive(subtype),
+            TypeKind::Handle { .. } | TypeKind::Request { .. } => handle(),
+            TypeKind::String { maybe_element_count, .. } => {
+                let max =
+                    self.max_length(maybe_element_count.as_ref().and_then(|bound| bound.as_u64()));
+                // Lengths are bounded in bytes, so cut strings short at a character boundary.
+                collection::vec(any::<char>(), 0..=max)
+                    .prop_map(move |chars| {
+                        let mut string = String::new();
+                        for c in chars {
+                            if string.len() + c.len_utf8() > max {
+                                break;
+                            }
+                            string.push(c);
+                        }
+                        Value::from(string)
+                    })
+                    .boxed()
+            }
+            TypeKind::Vector { element_type, maybe_element_count, .. } => {
+                let max =
+                    self.max_length(maybe_element_count.as_ref().and_then(|bound| bound.as_u64()));
+                if max == 0 || depth >= self.options.max_depth {
+                    return Ok(Just(Value::Array(Vec::new())).boxed());
+                }
+                let element = self.value(element_type, depth + 1)?;
+                collection::vec(element, 0..=max).prop_map(Value::Array).boxed()
+            }
+            TypeKind::Array { element_type, element_count, .. } => {
+                let count = element_count
+                    .as_ref()
+                    .and_then(|count| count.as_u64())
+                    .ok_or_else(|| unknown("array size"))?;
+                let element = self.value(element_type, depth)?;
+                collection::vec(element, count as usize).prop_map(Value::Array).boxed()
+            }
+            TypeKind::Identifier { identifier, .. } => self.decl(identifier, depth)?,
+            TypeKind::UnresolvedRequest { .. } | TypeKind::UnresolvedIdentifier { .. } => {
+                return Err(unknown("unresolved type"))
+            }
+        })
+    }
+
+    fn decl(&self, identifier: &DeclPath, depth: u32) -> Result<BoxedStrategy<Value>> {
+        let decl = self.lookup.lookup_decl(identifier).ok_or_else(|| unknown(identifier))?;
+        let depth = depth + 1;
+        Ok(match decl {
+            Decl::Const(_) => return Err(unknown(identifier)),
+            Decl::Protocol(_) => handle(),
+            Decl::Enum(decl) => {
+                let names: Vec<Value> =
+                    decl.members.iter().map(|member| member.name.inner.clone().into()).collect();
+                if names.is_empty() {
+                    return Err(unknown(identifier));
+                }
+                select(names).boxed()
+            }
+            Decl::Bits(decl) => {
+                let bits: Vec<u64> = decl
+                    .members
+                    .iter()
+                    .filter_map(|member| member.value.as_ref()?.integer_value())
+                    .map(|bit| bit as u64)
+                    .collect();
+                let count = bits.len();
+                subsequence(bits, 0..=count)
+                    .prop_map(|bits| Value::from(bits.into_iter().fold(0, |mask, bit| mask | bit)))
+                    .boxed()
+            }
+            Decl::Struct(decl) => members(
+                decl.members
+                    .iter()
+                    .map(|member| {
+                        Ok((member.name.inner.clone(), self.value(&member.r#type, depth)?))
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            Decl::Table(decl) => {
+                if depth >= self.options.max_depth {
+                    return Ok(Just(object(Vec::new())).boxed());
+                }
+                let fields: Vec<(String, BoxedStrategy<Option<Value>>)> = table_fields(decl)
+                    .into_iter()
+                    .map(|(_, name, r#type)| {
+                        Ok((
+                            name.inner.clone(),
+                            proptest::option::of(self.value(r#type, depth)?).boxed(),
+                        ))
+                    })
+                    .collect::<Result<_>>()?;
+                let (names, fields): (Vec<_>, Vec<_>) = fields.into_iter().unzip();
+                fields
+                    .prop_map(move |values| {
+                        object(
+                            names
+                                .iter()
+                                .cloned()
+                                .zip(values)
+                                .filter_map(|(name, value)| Some((name, value?)))
+                                .collect(),
+                        )
+                    })
+                    .boxed()
+            }
+            Decl::Union(decl) => one_of(
+                decl.members
+                    .iter()
+                    .map(|member| {
+                        Ok((member.name.inner.clone(), self.value(&member.r#type, depth)?))
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            Decl::XUnion(decl) => {
+                let members: Vec<_> = decl
+                    .members
+                    .iter()
+                    .map(|member| {
+                        Ok((member.name.inner.clone(), self.value(&member.r#type, depth)?))
+                    })
+                    .collect::<Result<_>>()?;
+                if members.is_empty() {
+                    return Err(unknown(identifier));
+                }
+                one_of(members)
+            }
+        })
+    }
+}
+
+/// Returns a strategy generating values of `r#type`.
+pub fn value_strategy<L: DeclLookup + ?Sized>(
+    lookup: &L,
+    r#type: &Type,
+    options: &Options,
+) -> std::result::Result<BoxedStrategy<Value>, wire::Error> {
+    Builder { lookup, options: *options }.value(r#type, 0)
+}
+
+/// Returns a strategy generating values of the declaration at `path`, such as a struct or table.
+pub fn decl_strategy<L: DeclLookup + ?Sized>(
+    lookup: &L,
+    path: &DeclPath,
+    options: &Options,
+) -> std::result::Result<BoxedStrategy<Value>, wire::Error> {
+    Builder { lookup, options: *options }.decl(path, 0)
+}
+
+/// Returns a strategy generating the parameters of a request or response, as an object keyed by
+/// parameter name.
+pub fn message_strategy<L: DeclLookup + ?Sized>(
+    lookup: &L,
+    message: &MethodReqRes,
+    options: &Options,
+) -> std::result::Result<BoxedStrategy<Value>, wire::Error> {
+    let builder = Builder { lookup, options: *options };
+    Ok(members(
+        message
+            .parameters
+            .iter()
+            .map(|param| Ok((param.name.inner.clone(), builder.value(&param.r#type, 0)?)))
+            .collect::<Result<_>>()?,
+    ))
+}
+
+/// Returns the non-nullable type referring to the declaration at `path`.
+pub fn decl_type(path: &DeclPath) -> Type {
+    Type::from_decl(path.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            fixtures,
+            wire::{decode_message, decode_value, encode_message, encode_value, TransactionHeader},
+            Library,
+        },
+        proptest::test_runner::TestRunner,
+        serde_json::json,
+    };
+
+    /// Checks that every value generated for `r#type` decodes back to itself once encoded.
+    fn check_round_trip(library: &Library, r#type: &Type, strategy: BoxedStrategy<Value>) {
+        TestRunner::default()
+            .run(&strategy, |value| {
+                let message = encode_value(library, r#type, &value).unwrap();
+                let decoded = decode_value(library, r#type, &message.bytes, &message.handles);
+                prop_assert_eq!(decoded.unwrap().value, value);
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn decl_values_round_trip() {
+        let library = fixtures::library("example");
+        for name in &["Point", "Node", "Settings", "Value", "Color"] {
+            let path =
+                DeclPath { library_name: "test.example".to_string(), decl_name: name.to_string() };
+            let strategy = decl_strategy(&library, &path, &Options::default()).unwrap();
+            check_round_trip(&library, &decl_type(&path), strategy);
+        }
+    }
+
+    #[test]
+    fn nullable_values_round_trip() {
+        let library = fixtures::library("example");
+        let r#type = fixtures::r#type(json!({
+            "kind": "vector",
+            "element_type": {"kind": "string", "maybe_element_count": 4, "nullable": true},
+            "maybe_element_count": 3,
+            "nullable": true,
+        }));
+        let strategy = value_strategy(&library, &r#type, &Options::default()).unwrap();
+        check_round_trip(&library, &r#type, strategy);
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let library = fixtures::library("example");
+        for method in &library.protocols[0].methods {
+            for message in method.request.iter().chain(&method.response) {
+                let strategy = message_strategy(&library, message, &Options::default()).unwrap();
+                let header = TransactionHeader::new(1, method.ordinal.unwrap_or(0));
+                TestRunner::default()
+                    .run(&strategy, |value| {
+                        let encoded = encode_message(&library, &header, message, &value).unwrap();
+                        let decoded =
+                            decode_message(&library, message, &encoded.bytes, &encoded.handles);
+                        prop_assert_eq!(decoded.unwrap().value, value);
+                        Ok(())
+                    })
+                    .unwrap();
+            }
+        }
+    }
+}
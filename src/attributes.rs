@@ -0,0 +1,470 @@
+use {
+    super::{Attribute, Diagnostic, Library, Span, Spanned},
+    indexmap::IndexMap,
+    std::fmt,
+};
+
+/// The kinds of element an attribute can be attached to.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum Placement {
+    Library,
+    Const,
+    Bits,
+    BitsMember,
+    Enum,
+    EnumMember,
+    Protocol,
+    Method,
+    Struct,
+    StructMember,
+    Table,
+    TableMember,
+    Union,
+    UnionMember,
+    XUnion,
+    XUnionMember,
+}
+
+impl Placement {
+    pub const ALL: &'static [Placement] = &[
+        Placement::Library,
+        Placement::Const,
+        Placement::Bits,
+        Placement::BitsMember,
+        Placement::Enum,
+        Placement::EnumMember,
+        Placement::Protocol,
+        Placement::Method,
+        Placement::Struct,
+        Placement::StructMember,
+        Placement::Table,
+        Placement::TableMember,
+        Placement::Union,
+        Placement::UnionMember,
+        Placement::XUnion,
+        Placement::XUnionMember,
+    ];
+}
+
+impl fmt::Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Placement::Library => "library",
+            Placement::Const => "const",
+            Placement::Bits => "bits",
+            Placement::BitsMember => "bits member",
+            Placement::Enum => "enum",
+            Placement::EnumMember => "enum member",
+            Placement::Protocol => "protocol",
+            Placement::Method => "method",
+            Placement::Struct => "struct",
+            Placement::StructMember => "struct member",
+            Placement::Table => "table",
+            Placement::TableMember => "table member",
+            Placement::Union => "union",
+            Placement::UnionMember => "union member",
+            Placement::XUnion => "xunion",
+            Placement::XUnionMember => "xunion member",
+        })
+    }
+}
+
+/// The shape of value an attribute accepts.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum ValueKind {
+    /// The attribute is a marker and takes no value.
+    None,
+    /// The attribute may have a string value, e.g. `[Deprecated]` or `[Deprecated = "reason"]`.
+    OptionalString,
+    /// The attribute requires a string value.
+    String,
+    /// The attribute requires an unsigned integer value.
+    Number,
+    /// The attribute requires a comma-separated list of names.
+    List,
+}
+
+/// The typed value of an attribute, parsed according to its `ValueKind`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AttributeValue {
+    Empty,
+    String(String),
+    Number(u64),
+    List(Vec<String>),
+}
+
+/// A description of an attribute that may appear in a library.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AttributeSchema {
+    pub name: String,
+    pub placements: Vec<Placement>,
+    pub value: ValueKind,
+}
+
+impl AttributeSchema {
+    pub fn new(name: impl Into<String>, placements: &[Placement], value: ValueKind) -> Self {
+        AttributeSchema { name: name.into(), placements: placements.to_vec(), value }
+    }
+
+    /// Parses the value of `attr` according to this schema.
+    pub fn parse(&self, attr: &Attribute) -> Result<AttributeValue, AttributeError> {
+        let invalid = |reason: &str| AttributeError::InvalidValue {
+            name: self.name.clone(),
+            reason: reason.to_string(),
+            span: attr.value.as_ref().and_then(|value| value.span).or(attr.name.span),
+        };
+        // The IR writes attributes without a value, such as `[Transitional]`, with an empty one.
+        let value =
+            attr.value.as_ref().map(|value| value.as_str()).filter(|value| !value.is_empty());
+        match (self.value, value) {
+            (ValueKind::None, None) | (ValueKind::OptionalString, None) => {
+                Ok(AttributeValue::Empty)
+            }
+            (ValueKind::None, Some(_)) => Err(invalid("no value is allowed")),
+            (_, None) => Err(invalid("a value is required")),
+            (ValueKind::OptionalString, Some(value)) | (ValueKind::String, Some(value)) => {
+                Ok(AttributeValue::String(value.to_string()))
+            }
+            (ValueKind::Number, Some(value)) => value
+                .trim()
+                .parse()
+                .map(AttributeValue::Number)
+                .map_err(|_| invalid("expected an unsigned integer")),
+            (ValueKind::List, Some(value)) => {
+                let items: Vec<String> =
+                    value.split(',').map(|item| item.trim().to_string()).collect();
+                if items.iter().any(String::is_empty) {
+                    return Err(invalid("expected a comma-separated list of names"));
+                }
+                Ok(AttributeValue::List(items))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AttributeError {
+    /// No schema is registered under the attribute's name.
+    Unknown { name: String, span: Option<Span> },
+    /// The attribute is attached to an element kind its schema doesn't allow.
+    Misplaced { name: String, placement: Placement, span: Option<Span> },
+    /// The attribute's value doesn't match its schema's `ValueKind`.
+    InvalidValue { name: String, reason: String, span: Option<Span> },
+}
+
+impl AttributeError {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            AttributeError::Unknown { span, .. }
+            | AttributeError::Misplaced { span, .. }
+            | AttributeError::InvalidValue { span, .. } => *span,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            AttributeError::Unknown { .. } => "unknown-attribute",
+            AttributeError::Misplaced { .. } => "misplaced-attribute",
+            AttributeError::InvalidValue { .. } => "invalid-attribute-value",
+        }
+    }
+}
+
+impl fmt::Display for AttributeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AttributeError::Unknown { name, .. } => write!(f, "unknown attribute `{}`", name),
+            AttributeError::Misplaced { name, placement, .. } => {
+                write!(f, "attribute `{}` is not allowed on a {}", name, placement)
+            }
+            AttributeError::InvalidValue { name, reason, .. } => {
+                write!(f, "invalid value for attribute `{}`: {}", name, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AttributeError {}
+
+impl From<AttributeError> for Diagnostic {
+    fn from(err: AttributeError) -> Diagnostic {
+        Diagnostic::error(err.code(), err.to_string(), err.span())
+    }
+}
+
+/// The set of attributes recognized when validating a library.
+#[derive(Debug, Clone)]
+pub struct AttributeRegistry {
+    schemas: IndexMap<String, AttributeSchema>,
+}
+
+impl Default for AttributeRegistry {
+    fn default() -> Self {
+        AttributeRegistry::with_builtins()
+    }
+}
+
+impl AttributeRegistry {
+    /// Creates a registry that recognizes no attributes.
+    pub fn empty() -> Self {
+        AttributeRegistry { schemas: IndexMap::new() }
+    }
+
+    /// Creates a registry that recognizes the attributes defined by the FIDL language.
+    pub fn with_builtins() -> Self {
+        use Placement::*;
+        const SIZED: &[Placement] = &[Protocol, Method, Struct, Table, Union, XUnion];
+        const MEMBERS: &[Placement] =
+            &[Method, BitsMember, EnumMember, TableMember, UnionMember, XUnionMember];
+
+        let mut registry = AttributeRegistry::empty();
+        registry.register(AttributeSchema::new("Doc", Placement::ALL, ValueKind::String));
+        registry.register(AttributeSchema::new("Transport", &[Protocol], ValueKind::List));
+        registry.register(AttributeSchema::new(
+            "Discoverable",
+            &[Protocol],
+            ValueKind::OptionalString,
+        ));
+        registry.register(AttributeSchema::new("Layout", &[Protocol], ValueKind::String));
+        registry.register(AttributeSchema::new("Selector", &[Method], ValueKind::String));
+        registry.register(AttributeSchema::new("MaxBytes", SIZED, ValueKind::Number));
+        registry.register(AttributeSchema::new("MaxHandles", SIZED, ValueKind::Number));
+        registry.register(AttributeSchema::new(
+            "Deprecated",
+            Placement::ALL,
+            ValueKind::OptionalString,
+        ));
+        registry.register(AttributeSchema::new("Transitional", MEMBERS, ValueKind::OptionalString));
+        registry.register(AttributeSchema::new("FragileBase", &[Protocol], ValueKind::None));
+        registry.register(AttributeSchema::new(
+            super::AVAILABLE_ATTRIBUTE,
+            Placement::ALL,
+            ValueKind::String,
+        ));
+        registry
+    }
+
+    /// Adds a schema to the registry, returning the schema previously registered under the same
+    /// name, if any.
+    pub fn register(&mut self, schema: AttributeSchema) -> Option<AttributeSchema> {
+        self.schemas.insert(schema.name.clone(), schema)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AttributeSchema> {
+        self.schemas.get(name)
+    }
+
+    pub fn schemas(&self) -> impl Iterator<Item = &AttributeSchema> {
+        self.schemas.values()
+    }
+
+    /// Checks `attr` against its schema and returns its typed value.
+    pub fn parse(
+        &self,
+        attr: &Attribute,
+        placement: Placement,
+    ) -> Result<AttributeValue, AttributeError> {
+        let schema = self.get(&attr.name).ok_or_else(|| AttributeError::Unknown {
+            name: attr.name.inner.clone(),
+            span: attr.name.span,
+        })?;
+        if !schema.placements.contains(&placement) {
+            return Err(AttributeError::Misplaced {
+                name: attr.name.inner.clone(),
+                placement,
+                span: attr.name.span,
+            });
+        }
+        schema.parse(attr)
+    }
+
+    /// Checks every attribute in `library`, reporting unknown and misplaced attributes and
+    /// attributes with invalid values.
+    pub fn validate(&self, library: &Library) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for_each_attribute_list(library, |placement, attributes| {
+            for attr in attributes {
+                if let Err(err) = self.parse(attr, placement) {
+                    diagnostics.push(err.into());
+                }
+            }
+        });
+        diagnostics
+    }
+}
+
+/// Calls `f` with the attributes of the library and of every declaration and member in it.
+pub fn for_each_attribute_list<'a>(
+    library: &'a Library,
+    mut f: impl FnMut(Placement, &'a [Spanned<Attribute>]),
+) {
+    f(Placement::Library, &library.attributes);
+    for decl in &library.consts {
+        f(Placement::Const, &decl.attributes);
+    }
+    for decl in &library.bits {
+        f(Placement::Bits, &decl.attributes);
+        for member in &decl.members {
+            f(Placement::BitsMember, &member.attributes);
+        }
+    }
+    for decl in &library.enums {
+        f(Placement::Enum, &decl.attributes);
+        for member in &decl.members {
+            f(Placement::EnumMember, &member.attributes);
+        }
+    }
+    for decl in &library.protocols {
+        f(Placement::Protocol, &decl.attributes);
+        for method in &decl.methods {
+            f(Placement::Method, &method.attributes);
+        }
+    }
+    for decl in &library.structs {
+        f(Placement::Struct, &decl.attributes);
+        for member in &decl.members {
+            f(Placement::StructMember, &member.attributes);
+        }
+    }
+    for decl in &library.tables {
+        f(Placement::Table, &decl.attributes);
+        for member in &decl.members {
+            f(Placement::TableMember, &member.attributes);
+        }
+    }
+    for decl in &library.unions {
+        f(Placement::Union, &decl.attributes);
+        for member in &decl.members {
+            f(Placement::UnionMember, &member.attributes);
+        }
+    }
+    for decl in &library.xunions {
+        f(Placement::XUnion, &decl.attributes);
+        for member in &decl.members {
+            f(Placement::XUnionMember, &member.attributes);
+        }
+    }
+}
+
+/// Typed accessors for the built-in attributes on a list of attributes.
+///
+/// Accessors return `None` when the attribute is absent or its value doesn't parse; use
+/// `AttributeRegistry::validate` to report malformed attributes.
+pub trait Attributes {
+    /// Returns the attribute named `name`, if present.
+    fn get(&self, name: &str) -> Option<&Attribute>;
+
+    /// Returns the string value of the attribute named `name`, if present and set. Attributes
+    /// without a value are written with an empty one in the IR, so empty values count as unset.
+    fn value(&self, name: &str) -> Option<&str> {
+        self.get(name)
+            .and_then(|attr| attr.value.as_ref())
+            .map(|value| value.as_str())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    fn doc(&self) -> Option<&str> {
+        self.value("Doc")
+    }
+
+    fn transports(&self) -> Vec<&str> {
+        self.value("Transport")
+            .map(|value| value.split(',').map(str::trim).collect())
+            .unwrap_or_default()
+    }
+
+    fn is_discoverable(&self) -> bool {
+        self.has("Discoverable")
+    }
+
+    /// Returns the discovery name set by `[Discoverable = "name"]`, if an explicit one is given.
+    fn discoverable_name(&self) -> Option<&str> {
+        self.value("Discoverable")
+    }
+
+    fn layout(&self) -> Option<&str> {
+        self.value("Layout")
+    }
+
+    fn selector(&self) -> Option<&str> {
+        self.value("Selector")
+    }
+
+    fn max_bytes(&self) -> Option<u64> {
+        self.value("MaxBytes").and_then(|value| value.trim().parse().ok())
+    }
+
+    fn max_handles(&self) -> Option<u64> {
+        self.value("MaxHandles").and_then(|value| value.trim().parse().ok())
+    }
+
+    fn is_deprecated(&self) -> bool {
+        self.has("Deprecated")
+    }
+
+    fn is_transitional(&self) -> bool {
+        self.has("Transitional")
+    }
+
+    fn is_fragile_base(&self) -> bool {
+        self.has("FragileBase")
+    }
+}
+
+impl Attributes for [Spanned<Attribute>] {
+    fn get(&self, name: &str) -> Option<&Attribute> {
+        self.iter().map(|attr| &attr.inner).find(|attr| *attr.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::fixtures};
+
+    #[test]
+    fn validates_attributes_as_fidlc_writes_them() {
+        let library = fixtures::library("attributes");
+        let diagnostics = AttributeRegistry::with_builtins().validate(&library);
+        let reported: Vec<(&str, &str)> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.code.as_str(), diagnostic.message.as_str()))
+            .collect();
+        assert_eq!(
+            reported,
+            [
+                (
+                    "invalid-attribute-value",
+                    "invalid value for attribute `FragileBase`: no value is allowed"
+                ),
+                ("unknown-attribute", "unknown attribute `Bogus`"),
+                ("misplaced-attribute", "attribute `MaxBytes` is not allowed on a table member"),
+            ]
+        );
+    }
+
+    #[test]
+    fn treats_empty_values_as_unset() {
+        let library = fixtures::library("attributes");
+        let marked = &library.protocols[0].attributes;
+        assert!(marked.is_discoverable());
+        assert_eq!(marked.discoverable_name(), None);
+        let named = &library.protocols[1].attributes;
+        assert_eq!(named.discoverable_name(), Some("fuchsia.test.Named"));
+    }
+
+    #[test]
+    fn rejects_misplaced_attributes() {
+        let library = fixtures::library("attributes");
+        let registry = AttributeRegistry::with_builtins();
+        let attr = &library.protocols[0].attributes[1];
+        assert!(registry.parse(attr, Placement::Protocol).is_ok());
+        assert!(matches!(
+            registry.parse(attr, Placement::Struct),
+            Err(AttributeError::Misplaced { placement: Placement::Struct, .. })
+        ));
+    }
+}
@@ -0,0 +1,278 @@
+use {
+    super::{Attribute, DeclPath, Library, Span, Spanned},
+    std::{collections::HashSet, fmt},
+};
+
+/// The name of the attribute carrying availability information, e.g.
+/// `[Available = "added=1, deprecated=2, removed=3"]`. Matched case-sensitively, like other
+/// attribute names.
+pub const AVAILABLE_ATTRIBUTE: &str = "Available";
+
+/// The API level used for `HEAD`, which is later than every numbered level.
+pub const HEAD: u64 = u64::MAX;
+
+/// The API levels at which a declaration or member was added, deprecated, and removed.
+#[derive(Debug, Clone, Copy, Default, Hash, Eq, PartialEq)]
+pub struct Availability {
+    pub added: Option<u64>,
+    pub deprecated: Option<u64>,
+    pub removed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AvailabilityError {
+    /// The attribute value contained an argument other than `added`, `deprecated`, or `removed`.
+    UnknownArgument { argument: String, span: Option<Span> },
+    /// An argument was not of the form `name=level`, or its level was not a number or `HEAD`.
+    InvalidArgument { argument: String, span: Option<Span> },
+    /// The same argument appeared more than once.
+    DuplicateArgument { argument: String, span: Option<Span> },
+    /// The levels were not ordered `added <= deprecated < removed`.
+    InvalidOrder { availability: Availability, span: Option<Span> },
+}
+
+impl fmt::Display for AvailabilityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AvailabilityError::UnknownArgument { argument, .. } => {
+                write!(f, "unknown availability argument `{}`", argument)
+            }
+            AvailabilityError::InvalidArgument { argument, .. } => {
+                write!(f, "invalid availability argument `{}`", argument)
+            }
+            AvailabilityError::DuplicateArgument { argument, .. } => {
+                write!(f, "duplicate availability argument `{}`", argument)
+            }
+            AvailabilityError::InvalidOrder { availability, .. } => write!(
+                f,
+                "availability levels must satisfy added <= deprecated < removed, got {:?}",
+                availability
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AvailabilityError {}
+
+fn parse_level(level: &str) -> Option<u64> {
+    if level == "HEAD" {
+        Some(HEAD)
+    } else {
+        level.parse().ok()
+    }
+}
+
+impl Availability {
+    /// Parses the value of an `Available` attribute, a comma-separated list of `name=level`.
+    pub fn parse(value: &str, span: Option<Span>) -> Result<Availability, AvailabilityError> {
+        let mut availability = Availability::default();
+        for argument in value.split(',').map(str::trim).filter(|arg| !arg.is_empty()) {
+            let invalid =
+                || AvailabilityError::InvalidArgument { argument: argument.to_string(), span };
+            let mut parts = argument.splitn(2, '=');
+            let name = parts.next().ok_or_else(invalid)?.trim();
+            let level = parts.next().map(str::trim).and_then(parse_level).ok_or_else(invalid)?;
+            let slot = match name {
+                "added" => &mut availability.added,
+                "deprecated" => &mut availability.deprecated,
+                "removed" => &mut availability.removed,
+                _ => {
+                    return Err(AvailabilityError::UnknownArgument {
+                        argument: name.to_string(),
+                        span,
+                    })
+                }
+            };
+            if slot.is_some() {
+                return Err(AvailabilityError::DuplicateArgument {
+                    argument: name.to_string(),
+                    span,
+                });
+            }
+            *slot = Some(level);
+        }
+        let added = availability.added.unwrap_or(0);
+        let in_order = availability.deprecated.map_or(true, |deprecated| added <= deprecated)
+            && availability.removed.map_or(true, |removed| {
+                added < removed && availability.deprecated.map_or(true, |d| d < removed)
+            });
+        if !in_order {
+            return Err(AvailabilityError::InvalidOrder { availability, span });
+        }
+        Ok(availability)
+    }
+
+    /// Reads the availability from a list of attributes. Elements without an `Available`
+    /// attribute are available at every level.
+    pub fn from_attributes(
+        attributes: &[Spanned<Attribute>],
+    ) -> Result<Availability, AvailabilityError> {
+        let attr = attributes.iter().find(|attr| *attr.name == AVAILABLE_ATTRIBUTE);
+        match attr.and_then(|attr| attr.value.as_ref()) {
+            Some(value) => Availability::parse(value, value.span),
+            None => Ok(Availability::default()),
+        }
+    }
+
+    /// Returns `true` if the element exists at API level `level`.
+    pub fn is_present_at(&self, level: u64) -> bool {
+        self.added.map_or(true, |added| added <= level)
+            && self.removed.map_or(true, |removed| level < removed)
+    }
+
+    /// Returns `true` if the element exists but is deprecated at API level `level`.
+    pub fn is_deprecated_at(&self, level: u64) -> bool {
+        self.is_present_at(level) && self.deprecated.is_some_and(|deprecated| deprecated <= level)
+    }
+}
+
+/// Removes the elements of `items` that are not present at `level`.
+fn retain_at<T>(
+    items: &mut Vec<Spanned<T>>,
+    level: u64,
+    attributes: impl Fn(&T) -> &[Spanned<Attribute>],
+) -> Result<(), AvailabilityError> {
+    let mut present = Vec::with_capacity(items.len());
+    for item in items.iter() {
+        present.push(Availability::from_attributes(attributes(item))?.is_present_at(level));
+    }
+    let mut present = present.into_iter();
+    items.retain(|_| present.next().unwrap_or(false));
+    Ok(())
+}
+
+impl Library {
+    /// Returns the library as seen at API level `level`, with declarations and members that are
+    /// not yet added or already removed at that level dropped.
+    ///
+    /// Layout information (`size`, `offset`, etc.) is carried over from `self` as-is, so it
+    /// reflects the layout at the level the IR was generated for.
+    pub fn at_version(&self, level: u64) -> Result<Library, AvailabilityError> {
+        let mut library = self.clone();
+        let mut removed: HashSet<DeclPath> = HashSet::new();
+
+        macro_rules! filter_decls {
+            ($($decls:ident),*) => {$(
+                for decl in &library.$decls {
+                    if !Availability::from_attributes(&decl.attributes)?.is_present_at(level) {
+                        removed.insert(decl.name.inner.clone());
+                    }
+                }
+                library.$decls.retain(|decl| !removed.contains(&decl.name));
+            )*};
+        }
+        filter_decls!(consts, bits, enums, protocols, structs, tables, unions, xunions);
+
+        for decl in &mut library.bits {
+            retain_at(&mut decl.members, level, |member| &member.attributes)?;
+        }
+        for decl in &mut library.enums {
+            retain_at(&mut decl.members, level, |member| &member.attributes)?;
+        }
+        for decl in &mut library.protocols {
+            retain_at(&mut decl.methods, level, |method| &method.attributes)?;
+        }
+        for decl in &mut library.structs {
+            retain_at(&mut decl.members, level, |member| &member.attributes)?;
+        }
+        for decl in &mut library.tables {
+            retain_at(&mut decl.members, level, |member| &member.attributes)?;
+        }
+        for decl in &mut library.unions {
+            retain_at(&mut decl.members, level, |member| &member.attributes)?;
+        }
+        for decl in &mut library.xunions {
+            retain_at(&mut decl.members, level, |member| &member.attributes)?;
+        }
+
+        library.declarations.retain(|path, _| !removed.contains(&path.inner));
+        let removed_names: HashSet<String> = removed
+            .iter()
+            .map(|path| [&path.library_name, "/", &path.decl_name].concat())
+            .collect();
+        library.declaration_order.retain(|name| !removed_names.contains(name));
+        Ok(library)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::fixtures};
+
+    fn attributes(json: &str) -> Vec<Spanned<Attribute>> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn reads_available_attribute() {
+        let attributes = attributes(r#"[{"name": "Available", "value": "added=2,removed=5"}]"#);
+        let availability = Availability::from_attributes(&attributes).unwrap();
+        assert_eq!(
+            availability,
+            Availability { added: Some(2), deprecated: None, removed: Some(5) }
+        );
+        assert!(!availability.is_present_at(1));
+        assert!(availability.is_present_at(4));
+        assert!(!availability.is_present_at(5));
+    }
+
+    #[test]
+    fn matches_attribute_name_case_sensitively() {
+        let attributes = attributes(r#"[{"name": "available", "value": "added=2"}]"#);
+        assert_eq!(Availability::from_attributes(&attributes), Ok(Availability::default()));
+    }
+
+    #[test]
+    fn rejects_removal_before_addition() {
+        let attributes = attributes(r#"[{"name": "Available", "value": "added=3,removed=2"}]"#);
+        assert!(matches!(
+            Availability::from_attributes(&attributes),
+            Err(AvailabilityError::InvalidOrder { .. })
+        ));
+    }
+
+    #[test]
+    fn filters_declarations_and_members_by_level() {
+        let mut library = fixtures::library("example");
+        let available = |value: &str| {
+            attributes(&format!(r#"[{{"name": "Available", "value": "{}"}}]"#, value))
+        };
+        library.structs[0].attributes = available("added=2");
+        library.structs[1].attributes = available("removed=3");
+        library.structs[1].members[2].attributes = available("added=5");
+        library.tables[0].attributes = available("deprecated=1");
+        library.enums[0].members[1].attributes = available("removed=2");
+        library.protocols[0].methods[1].attributes = available("added=3");
+
+        let names = |library: &Library| -> Vec<String> {
+            library.decl_names().iter().map(|name| name.decl_name.clone()).collect()
+        };
+        let at_1 = library.at_version(1).unwrap();
+        assert_eq!(
+            names(&at_1),
+            ["maxCount", "Color", "EchoProtocol", "Node", "Settings", "Value"]
+        );
+        assert!(!at_1.declaration_order.contains(&"test.example/Point".to_string()));
+        assert!(at_1.declarations.keys().all(|path| path.decl_name != "Point"));
+        let members: Vec<_> =
+            at_1.structs[0].members.iter().map(|member| member.name.as_str()).collect();
+        assert_eq!(members, ["a", "children", "color"]);
+        assert_eq!(at_1.enums[0].members.len(), 2);
+        assert_eq!(at_1.protocols[0].methods.len(), 1);
+
+        let at_2 = library.at_version(2).unwrap();
+        assert_eq!(
+            names(&at_2),
+            ["maxCount", "Color", "EchoProtocol", "Point", "Node", "Settings", "Value"]
+        );
+        assert_eq!(at_2.enums[0].members.len(), 1);
+
+        let at_head = library.at_version(HEAD).unwrap();
+        assert_eq!(
+            names(&at_head),
+            ["maxCount", "Color", "EchoProtocol", "Point", "Settings", "Value"]
+        );
+        assert_eq!(at_head.protocols[0].methods.len(), 2);
+        assert_eq!(at_head.declaration_order.len(), 6);
+    }
+}
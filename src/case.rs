@@ -0,0 +1,255 @@
+//! Conversions between the naming conventions used by FIDL and its target languages, and
+//! escaping of names that are reserved words in those languages.
+
+/// Splits an identifier into lowercase words at underscores, lower-to-upper transitions, and
+/// the end of runs of capitals, so `HTTPServer2Config` becomes `http`, `server2`, `config`.
+pub fn split_words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut current = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        if c.is_uppercase() && !current.is_empty() {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_is_lower) {
+                words.push(std::mem::take(&mut current));
+            }
+        }
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// `FooBar` -> `foo_bar`
+pub fn to_snake_case(name: &str) -> String {
+    split_words(name).join("_")
+}
+
+/// `foo_bar` -> `FOO_BAR`
+pub fn to_screaming_snake_case(name: &str) -> String {
+    to_snake_case(name).to_uppercase()
+}
+
+/// `foo_bar` -> `FooBar`
+pub fn to_upper_camel_case(name: &str) -> String {
+    split_words(name).iter().map(|word| capitalize(word)).collect()
+}
+
+/// `foo_bar` -> `fooBar`
+pub fn to_lower_camel_case(name: &str) -> String {
+    let words = split_words(name);
+    let mut iter = words.iter();
+    let mut out = iter.next().cloned().unwrap_or_default();
+    out.extend(iter.map(|word| capitalize(word)));
+    out
+}
+
+pub fn is_snake_case(name: &str) -> bool {
+    !name.is_empty() && to_snake_case(name) == name
+}
+
+pub fn is_screaming_snake_case(name: &str) -> bool {
+    !name.is_empty() && to_screaming_snake_case(name) == name
+}
+
+pub fn is_upper_camel_case(name: &str) -> bool {
+    name.chars().next().is_some_and(char::is_uppercase) && !name.contains('_')
+}
+
+/// A target language whose reserved words can't be used as identifiers.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Language {
+    C,
+    Cpp,
+    Dart,
+    Go,
+    Python,
+    Rust,
+    TypeScript,
+}
+
+impl Language {
+    /// Returns the language named `name`: `c`, `cpp`, `dart`, `go`, `python`, `rust`, or
+    /// `typescript`.
+    pub fn from_name(name: &str) -> Option<Language> {
+        Some(match name {
+            "c" => Language::C,
+            "cpp" => Language::Cpp,
+            "dart" => Language::Dart,
+            "go" => Language::Go,
+            "python" => Language::Python,
+            "rust" => Language::Rust,
+            "typescript" => Language::TypeScript,
+            _ => return None,
+        })
+    }
+
+    /// Returns the reserved words of the language.
+    #[rustfmt::skip]
+    pub fn keywords(self) -> &'static [&'static str] {
+        match self {
+            Language::C => &[
+                "auto", "bool", "break", "case", "char", "const", "continue", "default", "do",
+                "double", "else", "enum", "extern", "false", "float", "for", "goto", "if", "inline",
+                "int", "long", "register", "restrict", "return", "short", "signed", "sizeof",
+                "static", "struct", "switch", "true", "typedef", "union", "unsigned", "void",
+                "volatile", "while",
+            ],
+            Language::Cpp => &[
+                "alignas", "alignof", "and", "and_eq", "asm", "auto", "bitand", "bitor", "bool",
+                "break", "case", "catch", "char", "char8_t", "char16_t", "char32_t", "class",
+                "compl", "concept", "const", "consteval", "constexpr", "constinit", "const_cast",
+                "continue", "co_await", "co_return", "co_yield", "decltype", "default", "delete",
+                "do", "double", "dynamic_cast", "else", "enum", "explicit", "export", "extern",
+                "false", "float", "for", "friend", "goto", "if", "inline", "int", "long", "mutable",
+                "namespace", "new", "noexcept", "not", "not_eq", "nullptr", "operator", "or",
+                "or_eq", "private", "protected", "public", "register", "reinterpret_cast",
+                "requires", "return", "short", "signed", "sizeof", "static", "static_assert",
+                "static_cast", "struct", "switch", "template", "this", "thread_local", "throw",
+                "true", "try", "typedef", "typeid", "typename", "union", "unsigned", "using",
+                "virtual", "void", "volatile", "wchar_t", "while", "xor", "xor_eq",
+            ],
+            Language::Dart => &[
+                "abstract", "as", "assert", "async", "await", "break", "case", "catch", "class",
+                "const", "continue", "covariant", "default", "deferred", "do", "dynamic", "else",
+                "enum", "export", "extends", "extension", "external", "factory", "false", "final",
+                "finally", "for", "Function", "get", "hide", "if", "implements", "import", "in",
+                "interface", "is", "late", "library", "mixin", "new", "null", "on", "operator",
+                "part", "required", "rethrow", "return", "set", "show", "static", "super", "switch",
+                "sync", "this", "throw", "true", "try", "typedef", "var", "void", "while", "with",
+                "yield",
+            ],
+            Language::Go => &[
+                "break", "case", "chan", "const", "continue", "default", "defer", "else",
+                "fallthrough", "for", "func", "go", "goto", "if", "import", "interface", "map",
+                "package", "range", "return", "select", "struct", "switch", "type", "var",
+            ],
+            Language::Python => &[
+                "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class",
+                "continue", "def", "del", "elif", "else", "except", "finally", "for", "from",
+                "global", "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass",
+                "raise", "return", "try", "while", "with", "yield",
+            ],
+            Language::Rust => &[
+                "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+                "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if",
+                "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override",
+                "priv", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+                "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+                "where", "while", "yield",
+            ],
+            Language::TypeScript => &[
+                "break", "case", "catch", "class", "const", "continue", "debugger", "default",
+                "delete", "do", "else", "enum", "export", "extends", "false", "finally", "for",
+                "function", "if", "implements", "import", "in", "instanceof", "interface", "let",
+                "new", "null", "package", "private", "protected", "public", "return", "static",
+                "super", "switch", "this", "throw", "true", "try", "typeof", "var", "void", "while",
+                "with", "yield",
+            ],
+        }
+    }
+
+    pub fn is_keyword(self, name: &str) -> bool {
+        self.keywords().contains(&name)
+    }
+}
+
+/// Appends an underscore to `name` if it's a reserved word in `language`, so `type` becomes
+/// `type_` in Rust.
+pub fn escape_keyword(name: &str, language: Language) -> String {
+    if language.is_keyword(name) {
+        format!("{}_", name)
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_words() {
+        assert_eq!(split_words("HTTPServer2Config"), ["http", "server2", "config"]);
+        assert_eq!(split_words("fooBar"), ["foo", "bar"]);
+        assert_eq!(split_words("__foo__bar_"), ["foo", "bar"]);
+        assert_eq!(split_words("IPv4Address"), ["i", "pv4", "address"]);
+        assert_eq!(split_words("ABC"), ["abc"]);
+        assert_eq!(split_words(""), Vec::<String>::new());
+    }
+
+    #[test]
+    fn converts_case() {
+        assert_eq!(to_snake_case("HTTPServer2Config"), "http_server2_config");
+        assert_eq!(to_screaming_snake_case("maxCount"), "MAX_COUNT");
+        assert_eq!(to_upper_camel_case("foo_bar"), "FooBar");
+        assert_eq!(to_upper_camel_case("HTTP_SERVER"), "HttpServer");
+        assert_eq!(to_lower_camel_case("FooBar"), "fooBar");
+        assert_eq!(to_lower_camel_case("MAX_COUNT"), "maxCount");
+        assert_eq!(to_lower_camel_case(""), "");
+    }
+
+    #[test]
+    fn checks_case() {
+        assert!(is_snake_case("foo_bar2"));
+        assert!(!is_snake_case("fooBar"));
+        assert!(!is_snake_case("foo__bar"));
+        assert!(!is_snake_case(""));
+        assert!(is_screaming_snake_case("MAX_COUNT"));
+        assert!(!is_screaming_snake_case("Max_Count"));
+        assert!(!is_screaming_snake_case(""));
+        assert!(is_upper_camel_case("EchoProtocol"));
+        assert!(is_upper_camel_case("HTTPServer"));
+        assert!(!is_upper_camel_case("echoProtocol"));
+        assert!(!is_upper_camel_case("Echo_Protocol"));
+        assert!(!is_upper_camel_case(""));
+    }
+
+    #[test]
+    fn languages_by_name() {
+        assert_eq!(Language::from_name("c"), Some(Language::C));
+        assert_eq!(Language::from_name("cpp"), Some(Language::Cpp));
+        assert_eq!(Language::from_name("dart"), Some(Language::Dart));
+        assert_eq!(Language::from_name("go"), Some(Language::Go));
+        assert_eq!(Language::from_name("python"), Some(Language::Python));
+        assert_eq!(Language::from_name("rust"), Some(Language::Rust));
+        assert_eq!(Language::from_name("typescript"), Some(Language::TypeScript));
+        assert_eq!(Language::from_name("Rust"), None);
+        assert_eq!(Language::from_name("ts"), None);
+    }
+
+    #[test]
+    fn escapes_reserved_words() {
+        assert_eq!(escape_keyword("type", Language::Rust), "type_");
+        assert_eq!(escape_keyword("Self", Language::Rust), "Self_");
+        assert_eq!(escape_keyword("self_", Language::Rust), "self_");
+        assert_eq!(escape_keyword("type", Language::Go), "type_");
+        assert_eq!(escape_keyword("None", Language::Python), "None_");
+        assert_eq!(escape_keyword("none", Language::Python), "none");
+        assert_eq!(escape_keyword("namespace", Language::Cpp), "namespace_");
+        assert_eq!(escape_keyword("namespace", Language::C), "namespace");
+        assert_eq!(escape_keyword("delete", Language::TypeScript), "delete_");
+        assert_eq!(escape_keyword("interface", Language::TypeScript), "interface_");
+        // Contextual keywords and built-in type names are valid identifiers.
+        for name in &["type", "string", "number", "of", "from", "get", "set", "constructor"] {
+            assert_eq!(escape_keyword(name, Language::TypeScript), *name);
+        }
+    }
+}
@@ -0,0 +1,278 @@
+//! A client that calls protocol methods by name with JSON arguments, without generated bindings.
+
+use {
+    crate::{
+        transport::Transport,
+        visit::DeclLookup,
+        wire::{self, decode_message, encode_message, Message, TransactionHeader},
+        Method, Protocol,
+    },
+    serde_json::Value,
+    std::{collections::VecDeque, fmt, io},
+};
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The protocol has no method with the given name.
+    UnknownMethod(String),
+    /// The method is an event, so it can't be called.
+    NotCallable(String),
+    /// The method has no ordinal in the IR.
+    MissingOrdinal(String),
+    Wire(wire::Error),
+    Transport(io::Error),
+    /// A message arrived whose ordinal doesn't belong to any method of the protocol.
+    UnexpectedMessage {
+        txid: u32,
+        ordinal: u64,
+    },
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientError::UnknownMethod(name) => write!(f, "unknown method `{}`", name),
+            ClientError::NotCallable(name) => write!(f, "`{}` is an event", name),
+            ClientError::MissingOrdinal(name) => write!(f, "`{}` has no ordinal", name),
+            ClientError::Wire(err) => write!(f, "{}", err),
+            ClientError::Transport(err) => write!(f, "transport error: {}", err),
+            ClientError::UnexpectedMessage { txid, ordinal } => {
+                write!(f, "unexpected message with txid {:#x} and ordinal {:#x}", txid, ordinal)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<wire::Error> for ClientError {
+    fn from(err: wire::Error) -> Self {
+        ClientError::Wire(err)
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(err: io::Error) -> Self {
+        ClientError::Transport(err)
+    }
+}
+
+/// An event received from the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub method: String,
+    pub value: Value,
+}
+
+/// Returns the ordinal a method's messages are sent with.
+pub fn method_ordinal(method: &Method) -> Result<u64, ClientError> {
+    method
+        .ordinal
+        .or(*method.generated_ordinal)
+        .ok_or_else(|| ClientError::MissingOrdinal(method.name.inner.clone()))
+}
+
+/// A client for one protocol, calling methods by name over a [`Transport`].
+///
+/// Arguments and results are JSON objects keyed by parameter name, in the representation used by
+/// [`wire`]. Events that arrive while waiting for a response are queued and can be read with
+/// [`DynamicClient::next_event`].
+pub struct DynamicClient<'a, L: ?Sized, T> {
+    lookup: &'a L,
+    protocol: &'a Protocol,
+    transport: T,
+    next_txid: u32,
+    events: VecDeque<Event>,
+}
+
+impl<'a, L: DeclLookup + ?Sized, T: Transport> DynamicClient<'a, L, T> {
+    /// Creates a client for `protocol`, resolving the types it uses through `lookup`.
+    pub fn new(lookup: &'a L, protocol: &'a Protocol, transport: T) -> Self {
+        DynamicClient { lookup, protocol, transport, next_txid: 1, events: VecDeque::new() }
+    }
+
+    pub fn protocol(&self) -> &'a Protocol {
+        self.protocol
+    }
+
+    pub fn transport(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    pub fn method(&self, name: &str) -> Result<&'a Method, ClientError> {
+        self.protocol
+            .methods
+            .iter()
+            .map(|method| &**method)
+            .find(|method| *method.name == name)
+            .ok_or_else(|| ClientError::UnknownMethod(name.to_string()))
+    }
+
+    /// Encodes a request for the method `name` with transaction id `txid`.
+    pub fn encode_request(
+        &self,
+        name: &str,
+        txid: u32,
+        args: &Value,
+    ) -> Result<Message, ClientError> {
+        let method = self.method(name)?;
+        let request =
+            method.request.as_ref().ok_or_else(|| ClientError::NotCallable(name.to_string()))?;
+        let header = TransactionHeader::new(txid, method_ordinal(method)?);
+        Ok(encode_message(self.lookup, &header, request, args)?)
+    }
+
+    /// Decodes a response to, or event of, the method `name`.
+    pub fn decode_response(&self, name: &str, message: &Message) -> Result<Value, ClientError> {
+        let method = self.method(name)?;
+        let response = method.response.as_ref().ok_or_else(|| {
+            let header = TransactionHeader::decode(&message.bytes).ok();
+            ClientError::UnexpectedMessage {
+                txid: header.map_or(0, |header| header.txid),
+                ordinal: header.map_or(0, |header| header.ordinal),
+            }
+        })?;
+        Ok(decode_message(self.lookup, response, &message.bytes, &message.handles)?.value)
+    }
+
+    fn method_by_ordinal(&self, ordinal: u64) -> Option<&'a Method> {
+        self.protocol.methods.iter().map(|method| &**method).find(|method| {
+            *method.ordinal == Some(ordinal) || *method.generated_ordinal == Some(ordinal)
+        })
+    }
+
+    /// Receives the next message, queueing it if it's an event.
+    fn receive(&mut self) -> Result<Option<(TransactionHeader, Message)>, ClientError> {
+        let message = self.transport.receive()?;
+        let header = TransactionHeader::decode(&message.bytes)?;
+        let unexpected =
+            ClientError::UnexpectedMessage { txid: header.txid, ordinal: header.ordinal };
+        let method = self.method_by_ordinal(header.ordinal).ok_or(unexpected)?;
+        if header.txid == 0 && method.is_event() {
+            let value = self.decode_response(&method.name, &message)?;
+            self.events.push_back(Event { method: method.name.inner.clone(), value });
+            return Ok(None);
+        }
+        Ok(Some((header, message)))
+    }
+
+    /// Calls the method `name` with `args`. For two-way methods this waits for and returns the
+    /// response; for one-way methods it returns `null` once the request is sent.
+    pub fn call(&mut self, name: &str, args: &Value) -> Result<Value, ClientError> {
+        let method = self.method(name)?;
+        if method.response.is_none() {
+            let request = self.encode_request(name, 0, args)?;
+            self.transport.send(request)?;
+            return Ok(Value::Null);
+        }
+        let txid = self.next_txid;
+        self.next_txid = self.next_txid.checked_add(1).unwrap_or(1);
+        let request = self.encode_request(name, txid, args)?;
+        self.transport.send(request)?;
+        loop {
+            if let Some((header, message)) = self.receive()? {
+                if header.txid != txid || Some(header.ordinal) != method_ordinal(method).ok() {
+                    return Err(ClientError::UnexpectedMessage {
+                        txid: header.txid,
+                        ordinal: header.ordinal,
+                    });
+                }
+                return self.decode_response(name, &message);
+            }
+        }
+    }
+
+    /// Returns the next event, waiting for one if none are queued.
+    pub fn next_event(&mut self) -> Result<Event, ClientError> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(event);
+            }
+            if let Some((header, _)) = self.receive()? {
+                return Err(ClientError::UnexpectedMessage {
+                    txid: header.txid,
+                    ordinal: header.ordinal,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{fixtures, transport::MemoryTransport, Library},
+        serde_json::json,
+    };
+
+    fn response(library: &Library, method: &str, txid: u32, value: Value) -> Message {
+        let method = library.protocols[0]
+            .methods
+            .iter()
+            .find(|candidate| *candidate.name == method)
+            .unwrap();
+        let header = TransactionHeader::new(txid, method_ordinal(method).unwrap());
+        encode_message(library, &header, method.response.as_ref().unwrap(), &value).unwrap()
+    }
+
+    #[test]
+    fn calls_method_and_queues_events() {
+        let library = fixtures::library("example");
+        let (transport, mut server) = MemoryTransport::pair();
+        let mut client = DynamicClient::new(&library, &library.protocols[0], transport);
+        server.send(response(&library, "OnEvent", 0, json!({"vmo": 7}))).unwrap();
+        server.send(response(&library, "Echo", 1, json!({"response": "hi"}))).unwrap();
+
+        let args = json!({"value": "hi", "p": {"x": 1, "y": 2}});
+        assert_eq!(client.call("Echo", &args).unwrap(), json!({"response": "hi"}));
+        let request = server.try_receive().unwrap().unwrap();
+        let header = TransactionHeader::decode(&request.bytes).unwrap();
+        assert_eq!((header.txid, header.ordinal), (1, 1234));
+        let echo = library.protocols[0].methods[0].request.as_ref().unwrap();
+        assert_eq!(decode_message(&library, echo, &request.bytes, &[]).unwrap().value, args);
+
+        let event = client.next_event().unwrap();
+        assert_eq!(event, Event { method: "OnEvent".to_string(), value: json!({"vmo": 7}) });
+    }
+
+    #[test]
+    fn rejects_unknown_and_uncallable_methods() {
+        let library = fixtures::library("example");
+        let (transport, _server) = MemoryTransport::pair();
+        let mut client = DynamicClient::new(&library, &library.protocols[0], transport);
+        match client.call("Missing", &json!({})) {
+            Err(ClientError::UnknownMethod(name)) => assert_eq!(name, "Missing"),
+            other => panic!("expected an unknown method, got {:?}", other),
+        }
+        match client.call("OnEvent", &json!({})) {
+            Err(ClientError::NotCallable(name)) => assert_eq!(name, "OnEvent"),
+            other => panic!("expected an uncallable method, got {:?}", other),
+        }
+        match client.call("Echo", &json!({"value": 5})) {
+            Err(ClientError::Wire(_)) => {}
+            other => panic!("expected a wire error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_mismatched_responses() {
+        let library = fixtures::library("example");
+        let (transport, mut server) = MemoryTransport::pair();
+        let mut client = DynamicClient::new(&library, &library.protocols[0], transport);
+        server.send(response(&library, "Echo", 9, json!({"response": null}))).unwrap();
+        let args = json!({"value": "", "p": {"x": 0, "y": 0}});
+        match client.call("Echo", &args) {
+            Err(ClientError::UnexpectedMessage { txid, ordinal }) => {
+                assert_eq!((txid, ordinal), (9, 1234))
+            }
+            other => panic!("expected an unexpected message, got {:?}", other),
+        }
+
+        drop(server);
+        match client.call("Echo", &args) {
+            Err(ClientError::Transport(err)) => assert_eq!(err.kind(), io::ErrorKind::BrokenPipe),
+            other => panic!("expected a transport error, got {:?}", other),
+        }
+    }
+}
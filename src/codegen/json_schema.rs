@@ -0,0 +1,426 @@
+//! Translation of declarations into JSON Schema (draft 2020-12), describing values in the JSON
+//! representation used by [`wire`](crate::wire), e.g. for validating configuration files.
+//!
+//! Each schema document refers to its declaration through `$ref` and defines it, along with
+//! every declaration it uses, under `$defs`, keyed by `library.Name`. Strings are bounded with
+//! `maxLength`, which JSON Schema counts in characters rather than the bytes FIDL counts, so
+//! strings with multi-byte characters may pass validation and still be too long to encode.
+//! Likewise, bits accept any integer of their underlying type, though the encoder rejects
+//! values with unknown bits set. Otherwise the schemas accept what the encoder does: enums as a
+//! member name or value, table fields as null when absent, and handles as nonzero numbers.
+
+use {
+    crate::{
+        attributes::Attributes,
+        layout::{table_fields, underlying_primitive},
+        visit::{Decl, DeclLookup, WithDeps},
+        Attribute, DeclPath, Library, PrimitiveSubtype, Spanned, Type, TypeKind,
+    },
+    serde_json::{json, Map, Value},
+};
+
+/// The JSON Schema dialect of the generated documents.
+pub const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+fn def_name(path: &DeclPath) -> String {
+    format!("{}.{}", path.library_name, path.decl_name)
+}
+
+fn reference(path: &DeclPath) -> Value {
+    json!({ "$ref": format!("#/$defs/{}", def_name(path)) })
+}
+
+fn integer_range(subtype: &PrimitiveSubtype) -> Option<(Value, Value)> {
+    Some(match subtype {
+        PrimitiveSubtype::Int8 => (i8::MIN.into(), i8::MAX.into()),
+        PrimitiveSubtype::Int16 => (i16::MIN.into(), i16::MAX.into()),
+        PrimitiveSubtype::Int32 => (i32::MIN.into(), i32::MAX.into()),
+        PrimitiveSubtype::Int64 => (i64::MIN.into(), i64::MAX.into()),
+        PrimitiveSubtype::UInt8 => (0.into(), u8::MAX.into()),
+        PrimitiveSubtype::UInt16 => (0.into(), u16::MAX.into()),
+        PrimitiveSubtype::UInt32 => (0.into(), u32::MAX.into()),
+        PrimitiveSubtype::UInt64 => (0.into(), u64::MAX.into()),
+        PrimitiveSubtype::Bool | PrimitiveSubtype::Float32 | PrimitiveSubtype::Float64 => {
+            return None
+        }
+    })
+}
+
+fn primitive(subtype: &PrimitiveSubtype) -> Value {
+    match subtype {
+        PrimitiveSubtype::Bool => json!({ "type": "boolean" }),
+        PrimitiveSubtype::Float32 | PrimitiveSubtype::Float64 => json!({ "type": "number" }),
+        _ => {
+            let (minimum, maximum) = integer_range(subtype).unwrap_or_default();
+            json!({ "type": "integer", "minimum": minimum, "maximum": maximum })
+        }
+    }
+}
+
+/// Handles and channel ends are represented by their handle numbers, which are never zero.
+fn handle() -> Value {
+    json!({ "type": "integer", "minimum": 1, "maximum": u32::MAX })
+}
+
+fn nullable(schema: Value) -> Value {
+    json!({ "oneOf": [schema, { "type": "null" }] })
+}
+
+fn with_doc(mut schema: Value, attributes: &[Spanned<Attribute>]) -> Value {
+    if let (Some(doc), Some(object)) = (attributes.doc(), schema.as_object_mut()) {
+        object.insert("description".to_string(), doc.trim().into());
+    }
+    schema
+}
+
+fn object(properties: Map<String, Value>, required: Vec<Value>) -> Value {
+    let mut schema = json!({ "type": "object", "properties": properties });
+    if !required.is_empty() {
+        schema["required"] = required.into();
+    }
+    schema["additionalProperties"] = false.into();
+    schema
+}
+
+/// Collects the definitions of the declarations used by a schema.
+struct Builder<'a, L: ?Sized> {
+    lookup: &'a L,
+    defs: Map<String, Value>,
+    pending: Vec<DeclPath>,
+}
+
+impl<L: DeclLookup + ?Sized> Builder<'_, L> {
+    fn r#type(&mut self, r#type: &Type) -> Value {
+        let schema = self.non_null(r#type);
+        if *r#type.nullable {
+            nullable(schema)
+        } else {
+            schema
+        }
+    }
+
+    fn non_null(&mut self, r#type: &Type) -> Value {
+        match &*r#type.kind {
+            TypeKind::Primitive { subtype } => primitive(subtype),
+            TypeKind::Handle { .. } | TypeKind::Request { .. } => handle(),
+            TypeKind::String { maybe_element_count, .. } => {
+                let mut schema = json!({ "type": "string" });
+                if let Some(count) = maybe_element_count {
+                    schema["maxLength"] = (**count).clone().into();
+                }
+                schema
+            }
+            TypeKind::Vector { element_type, maybe_element_count, .. } => {
+                let mut schema = json!({ "type": "array", "items": self.r#type(element_type) });
+                if let Some(count) = maybe_element_count {
+                    schema["maxItems"] = (**count).clone().into();
+                }
+                schema
+            }
+            TypeKind::Array { element_type, element_count, .. } => {
+                let mut schema = json!({ "type": "array", "items": self.r#type(element_type) });
+                if let Some(count) = &**element_count {
+                    schema["minItems"] = (**count).clone().into();
+                    schema["maxItems"] = (**count).clone().into();
+                }
+                schema
+            }
+            TypeKind::Identifier { identifier, .. } => {
+                match self.lookup.lookup_decl(identifier) {
+                    Some(Decl::Protocol(_)) => handle(),
+                    // Nothing is known about undeclared types, so any value is allowed.
+                    Some(Decl::Const(_)) | None => json!({}),
+                    Some(_) => {
+                        let name = def_name(identifier);
+                        if !self.defs.contains_key(&name) && !self.pending.contains(&**identifier) {
+                            self.pending.push(identifier.inner.clone());
+                        }
+                        reference(identifier)
+                    }
+                }
+            }
+            TypeKind::UnresolvedRequest { .. } | TypeKind::UnresolvedIdentifier { .. } => {
+                json!({})
+            }
+        }
+    }
+
+    /// Returns the schema defining `decl`, or `None` for protocols and consts.
+    fn decl(&mut self, decl: Decl<'_>) -> Option<Value> {
+        let schema = match decl {
+            Decl::Const(_) | Decl::Protocol(_) => return None,
+            Decl::Enum(decl) => {
+                // Enums are written either as a member name or as a member's value.
+                let names = decl.members.iter().map(|member| member.name.inner.clone().into());
+                let values = decl.members.iter().filter_map(|member| {
+                    let value = member.value.as_ref()?.integer_value()?;
+                    Some(if value < 0 {
+                        Value::from(value as i64)
+                    } else {
+                        Value::from(value as u64)
+                    })
+                });
+                json!({ "enum": names.chain(values).collect::<Vec<Value>>() })
+            }
+            Decl::Bits(bits) => {
+                // Bits are written either as a number or as a list of member names.
+                let names: Vec<Value> =
+                    bits.members.iter().map(|member| member.name.inner.clone().into()).collect();
+                let subtype = underlying_primitive(decl).unwrap_or(PrimitiveSubtype::UInt32);
+                json!({
+                    "anyOf": [
+                        primitive(&subtype),
+                        { "type": "array", "items": { "enum": names }, "uniqueItems": true },
+                    ]
+                })
+            }
+            Decl::Struct(decl) => {
+                let mut properties = Map::new();
+                let mut required = Vec::new();
+                for member in &decl.members {
+                    let schema = with_doc(self.r#type(&member.r#type), &member.attributes);
+                    properties.insert(member.name.inner.clone(), schema);
+                    if !*member.r#type.nullable {
+                        required.push(member.name.inner.clone().into());
+                    }
+                }
+                object(properties, required)
+            }
+            Decl::Table(decl) => {
+                let mut properties = Map::new();
+                // Absent fields may also be written as null.
+                for (_, name, r#type) in table_fields(decl) {
+                    properties.insert(name.inner.clone(), nullable(self.non_null(r#type)));
+                }
+                object(properties, Vec::new())
+            }
+            Decl::Union(decl) => {
+                let members = decl.members.iter().map(|member| (&member.name, &member.r#type));
+                self.one_of(members)
+            }
+            Decl::XUnion(decl) => {
+                let members = decl.members.iter().map(|member| (&member.name, &member.r#type));
+                self.one_of(members)
+            }
+        };
+        let mut schema = with_doc(schema, decl.attributes());
+        schema["title"] = decl.name().decl_name.clone().into();
+        Some(schema)
+    }
+
+    /// Returns a schema accepting an object with exactly one of `members`.
+    fn one_of<'b>(
+        &mut self,
+        members: impl Iterator<Item = (&'b Spanned<String>, &'b Spanned<Type>)>,
+    ) -> Value {
+        let variants: Vec<Value> = members
+            .map(|(name, r#type)| {
+                let mut properties = Map::new();
+                properties.insert(name.inner.clone(), self.r#type(r#type));
+                object(properties, vec![name.inner.clone().into()])
+            })
+            .collect();
+        json!({ "oneOf": variants })
+    }
+
+    /// Defines every pending declaration and the ones they use in turn.
+    fn define_pending(&mut self) {
+        while let Some(path) = self.pending.pop() {
+            let schema = self.lookup.lookup_decl(&path).and_then(|decl| self.decl(decl));
+            self.defs.insert(def_name(&path), schema.unwrap_or_else(|| json!({})));
+        }
+    }
+}
+
+/// Returns a self-contained schema document for values of the declaration at `path`, or `None`
+/// if it's unknown or isn't a type.
+pub fn decl_schema<L: DeclLookup + ?Sized>(lookup: &L, path: &DeclPath) -> Option<Value> {
+    let mut builder = Builder { lookup, defs: Map::new(), pending: Vec::new() };
+    // A placeholder keeps recursive references from defining the root a second time.
+    builder.defs.insert(def_name(path), Value::Null);
+    let schema = builder.decl(lookup.lookup_decl(path)?)?;
+    builder.defs.insert(def_name(path), schema);
+    builder.define_pending();
+    let mut defs: Vec<(String, Value)> = builder.defs.into_iter().collect();
+    defs.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut document = json!({ "$schema": DIALECT });
+    document["$ref"] = reference(path)["$ref"].take();
+    document["$defs"] = Value::Object(defs.into_iter().collect());
+    Some(document)
+}
+
+/// Returns a schema document for each type declaration in `library`, in declaration-list order.
+/// Declarations from `deps` are included in the documents that use them.
+pub fn emit(library: &Library, deps: &[Library]) -> Vec<(DeclPath, Value)> {
+    let lookup = WithDeps { library, deps };
+    library
+        .decl_names()
+        .into_iter()
+        .filter_map(|name| Some((name.inner.clone(), decl_schema(&lookup, name)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::fixtures::{self, identifier, library_json, struct_json},
+    };
+
+    fn def(library: &Library, decl_name: &str) -> Value {
+        let path = DeclPath { library_name: library.name.to_string(), decl_name: decl_name.into() };
+        let mut document = decl_schema(library, &path).unwrap();
+        document["$defs"][def_name(&path)].take()
+    }
+
+    #[test]
+    fn enums_accept_names_and_values() {
+        let library = fixtures::library("example");
+        assert_eq!(def(&library, "Color")["enum"], json!(["RED", "Green", 1, 2]));
+    }
+
+    #[test]
+    fn table_fields_accept_null() {
+        let library = fixtures::library("example");
+        let schema = def(&library, "Settings");
+        assert_eq!(
+            schema["properties"]["enabled"],
+            json!({ "oneOf": [{ "type": "boolean" }, { "type": "null" }] })
+        );
+        assert_eq!(schema.get("required"), None);
+    }
+
+    fn member(name: &str, value: u64) -> Value {
+        let value = json!({"kind": "numeric", "value": value.to_string()});
+        json!({"attributes": [], "name": name, "value": {"kind": "literal", "literal": value}})
+    }
+
+    /// `Record` has a bounded string, a nullable bounded vector, and a nullable reference to
+    /// itself; `Flags` is a `uint8` bits.
+    fn records() -> Library {
+        let nullable_record =
+            json!({"kind": "identifier", "identifier": "test.json/Record", "nullable": true});
+        let tags = json!({
+            "kind": "vector",
+            "element_type": {"kind": "string", "nullable": false},
+            "maybe_element_count": 3,
+            "nullable": true,
+        });
+        library_json(json!({
+            "name": "test.json",
+            "bits_declarations": [{
+                "attributes": [],
+                "type": {"kind": "primitive", "subtype": "uint8"},
+                "name": "test.json/Flags",
+                "members": [member("READ", 1), member("WRITE", 2)],
+            }],
+            "struct_declarations": [struct_json("test.json/Record", &[
+                ("name", json!({"kind": "string", "maybe_element_count": 10, "nullable": false})),
+                ("tags", tags),
+                ("next", nullable_record),
+                ("flags", identifier("test.json/Flags")),
+            ])],
+        }))
+    }
+
+    #[test]
+    fn integers_are_bounded_by_their_subtype() {
+        let range = |subtype| {
+            let schema = primitive(&subtype);
+            assert_eq!(schema["type"], "integer");
+            (schema["minimum"].clone(), schema["maximum"].clone())
+        };
+        assert_eq!(range(PrimitiveSubtype::Int8), (json!(-128), json!(127)));
+        assert_eq!(range(PrimitiveSubtype::Int16), (json!(-32768), json!(32767)));
+        assert_eq!(range(PrimitiveSubtype::Int32), (json!(i32::MIN), json!(i32::MAX)));
+        assert_eq!(range(PrimitiveSubtype::Int64), (json!(i64::MIN), json!(i64::MAX)));
+        assert_eq!(range(PrimitiveSubtype::UInt8), (json!(0), json!(255)));
+        assert_eq!(range(PrimitiveSubtype::UInt16), (json!(0), json!(65535)));
+        assert_eq!(range(PrimitiveSubtype::UInt32), (json!(0), json!(u32::MAX)));
+        assert_eq!(range(PrimitiveSubtype::UInt64), (json!(0), json!(u64::MAX)));
+        assert_eq!(primitive(&PrimitiveSubtype::Bool), json!({ "type": "boolean" }));
+        assert_eq!(primitive(&PrimitiveSubtype::Float32), json!({ "type": "number" }));
+        assert_eq!(primitive(&PrimitiveSubtype::Float64), json!({ "type": "number" }));
+    }
+
+    #[test]
+    fn structs_require_non_nullable_members() {
+        let library = records();
+        let schema = def(&library, "Record");
+        assert_eq!(schema["required"], json!(["name", "flags"]));
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(schema["properties"]["name"], json!({ "type": "string", "maxLength": 10 }));
+        assert_eq!(
+            schema["properties"]["tags"],
+            json!({
+                "oneOf": [
+                    { "type": "array", "items": { "type": "string" }, "maxItems": 3 },
+                    { "type": "null" },
+                ]
+            })
+        );
+        assert_eq!(
+            schema["properties"]["next"],
+            json!({ "oneOf": [{ "$ref": "#/$defs/test.json.Record" }, { "type": "null" }] })
+        );
+    }
+
+    #[test]
+    fn bits_accept_numbers_and_names() {
+        let library = records();
+        assert_eq!(
+            def(&library, "Flags"),
+            json!({
+                "anyOf": [
+                    { "type": "integer", "minimum": 0, "maximum": 255 },
+                    { "type": "array", "items": { "enum": ["READ", "WRITE"] }, "uniqueItems": true },
+                ],
+                "title": "Flags",
+            })
+        );
+    }
+
+    #[test]
+    fn xunions_accept_exactly_one_member() {
+        let library = fixtures::library("example");
+        assert_eq!(
+            def(&library, "Value")["oneOf"],
+            json!([
+                {
+                    "type": "object",
+                    "properties": { "num": { "type": "integer", "minimum": i64::MIN, "maximum": i64::MAX } },
+                    "required": ["num"],
+                    "additionalProperties": false,
+                },
+                {
+                    "type": "object",
+                    "properties": { "point": { "$ref": "#/$defs/test.example.Point" } },
+                    "required": ["point"],
+                    "additionalProperties": false,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn recursive_declarations_are_defined_once() {
+        let library = fixtures::library("example");
+        let path = DeclPath { library_name: "test.example".into(), decl_name: "Node".into() };
+        let document = decl_schema(&library, &path).unwrap();
+        assert_eq!(document["$schema"], DIALECT);
+        assert_eq!(document["$ref"], "#/$defs/test.example.Node");
+        let defs = document["$defs"].as_object().unwrap();
+        assert_eq!(defs.keys().collect::<Vec<_>>(), ["test.example.Color", "test.example.Node"]);
+        assert_eq!(
+            defs["test.example.Node"]["properties"]["children"],
+            json!({ "type": "array", "items": { "$ref": "#/$defs/test.example.Node" } })
+        );
+
+        let library = records();
+        let path = DeclPath { library_name: "test.json".into(), decl_name: "Record".into() };
+        let document = decl_schema(&library, &path).unwrap();
+        let defs = document["$defs"].as_object().unwrap();
+        assert_eq!(defs.keys().collect::<Vec<_>>(), ["test.json.Flags", "test.json.Record"]);
+        assert_eq!(defs["test.json.Record"]["title"], "Record");
+    }
+}
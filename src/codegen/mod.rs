@@ -0,0 +1,28 @@
+//! Generators that translate a library's IR into schemas and declarations for other languages.
+
+use {
+    crate::{attributes::Attributes, Attribute, Diagnostic, Spanned},
+    std::fmt::Write,
+};
+
+pub mod json_schema;
+pub mod protobuf;
+pub mod template;
+pub mod typescript;
+
+/// Generated source, along with diagnostics for anything that couldn't be translated exactly.
+#[derive(Debug, Clone)]
+pub struct Output {
+    pub source: String,
+    /// Warnings for declarations and members that were left out or approximated.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Writes the `Doc` attribute in `attributes` as line comments starting with `prefix`.
+fn write_doc(out: &mut String, attributes: &[Spanned<Attribute>], indent: &str, prefix: &str) {
+    if let Some(doc) = attributes.doc() {
+        for line in doc.trim_end().lines() {
+            writeln!(out, "{}{}{}", indent, prefix, line).unwrap();
+        }
+    }
+}
@@ -0,0 +1,565 @@
+//! Translation of a library into a proto3 schema, for bridging FIDL protocols to gRPC.
+//!
+//! Structs become messages with fields numbered in order, and tables become messages numbered
+//! by ordinal whose fields are all `optional`. Enums become enums, and unions and xunions become messages with a single `oneof`.
+//! Each protocol becomes a service whose methods take and return wrapper messages named
+//! `<Protocol><Method>Request` and `<Protocol><Method>Response`; one-way methods return
+//! `google.protobuf.Empty`. Methods whose wrapper names collide with a declaration are left
+//! out with an error.
+//!
+//! Handles, client and server ends, events, constants, and nested vectors have no protobuf
+//! equivalent and are left out with a warning, as are enum members that don't fit in an
+//! `int32`. Array sizes, bits, and the nullability of vectors are approximated, also with a
+//! warning.
+
+use {
+    super::{write_doc, Output},
+    crate::{
+        case::to_screaming_snake_case,
+        visit::{Decl, DeclLookup, WithDeps},
+        Attribute, DeclPath, Diagnostic, Library, MethodReqRes, PrimitiveSubtype, Span, Spanned,
+        TableMemberType, Type, TypeKind,
+    },
+    std::{collections::HashSet, convert::TryFrom, fmt::Write},
+};
+
+/// Returns the conventional path of the `.proto` file for `library_name`, e.g.
+/// `fuchsia/mem.proto` for `fuchsia.mem`.
+pub fn file_name(library_name: &str) -> String {
+    format!("{}.proto", library_name.replace('.', "/"))
+}
+
+fn primitive(subtype: &PrimitiveSubtype) -> &'static str {
+    match subtype {
+        PrimitiveSubtype::Bool => "bool",
+        PrimitiveSubtype::Int8 | PrimitiveSubtype::Int16 | PrimitiveSubtype::Int32 => "int32",
+        PrimitiveSubtype::Int64 => "int64",
+        PrimitiveSubtype::UInt8 | PrimitiveSubtype::UInt16 | PrimitiveSubtype::UInt32 => "uint32",
+        PrimitiveSubtype::UInt64 => "uint64",
+        PrimitiveSubtype::Float32 => "float",
+        PrimitiveSubtype::Float64 => "double",
+    }
+}
+
+struct Emitter<'a> {
+    lookup: WithDeps<'a>,
+    library: &'a Library,
+    imports: Vec<String>,
+    /// The names of messages, enums, and services emitted so far or declared in the library.
+    names: HashSet<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// A field declaration: its label (`optional`, `repeated`, or nothing) and its type.
+struct Field {
+    label: &'static str,
+    r#type: String,
+}
+
+impl Emitter<'_> {
+    fn unsupported(&mut self, site: &str, reason: &str, span: Option<Span>) {
+        self.diagnostics.push(Diagnostic::warning(
+            "proto-unsupported",
+            format!("`{}` was left out: {}", site, reason),
+            span,
+        ));
+    }
+
+    fn approximated(&mut self, site: &str, reason: &str, span: Option<Span>) {
+        self.diagnostics.push(Diagnostic::warning(
+            "proto-approximated",
+            format!("`{}` is approximated: {}", site, reason),
+            span,
+        ));
+    }
+
+    /// Claims `names` for the wrapper messages generated for the method `site`, reporting an
+    /// error and claiming none of them if a declaration or another wrapper already has one.
+    fn claim_wrappers(&mut self, site: &str, names: &[&str], span: Option<Span>) -> bool {
+        if let Some(name) = names.iter().find(|name| self.names.contains(**name)) {
+            self.diagnostics.push(Diagnostic::error(
+                "proto-name-collision",
+                format!(
+                    "`{}` was left out: its message `{}` collides with another declaration",
+                    site, name
+                ),
+                span,
+            ));
+            return false;
+        }
+        self.names.extend(names.iter().map(|name| name.to_string()));
+        true
+    }
+
+    /// Returns the name to refer to a declaration by, importing its file if it's in another
+    /// library.
+    fn decl_name(&mut self, path: &DeclPath) -> String {
+        if path.library_name == *self.library.name {
+            return path.decl_name.clone();
+        }
+        let import = file_name(&path.library_name);
+        if !self.imports.contains(&import) {
+            self.imports.push(import);
+        }
+        format!(".{}.{}", path.library_name, path.decl_name)
+    }
+
+    /// Returns the protobuf type of a singular value of `r#type`.
+    fn element(&mut self, r#type: &Type) -> Result<String, String> {
+        match &*r#type.kind {
+            TypeKind::Primitive { subtype } => Ok(primitive(subtype).to_string()),
+            TypeKind::String { .. } => Ok("string".to_string()),
+            TypeKind::Vector { element_type, .. } if is_byte(element_type) => {
+                Ok("bytes".to_string())
+            }
+            TypeKind::Vector { .. } | TypeKind::Array { .. } => {
+                Err("nested vectors and arrays have no protobuf equivalent".to_string())
+            }
+            TypeKind::Handle { .. } => Err("handles have no protobuf equivalent".to_string()),
+            TypeKind::Request { .. } => Err("server ends have no protobuf equivalent".to_string()),
+            TypeKind::Identifier { identifier, .. } => match self.lookup.lookup_decl(identifier) {
+                Some(Decl::Protocol(_)) => {
+                    Err("client ends have no protobuf equivalent".to_string())
+                }
+                Some(Decl::Bits(decl)) => match decl.r#type.as_ref().map(|r#type| &*r#type.kind) {
+                    Some(TypeKind::Primitive { subtype }) => Ok(primitive(subtype).to_string()),
+                    _ => Err(format!("the type of `{}` is unknown", **identifier)),
+                },
+                Some(Decl::Const(_)) | None => Err(format!("unknown type `{}`", **identifier)),
+                Some(_) => Ok(self.decl_name(identifier)),
+            },
+            TypeKind::UnresolvedRequest { .. } | TypeKind::UnresolvedIdentifier { .. } => {
+                Err("unresolved type".to_string())
+            }
+        }
+    }
+
+    /// Returns the field declaration for a member of type `r#type`, recording any
+    /// approximation against `site`.
+    fn field(&mut self, site: &str, r#type: &Spanned<Type>) -> Result<Field, String> {
+        match &*r#type.kind {
+            TypeKind::Vector { element_type, .. } if !is_byte(element_type) => {
+                let element = self.element(element_type)?;
+                if *r#type.nullable {
+                    self.approximated(site, "repeated fields can't be null", r#type.span);
+                }
+                Ok(Field { label: "repeated ", r#type: element })
+            }
+            TypeKind::Array { element_type, element_count, .. } => {
+                let element = self.element(element_type)?;
+                let count = element_count.as_ref().map(|count| count.to_string());
+                let reason = format!(
+                    "repeated fields can't have a fixed size ({})",
+                    count.unwrap_or_default()
+                );
+                self.approximated(site, &reason, r#type.span);
+                Ok(Field { label: "repeated ", r#type: element })
+            }
+            _ => {
+                let element = self.element(r#type)?;
+                let label = if *r#type.nullable { "optional " } else { "" };
+                Ok(Field { label, r#type: element })
+            }
+        }
+    }
+
+    /// Writes the fields of a message, numbered in order from 1. Fields that are left out keep
+    /// their numbers, so the numbers of the others don't depend on what's supported.
+    fn fields<'b>(
+        &mut self,
+        out: &mut String,
+        parent: &str,
+        fields: impl Iterator<Item = (&'b Spanned<String>, &'b Spanned<Type>)>,
+    ) {
+        for (number, (name, r#type)) in (1..).zip(fields) {
+            let site = format!("{}.{}", parent, **name);
+            match self.field(&site, r#type) {
+                Ok(field) => {
+                    writeln!(out, "  {}{} {} = {};", field.label, field.r#type, **name, number)
+                        .unwrap();
+                }
+                Err(reason) => self.unsupported(&site, &reason, name.span),
+            }
+        }
+    }
+
+    fn message(&mut self, out: &mut String, name: &str, message: &MethodReqRes) {
+        writeln!(out, "\nmessage {} {{", name).unwrap();
+        let params = message.parameters.iter().map(|param| (&param.name, &param.r#type));
+        self.fields(out, name, params);
+        writeln!(out, "}}").unwrap();
+    }
+
+    fn decl(&mut self, out: &mut String, decl: Decl<'_>) {
+        let name = &decl.name().decl_name;
+        let span = decl.name().span;
+        match decl {
+            Decl::Const(_) => self.unsupported(name, "constants have no protobuf equivalent", span),
+            Decl::Bits(_) => {
+                self.approximated(name, "bits are represented by their underlying integer", span)
+            }
+            Decl::Enum(decl) => {
+                let prefix = to_screaming_snake_case(name);
+                let mut values: Vec<(String, i32)> = Vec::new();
+                for member in &decl.members {
+                    let site = format!("{}.{}", name, *member.name);
+                    let value = member.value.as_ref().and_then(|value| value.integer_value());
+                    match value.and_then(|value| i32::try_from(value).ok()) {
+                        Some(value) => values.push((
+                            format!("{}_{}", prefix, to_screaming_snake_case(&member.name)),
+                            value,
+                        )),
+                        None => self.unsupported(
+                            &site,
+                            "the value doesn't fit in an int32",
+                            member.name.span,
+                        ),
+                    }
+                }
+                // proto3 enums must start with a zero value, which is their default.
+                match values.iter().position(|(_, value)| *value == 0) {
+                    Some(zero) => {
+                        let value = values.remove(zero);
+                        values.insert(0, value);
+                    }
+                    None => values.insert(0, (format!("{}_UNSPECIFIED", prefix), 0)),
+                }
+                write_doc(out, &decl.attributes, "", "//");
+                writeln!(out, "enum {} {{", name).unwrap();
+                for (value_name, value) in values {
+                    writeln!(out, "  {} = {};", value_name, value).unwrap();
+                }
+                writeln!(out, "}}").unwrap();
+                out.push('\n');
+            }
+            Decl::Protocol(decl) => {
+                let mut messages = String::new();
+                write_doc(out, &decl.attributes, "", "//");
+                writeln!(out, "service {} {{", name).unwrap();
+                for method in &decl.methods {
+                    let site = format!("{}.{}", name, *method.name);
+                    let request = match &method.request {
+                        Some(request) => request,
+                        None => {
+                            let reason = "events have no gRPC equivalent";
+                            self.unsupported(&site, reason, method.name.span);
+                            continue;
+                        }
+                    };
+                    let request_name = format!("{}{}Request", name, *method.name);
+                    let response_name = format!("{}{}Response", name, *method.name);
+                    let wrappers: &[&str] = match method.response {
+                        Some(_) => &[&request_name, &response_name],
+                        None => &[&request_name],
+                    };
+                    if !self.claim_wrappers(&site, wrappers, method.name.span) {
+                        continue;
+                    }
+                    self.message(&mut messages, &request_name, request);
+                    let response_name = match &method.response {
+                        Some(response) => {
+                            self.message(&mut messages, &response_name, response);
+                            response_name
+                        }
+                        None => {
+                            let import = "google/protobuf/empty.proto".to_string();
+                            if !self.imports.contains(&import) {
+                                self.imports.push(import);
+                            }
+                            "google.protobuf.Empty".to_string()
+                        }
+                    };
+                    write_doc(out, &method.attributes, "  ", "//");
+                    writeln!(
+                        out,
+                        "  rpc {}({}) returns ({});",
+                        *method.name, request_name, response_name
+                    )
+                    .unwrap();
+                }
+                writeln!(out, "}}").unwrap();
+                out.push_str(&messages);
+                out.push('\n');
+            }
+            Decl::Struct(decl) => {
+                write_doc(out, &decl.attributes, "", "//");
+                writeln!(out, "message {} {{", name).unwrap();
+                let members = decl.members.iter().map(|member| (&member.name, &member.r#type));
+                self.fields(out, name, members);
+                writeln!(out, "}}\n").unwrap();
+            }
+            Decl::Table(decl) => {
+                write_doc(out, &decl.attributes, "", "//");
+                writeln!(out, "message {} {{", name).unwrap();
+                for member in &decl.members {
+                    let ordinal = match &*member.ordinal {
+                        Some(ordinal) => ordinal.to_string(),
+                        None => continue,
+                    };
+                    match &member.member_type {
+                        TableMemberType::Reserved => {
+                            writeln!(out, "  reserved {};", ordinal).unwrap()
+                        }
+                        TableMemberType::Field { r#type, name: field_name, .. } => {
+                            let site = format!("{}.{}", name, **field_name);
+                            match self.field(&site, r#type) {
+                                // Table fields may always be absent, so every field that can
+                                // be is marked `optional`; repeated fields can't be.
+                                Ok(field) => {
+                                    let label = match field.label {
+                                        "repeated " => "repeated ",
+                                        _ => "optional ",
+                                    };
+                                    writeln!(
+                                        out,
+                                        "  {}{} {} = {};",
+                                        label, field.r#type, **field_name, ordinal
+                                    )
+                                    .unwrap()
+                                }
+                                Err(reason) => self.unsupported(&site, &reason, field_name.span),
+                            }
+                        }
+                    }
+                }
+                writeln!(out, "}}\n").unwrap();
+            }
+            Decl::Union(decl) => {
+                let members = decl.members.iter().map(|member| (&member.name, &member.r#type));
+                self.oneof(out, name, &decl.attributes, members);
+            }
+            Decl::XUnion(decl) => {
+                let members = decl.members.iter().map(|member| (&member.name, &member.r#type));
+                self.oneof(out, name, &decl.attributes, members);
+            }
+        }
+    }
+
+    fn oneof<'b>(
+        &mut self,
+        out: &mut String,
+        name: &str,
+        attributes: &[Spanned<Attribute>],
+        members: impl Iterator<Item = (&'b Spanned<String>, &'b Spanned<Type>)>,
+    ) {
+        write_doc(out, attributes, "", "//");
+        writeln!(out, "message {} {{", name).unwrap();
+        writeln!(out, "  oneof value {{").unwrap();
+        for (number, (member_name, r#type)) in (1..).zip(members) {
+            let site = format!("{}.{}", name, **member_name);
+            match self.field(&site, r#type) {
+                Ok(Field { label: "repeated ", .. }) => self.unsupported(
+                    &site,
+                    "repeated fields can't be part of a oneof",
+                    member_name.span,
+                ),
+                Ok(field) => {
+                    writeln!(out, "    {} {} = {};", field.r#type, **member_name, number).unwrap();
+                }
+                Err(reason) => self.unsupported(&site, &reason, member_name.span),
+            }
+        }
+        writeln!(out, "  }}").unwrap();
+        writeln!(out, "}}\n").unwrap();
+    }
+}
+
+fn is_byte(r#type: &Type) -> bool {
+    !*r#type.nullable
+        && matches!(&*r#type.kind, TypeKind::Primitive { subtype: PrimitiveSubtype::UInt8 })
+}
+
+/// Translates `library` into a proto3 schema in the package named after the library.
+/// Declarations from `deps` are referred to by their fully-qualified names and imported from
+/// the files named by [`file_name`].
+pub fn emit(library: &Library, deps: &[Library]) -> Output {
+    let mut emitter = Emitter {
+        lookup: WithDeps { library, deps },
+        library,
+        imports: Vec::new(),
+        // Constants aren't emitted, so they can't collide.
+        names: library
+            .decl_names()
+            .into_iter()
+            .filter(|name| !matches!(library.lookup(name), Some(Decl::Const(_))))
+            .map(|name| name.decl_name.clone())
+            .collect(),
+        diagnostics: Vec::new(),
+    };
+    let mut body = String::new();
+    for name in library.decl_names() {
+        if let Some(decl) = library.lookup(name) {
+            emitter.decl(&mut body, decl);
+        }
+    }
+    let mut source = String::new();
+    writeln!(source, "// Generated from the FIDL library {}. DO NOT EDIT.", *library.name).unwrap();
+    writeln!(source).unwrap();
+    writeln!(source, "syntax = \"proto3\";").unwrap();
+    writeln!(source).unwrap();
+    writeln!(source, "package {};", *library.name).unwrap();
+    writeln!(source).unwrap();
+    if !emitter.imports.is_empty() {
+        emitter.imports.sort();
+        for import in &emitter.imports {
+            writeln!(source, "import \"{}\";", import).unwrap();
+        }
+        writeln!(source).unwrap();
+    }
+    source.push_str(body.trim_end());
+    source.push('\n');
+    Output { source, diagnostics: emitter.diagnostics }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{fixtures, span::FileId},
+    };
+
+    fn span(start: u32) -> Option<Span> {
+        Some(Span { file_id: FileId(0), start, end: start + 1 })
+    }
+
+    #[test]
+    fn reports_enum_overflow_at_member() {
+        let mut library = fixtures::library("example");
+        let decl = &mut library.enums[0];
+        decl.name.span = span(1);
+        decl.members[1].name.span = span(2);
+        *decl.members[1].value = Some(
+            serde_json::from_str(
+                r#"{"kind": "literal", "literal": {"kind": "numeric", "value": "4294967295"}}"#,
+            )
+            .unwrap(),
+        );
+        let output = emit(&library, &[]);
+        let diagnostic = output
+            .diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.message.starts_with("`Color.Green`"))
+            .unwrap();
+        assert_eq!(diagnostic.span, span(2));
+        assert!(!output.source.contains("COLOR_GREEN"));
+    }
+
+    #[test]
+    fn reports_wrapper_collisions() {
+        let mut library = fixtures::library("example");
+        let mut wrapper = library.structs[0].clone();
+        wrapper.name.decl_name = "EchoProtocolEchoResponse".to_string();
+        library.structs.push(wrapper);
+        library.protocols[0].methods[0].name.span = span(3);
+        let output = emit(&library, &[]);
+        let collisions: Vec<_> = output
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.code == "proto-name-collision")
+            .map(|diagnostic| (diagnostic.message.as_str(), diagnostic.span))
+            .collect();
+        assert_eq!(
+            collisions,
+            [(
+                "`EchoProtocol.Echo` was left out: its message `EchoProtocolEchoResponse` \
+                 collides with another declaration",
+                span(3)
+            )]
+        );
+        assert!(!output.source.contains("rpc Echo"));
+        assert!(!output.source.contains("message EchoProtocolEchoRequest"));
+    }
+
+    #[test]
+    fn releases_request_name_when_response_collides() {
+        let mut library = fixtures::library("example");
+        let mut wrapper = library.structs[0].clone();
+        wrapper.name.decl_name = "EchoProtocolEchoResponse".to_string();
+        library.structs.push(wrapper);
+        // `EchoProtocolEch.o` wants the request name the colliding `EchoProtocol.Echo` gave up.
+        let mut protocol = library.protocols[0].clone();
+        protocol.name.decl_name = "EchoProtocolEch".to_string();
+        protocol.methods.truncate(1);
+        protocol.methods[0].name.inner = "o".to_string();
+        protocol.methods[0].response = None;
+        library.protocols.push(protocol);
+        let output = emit(&library, &[]);
+        let collisions = output
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.code == "proto-name-collision")
+            .count();
+        assert_eq!(collisions, 1);
+        assert!(output.source.contains(
+            "service EchoProtocolEch {\n  rpc o(EchoProtocolEchoRequest) returns (google.protobuf.Empty);\n}"
+        ));
+        assert!(output.source.contains("import \"google/protobuf/empty.proto\";\n"));
+    }
+
+    #[test]
+    fn maps_declarations() {
+        let output = emit(&fixtures::library("example"), &[]);
+        assert_eq!(
+            output.source,
+            r#"// Generated from the FIDL library test.example. DO NOT EDIT.
+
+syntax = "proto3";
+
+package test.example;
+
+enum Color {
+  COLOR_UNSPECIFIED = 0;
+  COLOR_RED = 1;
+  COLOR_GREEN = 2;
+}
+
+service EchoProtocol {
+  rpc Echo(EchoProtocolEchoRequest) returns (EchoProtocolEchoResponse);
+}
+
+message EchoProtocolEchoRequest {
+  string value = 1;
+  Point p = 2;
+}
+
+message EchoProtocolEchoResponse {
+  optional string response = 1;
+}
+
+message Point {
+  uint32 x = 1;
+  uint32 y = 2;
+}
+
+message Node {
+  uint32 a = 1;
+  repeated Node children = 2;
+  uint32 b = 3;
+  Color color = 4;
+}
+
+message Settings {
+  optional bool enabled = 1;
+  reserved 2;
+  optional string label = 3;
+}
+
+message Value {
+  oneof value {
+    int64 num = 1;
+    Point point = 2;
+  }
+}
+"#
+        );
+        let messages: Vec<_> =
+            output.diagnostics.iter().map(|diagnostic| diagnostic.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "`maxCount` was left out: constants have no protobuf equivalent",
+                "`EchoProtocol.OnEvent` was left out: events have no gRPC equivalent",
+            ]
+        );
+    }
+}
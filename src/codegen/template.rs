@@ -0,0 +1,593 @@
+//! A data model of a library for user-supplied code generation templates, and a generator that
+//! renders [MiniJinja](https://docs.rs/minijinja) templates against it (with the `minijinja`
+//! feature).
+//!
+//! The model is a plain serializable view of the IR: every declaration appears in
+//! `declaration_order`, so each one comes after the declarations it depends on, and every type
+//! records what its identifier resolves to. Its field names are part of this crate's API and only
+//! change along with [`MODEL_VERSION`]. Every field is always present, with absent values as
+//! `none`, so templates can be rendered with strict undefined checks.
+//!
+//! Templates are rendered with `library` (a [`LibraryModel`]) and `model_version` in scope, and
+//! can use these filters:
+//!
+//! * `to_snake_case`, `to_screaming_snake_case`, `to_upper_camel`, and `to_lower_camel`, which
+//!   convert between naming conventions,
+//! * `escape_keyword(language)`, which appends an underscore to reserved words of `c`, `cpp`,
+//!   `dart`, `go`, `python`, `rust`, or `typescript`.
+//!
+//! For example, this template declares a Go constant for each enum member:
+//!
+//! ```text
+//! {% for decl in library.declarations if decl.kind == "enum" %}
+//! {% for member in decl.members %}
+//! const {{ decl.name }}{{ member.name | to_upper_camel }} = {{ member.value }}
+//! {% endfor %}
+//! {% endfor %}
+//! ```
+
+use {
+    crate::{
+        attributes::Attributes,
+        layout::underlying_primitive,
+        visit::{Decl, DeclLookup, WithDeps},
+        Attribute, DeclPath, Library, MethodReqRes, PrimitiveSubtype, Spanned, TableMemberType,
+        Type, TypeKind,
+    },
+    serde::Serialize,
+};
+
+#[cfg(feature = "minijinja")]
+use {
+    crate::case::{
+        escape_keyword, to_lower_camel_case, to_screaming_snake_case, to_snake_case,
+        to_upper_camel_case, Language,
+    },
+    minijinja::{AutoEscape, Environment, ErrorKind, UndefinedBehavior},
+    std::fmt,
+};
+
+/// The version of the data model, incremented whenever a field is removed or changes meaning.
+pub const MODEL_VERSION: u32 = 1;
+
+/// An attribute, e.g. `{ name: "Transport", value: "Channel" }`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AttributeModel {
+    pub name: String,
+    /// The value, or `None` for a marker attribute without one.
+    pub value: Option<String>,
+}
+
+/// A type, with any declaration it refers to resolved.
+#[derive(Debug, Clone, Serialize)]
+pub struct TypeModel {
+    /// `primitive`, `string`, `vector`, `array`, `handle`, `request`, or `identifier`.
+    pub kind: String,
+    pub nullable: bool,
+    /// The subtype of a primitive (e.g. `uint32`) or handle (e.g. `vmo`).
+    pub subtype: Option<String>,
+    /// The element type of a vector or array.
+    pub element: Option<Box<TypeModel>>,
+    /// The size of an array, or the bound of a vector or string.
+    pub count: Option<u64>,
+    /// The fully-qualified name of the declaration an identifier refers to, or of the protocol
+    /// of a server end, e.g. `fuchsia.mem/Buffer`.
+    pub decl: Option<String>,
+    /// The kind of the declaration an identifier refers to (see [`DeclModel::kind`]), or `none`
+    /// if it isn't known.
+    pub decl_kind: Option<String>,
+    /// The type in FIDL syntax, with declarations in other libraries fully qualified.
+    pub fidl: String,
+}
+
+/// A member of a declaration: a field of a struct or table, a variant of a union or xunion, or a
+/// member of an enum or bits.
+#[derive(Debug, Clone, Serialize)]
+pub struct MemberModel {
+    /// The member's name, which is empty for reserved table members.
+    pub name: String,
+    pub doc: Option<String>,
+    pub attributes: Vec<AttributeModel>,
+    /// The type of a field or variant.
+    pub r#type: Option<TypeModel>,
+    /// The ordinal of a table or xunion member.
+    pub ordinal: Option<u64>,
+    /// The value of an enum or bits member in FIDL syntax.
+    pub value: Option<String>,
+    /// The default value of a struct or table field in FIDL syntax.
+    pub default: Option<String>,
+    /// Whether this is a reserved table member.
+    pub reserved: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ParameterModel {
+    pub name: String,
+    pub r#type: TypeModel,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MethodModel {
+    pub name: String,
+    pub doc: Option<String>,
+    pub attributes: Vec<AttributeModel>,
+    pub ordinal: Option<u64>,
+    /// The request parameters, or `none` for events.
+    pub request: Option<Vec<ParameterModel>>,
+    /// The response or event parameters, or `none` for one-way methods.
+    pub response: Option<Vec<ParameterModel>>,
+    pub is_event: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeclModel {
+    /// `const`, `bits`, `enum`, `protocol`, `struct`, `table`, `union`, or `xunion`.
+    pub kind: String,
+    /// The unqualified name, e.g. `Buffer`.
+    pub name: String,
+    pub library: String,
+    /// The fully-qualified name, e.g. `fuchsia.mem/Buffer`.
+    pub full_name: String,
+    pub doc: Option<String>,
+    pub attributes: Vec<AttributeModel>,
+    /// The type of a const, or the underlying primitive type of bits or an enum.
+    pub r#type: Option<TypeModel>,
+    /// The value of a const in FIDL syntax.
+    pub value: Option<String>,
+    pub members: Vec<MemberModel>,
+    pub methods: Vec<MethodModel>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryModel {
+    pub name: String,
+    pub doc: Option<String>,
+    pub attributes: Vec<AttributeModel>,
+    /// The names of the libraries this one depends on.
+    pub dependencies: Vec<String>,
+    /// The declarations of the library, in `declaration_order`. Declarations missing from it are
+    /// listed last.
+    pub declarations: Vec<DeclModel>,
+}
+
+fn kind_name(decl: Decl<'_>) -> &'static str {
+    match decl {
+        Decl::Const(_) => "const",
+        Decl::Bits(_) => "bits",
+        Decl::Enum(_) => "enum",
+        Decl::Protocol(_) => "protocol",
+        Decl::Struct(_) => "struct",
+        Decl::Table(_) => "table",
+        Decl::Union(_) => "union",
+        Decl::XUnion(_) => "xunion",
+    }
+}
+
+fn doc(attributes: &[Spanned<Attribute>]) -> Option<String> {
+    attributes.doc().map(str::to_string)
+}
+
+fn attribute_models(attributes: &[Spanned<Attribute>]) -> Vec<AttributeModel> {
+    attributes
+        .iter()
+        .map(|attribute| AttributeModel {
+            name: attribute.name.inner.clone(),
+            // Markers like `[Discoverable]` are written with an empty value in the IR.
+            value: attribute
+                .value
+                .as_ref()
+                .filter(|value| !value.is_empty())
+                .map(|value| value.inner.clone()),
+        })
+        .collect()
+}
+
+fn primitive_model(subtype: PrimitiveSubtype) -> TypeModel {
+    TypeModel {
+        kind: "primitive".to_string(),
+        nullable: false,
+        fidl: subtype.to_string(),
+        subtype: Some(subtype.to_string()),
+        element: None,
+        count: None,
+        decl: None,
+        decl_kind: None,
+    }
+}
+
+/// Builds models of types, resolving identifiers against a library and its dependencies.
+struct Resolver<'a> {
+    lookup: WithDeps<'a>,
+}
+
+impl Resolver<'_> {
+    fn r#type(&self, r#type: &Type) -> TypeModel {
+        let mut model = TypeModel {
+            kind: String::new(),
+            nullable: *r#type.nullable,
+            subtype: None,
+            element: None,
+            count: None,
+            decl: None,
+            decl_kind: None,
+            fidl: r#type.display_in(&self.lookup.library.name).to_string(),
+        };
+        model.kind = match &*r#type.kind {
+            TypeKind::Primitive { subtype } => {
+                model.subtype = Some(subtype.to_string());
+                "primitive"
+            }
+            TypeKind::String { maybe_element_count, .. } => {
+                model.count = maybe_element_count.as_ref().and_then(|count| count.as_u64());
+                "string"
+            }
+            TypeKind::Vector { element_type, maybe_element_count, .. } => {
+                model.element = Some(Box::new(self.r#type(element_type)));
+                model.count = maybe_element_count.as_ref().and_then(|count| count.as_u64());
+                "vector"
+            }
+            TypeKind::Array { element_type, element_count, .. } => {
+                model.element = Some(Box::new(self.r#type(element_type)));
+                model.count = element_count.as_ref().and_then(|count| count.as_u64());
+                "array"
+            }
+            TypeKind::Handle { subtype, .. } => {
+                model.subtype = Some(subtype.to_string());
+                "handle"
+            }
+            TypeKind::Request { subtype, .. } => {
+                model.decl = Some(subtype.to_string());
+                model.decl_kind = Some("protocol".to_string());
+                "request"
+            }
+            TypeKind::Identifier { identifier, .. } => {
+                model.decl = Some(identifier.to_string());
+                model.decl_kind =
+                    self.lookup.lookup_decl(identifier).map(|decl| kind_name(decl).to_string());
+                "identifier"
+            }
+            TypeKind::UnresolvedRequest { .. } => "request",
+            TypeKind::UnresolvedIdentifier { .. } => "identifier",
+        }
+        .to_string();
+        model
+    }
+
+    fn parameters(&self, message: &MethodReqRes) -> Vec<ParameterModel> {
+        message
+            .parameters
+            .iter()
+            .map(|param| ParameterModel {
+                name: param.name.inner.clone(),
+                r#type: self.r#type(&param.r#type),
+            })
+            .collect()
+    }
+
+    fn member(
+        &self,
+        attributes: &[Spanned<Attribute>],
+        name: &str,
+        r#type: Option<&Type>,
+    ) -> MemberModel {
+        MemberModel {
+            name: name.to_string(),
+            doc: doc(attributes),
+            attributes: attribute_models(attributes),
+            r#type: r#type.map(|r#type| self.r#type(r#type)),
+            ordinal: None,
+            value: None,
+            default: None,
+            reserved: false,
+        }
+    }
+
+    fn decl(&self, decl: Decl<'_>) -> DeclModel {
+        let path = decl.name();
+        let mut model = DeclModel {
+            kind: kind_name(decl).to_string(),
+            name: path.decl_name.clone(),
+            library: path.library_name.clone(),
+            full_name: path.to_string(),
+            doc: doc(decl.attributes()),
+            attributes: attribute_models(decl.attributes()),
+            r#type: underlying_primitive(decl).map(primitive_model),
+            value: None,
+            members: Vec::new(),
+            methods: Vec::new(),
+        };
+        match decl {
+            Decl::Const(decl) => {
+                model.r#type = Some(self.r#type(&decl.r#type));
+                model.value = Some(decl.value.to_string());
+            }
+            Decl::Bits(decl) => {
+                for member in &decl.members {
+                    let mut member_model = self.member(&member.attributes, &member.name, None);
+                    member_model.value = member.value.as_ref().map(|value| value.to_string());
+                    model.members.push(member_model);
+                }
+            }
+            Decl::Enum(decl) => {
+                for member in &decl.members {
+                    let mut member_model = self.member(&member.attributes, &member.name, None);
+                    member_model.value = member.value.as_ref().map(|value| value.to_string());
+                    model.members.push(member_model);
+                }
+            }
+            Decl::Protocol(decl) => {
+                for method in &decl.methods {
+                    model.methods.push(MethodModel {
+                        name: method.name.inner.clone(),
+                        doc: doc(&method.attributes),
+                        attributes: attribute_models(&method.attributes),
+                        ordinal: *method.ordinal,
+                        request: method.request.as_ref().map(|request| self.parameters(request)),
+                        response: method
+                            .response
+                            .as_ref()
+                            .map(|response| self.parameters(response)),
+                        is_event: method.is_event(),
+                    });
+                }
+            }
+            Decl::Struct(decl) => {
+                for member in &decl.members {
+                    let mut member_model =
+                        self.member(&member.attributes, &member.name, Some(&member.r#type));
+                    member_model.default =
+                        member.maybe_default_value.as_ref().map(|value| value.to_string());
+                    model.members.push(member_model);
+                }
+            }
+            Decl::Table(decl) => {
+                for member in &decl.members {
+                    let mut member_model = match &member.member_type {
+                        TableMemberType::Field { r#type, name, maybe_default_value } => {
+                            let mut member_model =
+                                self.member(&member.attributes, name, Some(r#type));
+                            member_model.default =
+                                maybe_default_value.as_ref().map(|value| value.to_string());
+                            member_model
+                        }
+                        TableMemberType::Reserved => {
+                            let mut member_model = self.member(&member.attributes, "", None);
+                            member_model.reserved = true;
+                            member_model
+                        }
+                    };
+                    member_model.ordinal =
+                        member.ordinal.as_ref().and_then(|ordinal| ordinal.as_u64());
+                    model.members.push(member_model);
+                }
+            }
+            Decl::Union(decl) => {
+                for member in &decl.members {
+                    let member_model =
+                        self.member(&member.attributes, &member.name, Some(&member.r#type));
+                    model.members.push(member_model);
+                }
+            }
+            Decl::XUnion(decl) => {
+                for member in &decl.members {
+                    let mut member_model =
+                        self.member(&member.attributes, &member.name, Some(&member.r#type));
+                    member_model.ordinal = *member.ordinal;
+                    model.members.push(member_model);
+                }
+            }
+        }
+        model
+    }
+}
+
+impl LibraryModel {
+    /// Builds the model of `library`, resolving identifiers against it and `deps`.
+    pub fn new(library: &Library, deps: &[Library]) -> Self {
+        let resolver = Resolver { lookup: WithDeps { library, deps } };
+        let mut paths: Vec<DeclPath> = library
+            .declaration_order
+            .iter()
+            .filter_map(|name| {
+                let mut parts = name.splitn(2, '/');
+                let library_name = parts.next()?.to_string();
+                let decl_name = parts.next()?.to_string();
+                Some(DeclPath { library_name, decl_name })
+            })
+            .collect();
+        for name in library.decl_names() {
+            if !paths.contains(name) {
+                paths.push(name.inner.clone());
+            }
+        }
+        LibraryModel {
+            name: library.name.inner.clone(),
+            doc: doc(&library.attributes),
+            attributes: attribute_models(&library.attributes),
+            dependencies: library.library_dependencies.iter().map(|dep| dep.name.clone()).collect(),
+            declarations: paths
+                .iter()
+                .filter_map(|path| Some(resolver.decl(library.lookup(path)?)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "minijinja")]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TemplateError {
+    /// A template couldn't be parsed.
+    Syntax { template: String, message: String },
+    /// No template was added under the name.
+    UnknownTemplate { template: String },
+    /// Rendering failed, e.g. because the template used an undefined value.
+    Render { template: String, message: String },
+}
+
+#[cfg(feature = "minijinja")]
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateError::Syntax { template, message } => {
+                write!(f, "syntax error in template `{}`: {}", template, message)
+            }
+            TemplateError::UnknownTemplate { template } => {
+                write!(f, "unknown template `{}`", template)
+            }
+            TemplateError::Render { template, message } => {
+                write!(f, "failed to render template `{}`: {}", template, message)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "minijinja")]
+impl std::error::Error for TemplateError {}
+
+/// Renders user-supplied templates against [`LibraryModel`]s.
+///
+/// Templates can include, import, and extend the other templates added to the same generator.
+/// Output isn't escaped, and trailing newlines are kept.
+#[cfg(feature = "minijinja")]
+pub struct Generator {
+    env: Environment<'static>,
+}
+
+#[cfg(feature = "minijinja")]
+impl Default for Generator {
+    fn default() -> Self {
+        Generator::new()
+    }
+}
+
+#[cfg(feature = "minijinja")]
+impl Generator {
+    pub fn new() -> Self {
+        let mut env = Environment::new();
+        env.set_auto_escape_callback(|_| AutoEscape::None);
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        env.set_keep_trailing_newline(true);
+        env.add_filter("to_snake_case", |name: &str| to_snake_case(name));
+        env.add_filter("to_screaming_snake_case", |name: &str| to_screaming_snake_case(name));
+        env.add_filter("to_upper_camel", |name: &str| to_upper_camel_case(name));
+        env.add_filter("to_lower_camel", |name: &str| to_lower_camel_case(name));
+        env.add_filter("escape_keyword", |name: &str, language: &str| {
+            let language = Language::from_name(language).ok_or_else(|| {
+                minijinja::Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("unknown language `{}`", language),
+                )
+            })?;
+            Ok::<_, minijinja::Error>(escape_keyword(name, language))
+        });
+        Generator { env }
+    }
+
+    /// Adds a template under `name`, replacing any template already added under it.
+    pub fn add_template(&mut self, name: &str, source: &str) -> Result<(), TemplateError> {
+        self.env.add_template_owned(name.to_string(), source.to_string()).map_err(|err| {
+            TemplateError::Syntax { template: name.to_string(), message: err.to_string() }
+        })
+    }
+
+    /// Renders the template added under `name` with `model` as `library`.
+    pub fn render(&self, name: &str, model: &LibraryModel) -> Result<String, TemplateError> {
+        let template = self
+            .env
+            .get_template(name)
+            .map_err(|_| TemplateError::UnknownTemplate { template: name.to_string() })?;
+        template
+            .render(minijinja::context! { library => model, model_version => MODEL_VERSION })
+            .map_err(|err| TemplateError::Render {
+                template: name.to_string(),
+                message: err.to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::fixtures};
+
+    #[test]
+    fn marker_attributes_have_no_value() {
+        let library = fixtures::library("attributes");
+        let model = LibraryModel::new(&library, &[]);
+        let attributes: Vec<_> = model.declarations[0]
+            .attributes
+            .iter()
+            .map(|attribute| (attribute.name.as_str(), attribute.value.as_deref()))
+            .collect();
+        assert_eq!(attributes, [("Discoverable", None), ("FragileBase", None)]);
+    }
+
+    #[cfg(feature = "minijinja")]
+    fn render(source: &str) -> Result<String, TemplateError> {
+        let mut generator = Generator::new();
+        generator.add_template("test", source)?;
+        generator.render("test", &LibraryModel::new(&fixtures::library("example"), &[]))
+    }
+
+    #[cfg(feature = "minijinja")]
+    #[test]
+    fn renders_templates() {
+        let source = "\
+// {{ library.name }} (model {{ model_version }})
+{%- for decl in library.declarations if decl.kind == \"enum\" %}
+{%- for member in decl.members %}
+const {{ decl.name }}{{ member.name | to_upper_camel }} = {{ member.value }}
+{%- endfor %}
+{% endfor %}";
+        assert_eq!(
+            render(source).unwrap(),
+            "// test.example (model 1)\nconst ColorRed = 1\nconst ColorGreen = 2\n"
+        );
+    }
+
+    #[cfg(feature = "minijinja")]
+    #[test]
+    fn converts_case() {
+        let source = "{{ 'HTTPServer2Config' | to_snake_case }} \
+            {{ 'fooBar' | to_screaming_snake_case }} \
+            {{ 'foo_bar' | to_upper_camel }} \
+            {{ 'FooBar' | to_lower_camel }}";
+        assert_eq!(render(source).unwrap(), "http_server2_config FOO_BAR FooBar fooBar");
+    }
+
+    #[cfg(feature = "minijinja")]
+    #[test]
+    fn escapes_keywords() {
+        let source = "{{ 'type' | escape_keyword('rust') }} {{ 'value' | escape_keyword('rust') }}";
+        assert_eq!(render(source).unwrap(), "type_ value");
+        match render("{{ 'type' | escape_keyword('cobol') }}") {
+            Err(TemplateError::Render { template, message }) => {
+                assert_eq!(template, "test");
+                assert!(message.contains("unknown language `cobol`"), "{}", message);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "minijinja")]
+    #[test]
+    fn undefined_values_are_errors() {
+        assert!(matches!(render("{{ library.nmae }}"), Err(TemplateError::Render { .. })));
+        assert!(matches!(render("{{ missing }}"), Err(TemplateError::Render { .. })));
+        // Absent values are `none` rather than undefined.
+        assert_eq!(
+            render(
+                "{% for decl in library.declarations if decl.kind == 'enum' %}\
+                {% if decl.value is none %}none{% endif %}{% endfor %}"
+            )
+            .unwrap(),
+            "none"
+        );
+        assert!(matches!(render("{% if %}"), Err(TemplateError::Syntax { .. })));
+        let generator = Generator::new();
+        let model = LibraryModel::new(&fixtures::library("example"), &[]);
+        assert_eq!(
+            generator.render("missing", &model),
+            Err(TemplateError::UnknownTemplate { template: "missing".to_string() })
+        );
+    }
+}
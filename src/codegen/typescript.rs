@@ -0,0 +1,470 @@
+//! Translation of a library into TypeScript declarations (`.d.ts`) describing values in the JSON
+//! representation used by [`wire`](crate::wire), for clients of a FIDL-to-JSON bridge.
+//!
+//! Structs become interfaces, and tables become interfaces whose fields are all optional.
+//! Unions and xunions become unions of single-property object types; xunions also admit the
+//! `{ $unknown_ordinal: number }` form decoded for members that aren't known. Enums become
+//! `const enum`s of member names, and bits become `const enum`s of their values. Each protocol
+//! becomes an interface named `<Protocol>Client`, whose methods take and resolve to payloads
+//! named `<Protocol><Method>Request`, `<Protocol><Method>Response`, and
+//! `<Protocol><Method>Event`. Methods whose payload names collide with a declaration are left
+//! out with an error.
+//!
+//! Handles, client ends, and server ends are their handle numbers. All numbers are `number`, so
+//! 64-bit values beyond 2^53 lose precision when parsed by `JSON.parse`. Constants aren't part of
+//! the JSON representation and are left out with a warning.
+
+use {
+    super::Output,
+    crate::{
+        attributes::Attributes,
+        visit::{Decl, DeclLookup, WithDeps},
+        Attribute, DeclPath, Diagnostic, Library, MethodReqRes, PrimitiveSubtype, Span, Spanned,
+        TableMemberType, Type, TypeKind,
+    },
+    std::{collections::HashSet, fmt::Write},
+};
+
+/// Returns the conventional name of the declaration file for `library_name`, e.g.
+/// `fuchsia.mem.d.ts`.
+pub fn file_name(library_name: &str) -> String {
+    format!("{}.d.ts", library_name)
+}
+
+/// Returns the name a library's declarations are imported under, e.g. `fuchsia_mem`.
+fn module_alias(library_name: &str) -> String {
+    library_name.replace('.', "_")
+}
+
+/// Writes the `Doc` attribute in `attributes` as a JSDoc comment.
+fn write_doc(out: &mut String, attributes: &[Spanned<Attribute>], indent: &str) {
+    if let Some(doc) = attributes.doc() {
+        writeln!(out, "{}/**", indent).unwrap();
+        for line in doc.trim_end().lines() {
+            writeln!(out, "{} *{}", indent, line.replace("*/", "*\\/")).unwrap();
+        }
+        writeln!(out, "{} */", indent).unwrap();
+    }
+}
+
+/// A member of an interface or union: its attributes, its name, and its type.
+type Field<'a> = (&'a [Spanned<Attribute>], &'a Spanned<String>, &'a Spanned<Type>);
+
+struct Emitter<'a> {
+    lookup: WithDeps<'a>,
+    library: &'a Library,
+    imports: Vec<String>,
+    /// The names of types emitted so far or declared in the library.
+    names: HashSet<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Emitter<'_> {
+    fn unsupported(&mut self, site: &str, reason: &str, span: Option<Span>) {
+        self.diagnostics.push(Diagnostic::warning(
+            "ts-unsupported",
+            format!("`{}` was left out: {}", site, reason),
+            span,
+        ));
+    }
+
+    /// Claims `names` for the payloads generated for the method `site`, reporting an error and
+    /// claiming none of them if a declaration or another payload already has one.
+    fn claim_payloads(&mut self, site: &str, names: &[&str], span: Option<Span>) -> bool {
+        if let Some(name) = names.iter().find(|name| self.names.contains(**name)) {
+            self.diagnostics.push(Diagnostic::error(
+                "ts-name-collision",
+                format!(
+                    "`{}` was left out: its payload `{}` collides with another declaration",
+                    site, name
+                ),
+                span,
+            ));
+            return false;
+        }
+        self.names.extend(names.iter().map(|name| name.to_string()));
+        true
+    }
+
+    fn unknown(&mut self, site: &str, reason: &str, span: Option<Span>) -> String {
+        self.diagnostics.push(Diagnostic::warning(
+            "ts-approximated",
+            format!("`{}` is typed as `unknown`: {}", site, reason),
+            span,
+        ));
+        "unknown".to_string()
+    }
+
+    /// Returns the name to refer to a declaration by, importing its library if it's another one.
+    fn decl_name(&mut self, path: &DeclPath) -> String {
+        if path.library_name == *self.library.name {
+            return path.decl_name.clone();
+        }
+        if !self.imports.contains(&path.library_name) {
+            self.imports.push(path.library_name.clone());
+        }
+        format!("{}.{}", module_alias(&path.library_name), path.decl_name)
+    }
+
+    /// Returns the TypeScript type of values of `r#type`, recording anything unknown against
+    /// `site`.
+    fn r#type(&mut self, site: &str, r#type: &Type, span: Option<Span>) -> String {
+        let non_null = match &*r#type.kind {
+            TypeKind::Primitive { subtype: PrimitiveSubtype::Bool } => "boolean".to_string(),
+            TypeKind::Primitive { .. } => "number".to_string(),
+            TypeKind::String { .. } => "string".to_string(),
+            TypeKind::Vector { element_type, .. } | TypeKind::Array { element_type, .. } => {
+                let element = self.r#type(site, element_type, span);
+                if element.contains(' ') {
+                    format!("({})[]", element)
+                } else {
+                    format!("{}[]", element)
+                }
+            }
+            TypeKind::Handle { .. } | TypeKind::Request { .. } => "number".to_string(),
+            TypeKind::Identifier { identifier, .. } => match self.lookup.lookup_decl(identifier) {
+                Some(Decl::Protocol(_)) => "number".to_string(),
+                Some(Decl::Const(_)) | None => {
+                    self.unknown(site, &format!("unknown type `{}`", **identifier), span)
+                }
+                Some(_) => self.decl_name(identifier),
+            },
+            TypeKind::UnresolvedRequest { .. } | TypeKind::UnresolvedIdentifier { .. } => {
+                self.unknown(site, "unresolved type", span)
+            }
+        };
+        if *r#type.nullable {
+            format!("{} | null", non_null)
+        } else {
+            non_null
+        }
+    }
+
+    /// Writes the fields of an interface, marking each one optional if `optional` is set.
+    fn fields<'b>(
+        &mut self,
+        out: &mut String,
+        parent: &str,
+        optional: bool,
+        fields: impl Iterator<Item = Field<'b>>,
+    ) {
+        for (attributes, name, r#type) in fields {
+            let site = format!("{}.{}", parent, **name);
+            let r#type = self.r#type(&site, r#type, name.span);
+            write_doc(out, attributes, "  ");
+            let marker = if optional { "?" } else { "" };
+            writeln!(out, "  {}{}: {};", **name, marker, r#type).unwrap();
+        }
+    }
+
+    fn payload(&mut self, out: &mut String, name: &str, message: &MethodReqRes) {
+        writeln!(out, "export interface {} {{", name).unwrap();
+        let params = message.parameters.iter().map(|param| (&[][..], &param.name, &param.r#type));
+        self.fields(out, name, false, params);
+        writeln!(out, "}}\n").unwrap();
+    }
+
+    fn decl(&mut self, out: &mut String, decl: Decl<'_>) {
+        let name = &decl.name().decl_name;
+        let span = decl.name().span;
+        match decl {
+            Decl::Const(_) => {
+                self.unsupported(name, "constants aren't part of the JSON representation", span)
+            }
+            Decl::Enum(decl) => {
+                write_doc(out, &decl.attributes, "");
+                writeln!(out, "export const enum {} {{", name).unwrap();
+                for member in &decl.members {
+                    write_doc(out, &member.attributes, "  ");
+                    writeln!(out, "  {} = \"{}\",", *member.name, *member.name).unwrap();
+                }
+                writeln!(out, "}}\n").unwrap();
+            }
+            Decl::Bits(decl) => {
+                write_doc(out, &decl.attributes, "");
+                writeln!(out, "export const enum {} {{", name).unwrap();
+                for member in &decl.members {
+                    let site = format!("{}.{}", name, *member.name);
+                    match member.value.as_ref().and_then(|value| value.integer_value()) {
+                        Some(value) => {
+                            write_doc(out, &member.attributes, "  ");
+                            writeln!(out, "  {} = {},", *member.name, value).unwrap();
+                        }
+                        None => self.unsupported(&site, "the value is unknown", member.name.span),
+                    }
+                }
+                writeln!(out, "}}\n").unwrap();
+            }
+            Decl::Struct(decl) => {
+                write_doc(out, &decl.attributes, "");
+                writeln!(out, "export interface {} {{", name).unwrap();
+                let members = decl
+                    .members
+                    .iter()
+                    .map(|member| (&member.attributes[..], &member.name, &member.r#type));
+                self.fields(out, name, false, members);
+                writeln!(out, "}}\n").unwrap();
+            }
+            Decl::Table(decl) => {
+                write_doc(out, &decl.attributes, "");
+                writeln!(out, "export interface {} {{", name).unwrap();
+                let members = decl.members.iter().filter_map(|member| match &member.member_type {
+                    TableMemberType::Field { r#type, name, .. } => {
+                        member.ordinal.as_ref()?;
+                        Some((&member.attributes[..], name, r#type))
+                    }
+                    TableMemberType::Reserved => None,
+                });
+                self.fields(out, name, true, members);
+                writeln!(out, "}}\n").unwrap();
+            }
+            Decl::Union(decl) => {
+                let members = decl
+                    .members
+                    .iter()
+                    .map(|member| (&member.attributes[..], &member.name, &member.r#type));
+                self.union(out, name, &decl.attributes, members, false);
+            }
+            Decl::XUnion(decl) => {
+                let members = decl
+                    .members
+                    .iter()
+                    .map(|member| (&member.attributes[..], &member.name, &member.r#type));
+                self.union(out, name, &decl.attributes, members, true);
+            }
+            Decl::Protocol(decl) => {
+                let mut client = String::new();
+                write_doc(&mut client, &decl.attributes, "");
+                writeln!(client, "export interface {}Client {{", name).unwrap();
+                for method in &decl.methods {
+                    let site = format!("{}.{}", name, *method.name);
+                    let prefix = format!("{}{}", name, *method.name);
+                    let payloads: Vec<(String, &MethodReqRes)> =
+                        match (&method.request, &method.response) {
+                            (Some(request), Some(response)) => vec![
+                                (format!("{}Request", prefix), request),
+                                (format!("{}Response", prefix), response),
+                            ],
+                            (Some(request), None) => vec![(format!("{}Request", prefix), request)],
+                            (None, Some(event)) => vec![(format!("{}Event", prefix), event)],
+                            (None, None) => continue,
+                        };
+                    let names: Vec<&str> =
+                        payloads.iter().map(|(payload_name, _)| payload_name.as_str()).collect();
+                    if !self.claim_payloads(&site, &names, method.name.span) {
+                        continue;
+                    }
+                    for (payload_name, message) in &payloads {
+                        self.payload(out, payload_name, message);
+                    }
+                    write_doc(&mut client, &method.attributes, "  ");
+                    match (&method.request, &method.response) {
+                        (None, _) => writeln!(
+                            client,
+                            "  on(event: \"{}\", listener: (event: {}) => void): void;",
+                            *method.name, names[0]
+                        ),
+                        (Some(_), None) => writeln!(
+                            client,
+                            "  {}(request: {}): Promise<void>;",
+                            *method.name, names[0]
+                        ),
+                        (Some(_), Some(_)) => writeln!(
+                            client,
+                            "  {}(request: {}): Promise<{}>;",
+                            *method.name, names[0], names[1]
+                        ),
+                    }
+                    .unwrap();
+                }
+                writeln!(client, "}}\n").unwrap();
+                out.push_str(&client);
+            }
+        }
+    }
+
+    /// Writes a union of single-property object types, one for each of `members`.
+    fn union<'b>(
+        &mut self,
+        out: &mut String,
+        name: &str,
+        attributes: &[Spanned<Attribute>],
+        members: impl Iterator<Item = Field<'b>>,
+        flexible: bool,
+    ) {
+        let mut variants = Vec::new();
+        for (member_attributes, member_name, r#type) in members {
+            let site = format!("{}.{}", name, **member_name);
+            let r#type = self.r#type(&site, r#type, member_name.span);
+            let mut variant = String::new();
+            write_doc(&mut variant, member_attributes, "  ");
+            write!(variant, "  | {{ {}: {} }}", **member_name, r#type).unwrap();
+            variants.push(variant);
+        }
+        if flexible {
+            variants.push("  | { $unknown_ordinal: number }".to_string());
+        }
+        write_doc(out, attributes, "");
+        if variants.is_empty() {
+            writeln!(out, "export type {} = never;\n", name).unwrap();
+        } else {
+            writeln!(out, "export type {} =\n{};\n", name, variants.join("\n")).unwrap();
+        }
+    }
+}
+
+/// Translates `library` into TypeScript declarations. Declarations from `deps` are imported
+/// from the files named by [`file_name`], under the library name with dots replaced by
+/// underscores.
+pub fn emit(library: &Library, deps: &[Library]) -> Output {
+    let mut emitter = Emitter {
+        lookup: WithDeps { library, deps },
+        library,
+        imports: Vec::new(),
+        // Constants aren't emitted, and protocols are emitted as `<Protocol>Client`.
+        names: library
+            .decl_names()
+            .into_iter()
+            .filter_map(|name| match library.lookup(name) {
+                Some(Decl::Const(_)) => None,
+                Some(Decl::Protocol(_)) => Some(format!("{}Client", name.decl_name)),
+                _ => Some(name.decl_name.clone()),
+            })
+            .collect(),
+        diagnostics: Vec::new(),
+    };
+    let mut body = String::new();
+    for name in library.decl_names() {
+        if let Some(decl) = library.lookup(name) {
+            emitter.decl(&mut body, decl);
+        }
+    }
+    let mut source = String::new();
+    writeln!(source, "// Generated from the FIDL library {}. DO NOT EDIT.", *library.name).unwrap();
+    writeln!(source).unwrap();
+    if !emitter.imports.is_empty() {
+        emitter.imports.sort();
+        for import in &emitter.imports {
+            writeln!(source, "import * as {} from \"./{}\";", module_alias(import), import)
+                .unwrap();
+        }
+        writeln!(source).unwrap();
+    }
+    source.push_str(body.trim_end());
+    source.push('\n');
+    Output { source, diagnostics: emitter.diagnostics }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::fixtures::{self, identifier, library_json, struct_json},
+        serde_json::json,
+    };
+
+    #[test]
+    fn emits_declarations() {
+        let mut library = fixtures::library("example");
+        library.structs[0].attributes =
+            serde_json::from_str(r#"[{"name": "Doc", "value": " A point. */\n"}]"#).unwrap();
+        let output = emit(&library, &[]);
+        assert_eq!(
+            output.source,
+            r#"// Generated from the FIDL library test.example. DO NOT EDIT.
+
+export const enum Color {
+  RED = "RED",
+  Green = "Green",
+}
+
+export interface EchoProtocolEchoRequest {
+  value: string;
+  p: Point;
+}
+
+export interface EchoProtocolEchoResponse {
+  response: string | null;
+}
+
+export interface EchoProtocolOnEventEvent {
+  vmo: number;
+}
+
+export interface EchoProtocolClient {
+  Echo(request: EchoProtocolEchoRequest): Promise<EchoProtocolEchoResponse>;
+  on(event: "OnEvent", listener: (event: EchoProtocolOnEventEvent) => void): void;
+}
+
+/**
+ * A point. *\/
+ */
+export interface Point {
+  x: number;
+  y: number;
+}
+
+export interface Node {
+  a: number;
+  children: Node[];
+  b: number;
+  color: Color;
+}
+
+export interface Settings {
+  enabled?: boolean;
+  label?: string;
+}
+
+export type Value =
+  | { num: number }
+  | { point: Point }
+  | { $unknown_ordinal: number };
+"#
+        );
+        let codes: Vec<_> = output.diagnostics.iter().map(|diagnostic| &*diagnostic.code).collect();
+        assert_eq!(codes, ["ts-unsupported"]);
+    }
+
+    #[test]
+    fn imports_dependencies() {
+        let dep = fixtures::library("example");
+        let library = library_json(json!({
+            "name": "test.other",
+            "struct_declarations": [struct_json(
+                "test.other/Line",
+                &[
+                    ("start", identifier("test.example/Point")),
+                    ("end", json!({"kind": "identifier", "identifier": "test.example/Point", "nullable": true})),
+                ],
+            )],
+        }));
+        let output = emit(&library, &[dep]);
+        assert!(output.source.contains("import * as test_example from \"./test.example\";\n"));
+        assert!(output.source.contains("  start: test_example.Point;\n"));
+        assert!(output.source.contains("  end: test_example.Point | null;\n"));
+    }
+
+    #[test]
+    fn reports_payload_collisions() {
+        let mut library = fixtures::library("example");
+        let mut payload = library.structs[0].clone();
+        payload.name.decl_name = "EchoProtocolEchoResponse".to_string();
+        library.structs.push(payload);
+        let output = emit(&library, &[]);
+        let collisions: Vec<_> = output
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.code == "ts-name-collision")
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(
+            collisions,
+            ["`EchoProtocol.Echo` was left out: its payload `EchoProtocolEchoResponse` collides \
+              with another declaration"]
+        );
+        assert!(!output.source.contains("Echo(request"));
+        assert!(!output.source.contains("interface EchoProtocolEchoRequest"));
+        assert_eq!(output.source.matches("interface EchoProtocolEchoResponse").count(), 1);
+    }
+}
@@ -0,0 +1,221 @@
+//! Emission of conformance tests for the C bindings.
+//!
+//! C values with out-of-line parts can't be written as literals, so the C tests work on bytes
+//! alone: success cases decode the golden bytes in place and re-encode them, checking that the
+//! result is unchanged, and decode-failure cases check that decoding fails. Encode-failure cases
+//! need a value to start from and are left out, as are cases with handles.
+
+use {
+    super::{Expectation, TestCase},
+    crate::{case::to_snake_case, DeclPath},
+    std::fmt::Write,
+};
+
+/// Returns the name of the coding table the C bindings generate for a declaration.
+pub fn coding_table(path: &DeclPath) -> String {
+    format!("{}_{}Table", path.library_name.replace('.', "_"), path.decl_name)
+}
+
+fn bytes_literal(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return "{0}".to_string();
+    }
+    let mut out = String::from("{\n");
+    for row in bytes.chunks(8) {
+        let row: Vec<String> = row.iter().map(|byte| format!("0x{:02x},", byte)).collect();
+        writeln!(out, "        {}", row.join(" ")).unwrap();
+    }
+    out.push_str("    }");
+    out
+}
+
+/// Emits a C test file exercising the C bindings of `library_name` with `cases`, for the
+/// `zxtest` framework.
+pub fn emit(library_name: &str, cases: &[TestCase]) -> String {
+    let mut out = String::new();
+    writeln!(out, "// Generated from conformance tests. DO NOT EDIT.").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "#include <lib/fidl/coding.h>").unwrap();
+    writeln!(out, "#include <string.h>").unwrap();
+    writeln!(out, "#include <zxtest/zxtest.h>").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "#include <{}/c/fidl.h>", library_name.replace('.', "/")).unwrap();
+    let mut externs: Vec<String> =
+        cases.iter().map(|case| coding_table(&case.decl_path(library_name))).collect();
+    externs.sort();
+    externs.dedup();
+    writeln!(out).unwrap();
+    for table in externs {
+        writeln!(out, "extern const fidl_type_t {};", table).unwrap();
+    }
+    for case in cases {
+        let table = coding_table(&case.decl_path(library_name));
+        let test_name = to_snake_case(&case.name);
+        writeln!(out).unwrap();
+        match &case.expectation {
+            Expectation::Success { .. } | Expectation::DecodeFailure { .. }
+                if !case.handles.is_empty() =>
+            {
+                writeln!(out, "// Skipped `{}`: handles are not supported.", case.name).unwrap();
+            }
+            Expectation::Success { bytes, .. } => {
+                writeln!(
+                    out,
+                    "TEST(Conformance, {test_name}_decode_encode) {{
+    uint8_t expected[] = {bytes};
+    FIDL_ALIGNDECL uint8_t buf[] = {bytes};
+    const char* error = NULL;
+    ASSERT_OK(fidl_decode(&{table}, buf, {len}, NULL, 0, &error), \"%s\", error);
+    uint32_t actual_handles = 0;
+    ASSERT_OK(fidl_encode(&{table}, buf, {len}, NULL, 0, &actual_handles, &error), \"%s\", error);
+    EXPECT_EQ(actual_handles, 0u);
+    EXPECT_BYTES_EQ(expected, buf, {len});
+}}",
+                    test_name = test_name,
+                    bytes = bytes_literal(bytes),
+                    table = table,
+                    len = bytes.len()
+                )
+                .unwrap();
+            }
+            Expectation::DecodeFailure { bytes, err } => {
+                writeln!(
+                    out,
+                    "TEST(Conformance, {test_name}_decode_failure) {{
+    // Expected error: {err}
+    FIDL_ALIGNDECL uint8_t buf[] = {bytes};
+    const char* error = NULL;
+    EXPECT_NOT_OK(fidl_decode(&{table}, buf, {len}, NULL, 0, &error));
+    EXPECT_NOT_NULL(error);
+}}",
+                    test_name = test_name,
+                    err = err,
+                    bytes = bytes_literal(bytes),
+                    table = table,
+                    len = bytes.len()
+                )
+                .unwrap();
+            }
+            Expectation::EncodeFailure { .. } => {
+                writeln!(out, "// Skipped `{}`: encode failures need a value.", case.name).unwrap();
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{wire::HandleInfo, HandleRights},
+        serde_json::json,
+    };
+
+    #[test]
+    fn emits_tests() {
+        let case = |name: &str, r#type: &str, expectation| TestCase {
+            name: name.to_string(),
+            r#type: r#type.to_string(),
+            handles: Vec::new(),
+            expectation,
+        };
+        let cases = [
+            case(
+                "PointSuccess",
+                "Point",
+                Expectation::Success {
+                    value: json!({"x": 1, "y": 2}),
+                    bytes: vec![1, 0, 0, 0, 2, 0, 0, 0, 3],
+                },
+            ),
+            case(
+                "PaddingFailure",
+                "test.other/Point",
+                Expectation::DecodeFailure {
+                    bytes: vec![1, 9],
+                    err: "NON_ZERO_PADDING".to_string(),
+                },
+            ),
+            case(
+                "EnumFailure",
+                "Color",
+                Expectation::EncodeFailure {
+                    value: json!(7),
+                    err: "INVALID_ENUM_VALUE".to_string(),
+                },
+            ),
+            case(
+                "Empty",
+                "Point",
+                Expectation::DecodeFailure { bytes: vec![], err: "OUT_OF_BOUNDS".to_string() },
+            ),
+            TestCase {
+                handles: vec![HandleInfo {
+                    handle: 1,
+                    obj_type: 3,
+                    rights: HandleRights::SAME_RIGHTS,
+                }],
+                ..case(
+                    "WithHandle",
+                    "Point",
+                    Expectation::Success { value: json!({"x": 1, "y": 2}), bytes: vec![1] },
+                )
+            },
+        ];
+        assert_eq!(
+            emit("test.example", &cases),
+            r#"// Generated from conformance tests. DO NOT EDIT.
+
+#include <lib/fidl/coding.h>
+#include <string.h>
+#include <zxtest/zxtest.h>
+
+#include <test/example/c/fidl.h>
+
+extern const fidl_type_t test_example_ColorTable;
+extern const fidl_type_t test_example_PointTable;
+extern const fidl_type_t test_other_PointTable;
+
+TEST(Conformance, point_success_decode_encode) {
+    uint8_t expected[] = {
+        0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+        0x03,
+    };
+    FIDL_ALIGNDECL uint8_t buf[] = {
+        0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+        0x03,
+    };
+    const char* error = NULL;
+    ASSERT_OK(fidl_decode(&test_example_PointTable, buf, 9, NULL, 0, &error), "%s", error);
+    uint32_t actual_handles = 0;
+    ASSERT_OK(fidl_encode(&test_example_PointTable, buf, 9, NULL, 0, &actual_handles, &error), "%s", error);
+    EXPECT_EQ(actual_handles, 0u);
+    EXPECT_BYTES_EQ(expected, buf, 9);
+}
+
+TEST(Conformance, padding_failure_decode_failure) {
+    // Expected error: NON_ZERO_PADDING
+    FIDL_ALIGNDECL uint8_t buf[] = {
+        0x01, 0x09,
+    };
+    const char* error = NULL;
+    EXPECT_NOT_OK(fidl_decode(&test_other_PointTable, buf, 2, NULL, 0, &error));
+    EXPECT_NOT_NULL(error);
+}
+
+// Skipped `EnumFailure`: encode failures need a value.
+
+TEST(Conformance, empty_decode_failure) {
+    // Expected error: OUT_OF_BOUNDS
+    FIDL_ALIGNDECL uint8_t buf[] = {0};
+    const char* error = NULL;
+    EXPECT_NOT_OK(fidl_decode(&test_example_PointTable, buf, 0, NULL, 0, &error));
+    EXPECT_NOT_NULL(error);
+}
+
+// Skipped `WithHandle`: handles are not supported.
+"#
+        );
+    }
+}
@@ -0,0 +1,231 @@
+//! Wire-format conformance tests written in a small GIDL-style language.
+//!
+//! Each test case pairs a value of a declared type with its expected encoding, or with the error
+//! its encoding or decoding is expected to fail with. Cases are checked against the IR using the
+//! encoder and decoder in [`wire`](crate::wire), and can be emitted as tests for the Rust and C
+//! bindings so they all share one set of golden bytes.
+
+use {
+    crate::{
+        visit::DeclLookup,
+        wire::{decode_value, encode_value, HandleInfo},
+        DeclPath, Type,
+    },
+    serde::Serialize,
+    serde_json::Value,
+};
+
+mod parse;
+pub use parse::{parse, ParseError};
+
+pub mod c;
+pub mod rust;
+
+/// What a test case expects to happen.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Expectation {
+    /// `value` encodes to exactly `bytes`, and `bytes` decodes to `value`.
+    Success { value: Value, bytes: Vec<u8> },
+    /// Encoding `value` fails with the error code `err`.
+    EncodeFailure { value: Value, err: String },
+    /// Decoding `bytes` fails with the error code `err`.
+    DecodeFailure { bytes: Vec<u8>, err: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TestCase {
+    pub name: String,
+    /// The name of the declaration the value has, either qualified or relative to the library
+    /// under test.
+    pub r#type: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub handles: Vec<HandleInfo>,
+    #[serde(flatten)]
+    pub expectation: Expectation,
+}
+
+impl TestCase {
+    /// Returns the path of the case's declaration, resolving unqualified names in `library_name`.
+    pub fn decl_path(&self, library_name: &str) -> DeclPath {
+        match self.r#type.rfind('/') {
+            Some(slash) => DeclPath {
+                library_name: self.r#type[..slash].to_string(),
+                decl_name: self.r#type[slash + 1..].to_string(),
+            },
+            None => {
+                DeclPath { library_name: library_name.to_string(), decl_name: self.r#type.clone() }
+            }
+        }
+    }
+}
+
+/// The result of checking one test case against the IR.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct Outcome {
+    pub name: String,
+    /// Why the case failed, or `None` if it passed.
+    pub failure: Option<String>,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(" ")
+}
+
+fn check_case<L: DeclLookup + ?Sized>(
+    lookup: &L,
+    library_name: &str,
+    case: &TestCase,
+) -> Result<(), String> {
+    let path = case.decl_path(library_name);
+    if lookup.lookup_decl(&path).is_none() {
+        return Err(format!("unknown type `{}`", path));
+    }
+    let r#type = Type::from_decl(path);
+    match &case.expectation {
+        Expectation::Success { value, bytes } => {
+            let encoded =
+                encode_value(lookup, &r#type, value).map_err(|err| format!("encode: {}", err))?;
+            if encoded.bytes != *bytes {
+                return Err(format!(
+                    "encoded bytes differ\n  expected: {}\n    actual: {}",
+                    hex(bytes),
+                    hex(&encoded.bytes)
+                ));
+            }
+            if encoded.handles.len() != case.handles.len() {
+                return Err(format!(
+                    "encoded {} handles, expected {}",
+                    encoded.handles.len(),
+                    case.handles.len()
+                ));
+            }
+            let decoded = decode_value(lookup, &r#type, bytes, &case.handles)
+                .map_err(|err| format!("decode: {}", err))?;
+            if decoded.value != *value {
+                return Err(format!(
+                    "decoded value differs\n  expected: {}\n    actual: {}",
+                    value, decoded.value
+                ));
+            }
+            Ok(())
+        }
+        Expectation::EncodeFailure { value, err } => match encode_value(lookup, &r#type, value) {
+            Ok(_) => Err(format!("encoding succeeded, expected {}", err)),
+            Err(actual) if actual.kind.code() == err => Ok(()),
+            Err(actual) => {
+                Err(format!("expected {}, got {} ({})", err, actual.kind.code(), actual))
+            }
+        },
+        Expectation::DecodeFailure { bytes, err } => {
+            match decode_value(lookup, &r#type, bytes, &case.handles) {
+                Ok(decoded) => {
+                    Err(format!("decoding succeeded with {}, expected {}", decoded.value, err))
+                }
+                Err(actual) if actual.kind.code() == err => Ok(()),
+                Err(actual) => {
+                    Err(format!("expected {}, got {} ({})", err, actual.kind.code(), actual))
+                }
+            }
+        }
+    }
+}
+
+/// Checks each case against the declarations in `lookup`, resolving unqualified type names in
+/// `library_name`.
+pub fn check<L: DeclLookup + ?Sized>(
+    lookup: &L,
+    library_name: &str,
+    cases: &[TestCase],
+) -> Vec<Outcome> {
+    cases
+        .iter()
+        .map(|case| Outcome {
+            name: case.name.clone(),
+            failure: check_case(lookup, library_name, case).err(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::fixtures, serde_json::json};
+
+    fn case(name: &str, r#type: &str, expectation: Expectation) -> TestCase {
+        TestCase {
+            name: name.to_string(),
+            r#type: r#type.to_string(),
+            handles: Vec::new(),
+            expectation,
+        }
+    }
+
+    fn success(value: Value, bytes: &[u8]) -> Expectation {
+        Expectation::Success { value, bytes: bytes.to_vec() }
+    }
+
+    fn encode_failure(value: Value, err: &str) -> Expectation {
+        Expectation::EncodeFailure { value, err: err.to_string() }
+    }
+
+    fn decode_failure(bytes: &[u8], err: &str) -> Expectation {
+        Expectation::DecodeFailure { bytes: bytes.to_vec(), err: err.to_string() }
+    }
+
+    fn failures(cases: &[TestCase]) -> Vec<(String, Option<String>)> {
+        let library = fixtures::library("example");
+        check(&library, "test.example", cases)
+            .into_iter()
+            .map(|outcome| (outcome.name, outcome.failure))
+            .collect()
+    }
+
+    #[test]
+    fn passes_expected_outcomes() {
+        let cases = [
+            case("Success", "Point", success(json!({"x": 1, "y": 2}), &[1, 0, 0, 0, 2, 0, 0, 0])),
+            case(
+                "Qualified",
+                "test.example/Color",
+                success(json!("Green"), &[2, 0, 0, 0, 0, 0, 0, 0]),
+            ),
+            case("EncodeFailure", "Color", encode_failure(json!(7), "INVALID_ENUM_VALUE")),
+            case(
+                "DecodeFailure",
+                "Point",
+                decode_failure(&[1, 9, 0, 0, 2, 0, 0, 0], "NON_ZERO_PADDING"),
+            ),
+        ];
+        for (name, failure) in failures(&cases) {
+            assert_eq!(failure, None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn reports_unexpected_outcomes() {
+        let cases = [
+            case(
+                "WrongBytes",
+                "Point",
+                success(json!({"x": 1, "y": 2}), &[1, 0, 0, 0, 3, 0, 0, 0]),
+            ),
+            case("Encodes", "Color", encode_failure(json!("RED"), "INVALID_ENUM_VALUE")),
+            case("WrongError", "Color", encode_failure(json!(7), "INVALID_VALUE")),
+            case("Decodes", "Point", decode_failure(&[1, 0, 0, 0, 2, 0, 0, 0], "NON_ZERO_PADDING")),
+            case("Unknown", "Missing", success(json!(null), &[])),
+        ];
+        let expected = [
+            "encoded bytes differ\n  expected: 01 00 00 00 03 00 00 00\n    actual: 01 00 00 00 02 00 00 00",
+            "encoding succeeded, expected INVALID_ENUM_VALUE",
+            "expected INVALID_VALUE, got INVALID_ENUM_VALUE (unknown enum value 7)",
+            "decoding succeeded with {\"x\":1,\"y\":2}, expected NON_ZERO_PADDING",
+            "unknown type `test.example/Missing`",
+        ];
+        for ((name, failure), (case, expected)) in
+            failures(&cases).into_iter().zip(cases.iter().zip(&expected))
+        {
+            assert_eq!(name, case.name);
+            assert_eq!(failure.as_deref(), Some(*expected), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,462 @@
+use {
+    super::{Expectation, TestCase},
+    crate::{
+        analysis::message_size::CHANNEL_MAX_MSG_BYTES, constant::parse_integer, wire::HandleInfo,
+    },
+    serde_json::{Map, Value},
+    std::{convert::TryFrom, fmt},
+};
+
+/// The most bytes a byte list may hold, the size of the largest channel message.
+const MAX_BYTES: usize = CHANNEL_MAX_MSG_BYTES as usize;
+
+/// An error in the syntax of a conformance file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// An identifier, which may contain `.` and `/` so that qualified names are one token.
+    Ident(String),
+    String(String),
+    /// The text of a numeric literal, including any sign.
+    Number(String),
+    Punct(char),
+    Eof,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "`{}`", ident),
+            Token::String(string) => write!(f, "{:?}", string),
+            Token::Number(number) => write!(f, "`{}`", number),
+            Token::Punct(c) => write!(f, "`{}`", c),
+            Token::Eof => write!(f, "end of file"),
+        }
+    }
+}
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl Lexer<'_> {
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError { line: self.line, column: self.column, message: message.into() }
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            match self.chars.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some('/') => {
+                    let mut ahead = self.chars.clone();
+                    ahead.next();
+                    if ahead.next() != Some('/') {
+                        return;
+                    }
+                    while self.chars.peek().is_some_and(|c| *c != '\n') {
+                        self.bump();
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Returns the next token and the position it starts at.
+    fn next(&mut self) -> Result<(Token, usize, usize), ParseError> {
+        self.skip_trivia();
+        let (line, column) = (self.line, self.column);
+        let c = match self.chars.peek() {
+            Some(c) => *c,
+            None => return Ok((Token::Eof, line, column)),
+        };
+        let token = if c.is_alphabetic() || c == '_' {
+            let mut ident = String::new();
+            while let Some(&c) = self.chars.peek() {
+                if !(c.is_alphanumeric() || c == '_' || c == '.' || c == '/') {
+                    break;
+                }
+                ident.push(c);
+                self.bump();
+            }
+            Token::Ident(ident)
+        } else if c.is_ascii_digit() || c == '-' {
+            let mut number = String::new();
+            while let Some(&c) = self.chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '+') {
+                    break;
+                }
+                number.push(c);
+                self.bump();
+            }
+            Token::Number(number)
+        } else if c == '"' {
+            self.bump();
+            let mut string = String::new();
+            loop {
+                match self.bump() {
+                    None => return Err(self.error("unterminated string")),
+                    Some('"') => break,
+                    Some('\\') => match self.bump() {
+                        Some('n') => string.push('\n'),
+                        Some('t') => string.push('\t'),
+                        Some('r') => string.push('\r'),
+                        Some('0') => string.push('\0'),
+                        Some(c @ '"') | Some(c @ '\\') => string.push(c),
+                        Some('u') => {
+                            let mut hex = String::new();
+                            if self.bump() != Some('{') {
+                                return Err(self.error("expected `{` after `\\u`"));
+                            }
+                            loop {
+                                match self.bump() {
+                                    Some('}') => break,
+                                    Some(c) => hex.push(c),
+                                    None => return Err(self.error("unterminated string")),
+                                }
+                            }
+                            let c = u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(std::char::from_u32)
+                                .ok_or_else(|| self.error("invalid unicode escape"))?;
+                            string.push(c);
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    },
+                    Some(c) => string.push(c),
+                }
+            }
+            Token::String(string)
+        } else if "{}[](),:=".contains(c) {
+            self.bump();
+            Token::Punct(c)
+        } else {
+            return Err(self.error(format!("unexpected character `{}`", c)));
+        };
+        Ok((token, line, column))
+    }
+}
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    token: Token,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Result<Self, ParseError> {
+        let mut lexer = Lexer { chars: source.chars().peekable(), line: 1, column: 1 };
+        let (token, line, column) = lexer.next()?;
+        Ok(Parser { lexer, token, line, column })
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError { line: self.line, column: self.column, message: message.into() }
+    }
+
+    fn advance(&mut self) -> Result<Token, ParseError> {
+        let (token, line, column) = self.lexer.next()?;
+        self.line = line;
+        self.column = column;
+        Ok(std::mem::replace(&mut self.token, token))
+    }
+
+    fn eat(&mut self, c: char) -> Result<bool, ParseError> {
+        if self.token == Token::Punct(c) {
+            self.advance()?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        if !self.eat(c)? {
+            return Err(self.error(format!("expected `{}`, found {}", c, self.token)));
+        }
+        Ok(())
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        match self.advance()? {
+            Token::Ident(ident) => Ok(ident),
+            token => Err(self.error(format!("expected an identifier, found {}", token))),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        match self.advance()? {
+            Token::String(string) => Ok(string),
+            token => Err(self.error(format!("expected a string, found {}", token))),
+        }
+    }
+
+    fn integer(&mut self) -> Result<i128, ParseError> {
+        match self.advance()? {
+            Token::Number(number) => parse_integer(&number)
+                .ok_or_else(|| self.error(format!("invalid integer `{}`", number))),
+            token => Err(self.error(format!("expected an integer, found {}", token))),
+        }
+    }
+
+    /// Parses a comma-separated list of items up to and including `close`.
+    fn list<T>(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = Vec::new();
+        while !self.eat(close)? {
+            items.push(item(self)?);
+            if !self.eat(',')? {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// Parses a value, returning the type name it was written with, if any.
+    fn value(&mut self) -> Result<(Option<String>, Value), ParseError> {
+        match self.advance()? {
+            Token::String(string) => Ok((None, string.into())),
+            Token::Number(number) => {
+                if let Some(value) = parse_integer(&number) {
+                    let value = if value < 0 {
+                        i64::try_from(value).ok().map(Value::from)
+                    } else {
+                        u64::try_from(value).ok().map(Value::from)
+                    };
+                    return value.map(|value| (None, value)).ok_or_else(|| {
+                        self.error(format!("integer `{}` is out of range", number))
+                    });
+                }
+                let value: f64 = number
+                    .parse()
+                    .map_err(|_| self.error(format!("invalid number `{}`", number)))?;
+                Ok((None, value.into()))
+            }
+            Token::Ident(ident) => match ident.as_str() {
+                "true" => Ok((None, true.into())),
+                "false" => Ok((None, false.into())),
+                "null" => Ok((None, Value::Null)),
+                _ if self.eat('{')? => Ok((Some(ident), self.object()?)),
+                // Bare identifiers are enum members.
+                _ => Ok((None, ident.into())),
+            },
+            Token::Punct('{') => Ok((None, self.object()?)),
+            Token::Punct('[') => {
+                let items = self.list(']', |parser| Ok(parser.value()?.1))?;
+                Ok((None, Value::Array(items)))
+            }
+            token => Err(self.error(format!("expected a value, found {}", token))),
+        }
+    }
+
+    /// Parses the members of an object after its opening brace.
+    fn object(&mut self) -> Result<Value, ParseError> {
+        let entries = self.list('}', |parser| {
+            let name = parser.ident()?;
+            parser.expect(':')?;
+            Ok((name, parser.value()?.1))
+        })?;
+        Ok(Value::Object(entries.into_iter().collect::<Map<_, _>>()))
+    }
+
+    /// Parses a list of bytes. Besides single bytes, items may be `num(value):size` for a
+    /// little-endian integer, `repeat(byte):count`, or `padding:count` for zeros.
+    fn bytes(&mut self) -> Result<Vec<u8>, ParseError> {
+        self.expect('[')?;
+        let mut len = 0;
+        let chunks = self.list(']', |parser| {
+            let (line, column) = (parser.line, parser.column);
+            let error = |message: String| ParseError { line, column, message };
+            let too_long = || error(format!("byte lists are limited to {} bytes", MAX_BYTES));
+            if let Token::Number(_) = parser.token {
+                let byte = parser.integer()?;
+                if len == MAX_BYTES {
+                    return Err(too_long());
+                }
+                len += 1;
+                return u8::try_from(byte)
+                    .map(|byte| vec![byte])
+                    .map_err(|_| error(format!("{} is not a byte", byte)));
+            }
+            let function = parser.ident()?;
+            let argument = if function == "padding" {
+                0
+            } else {
+                parser.expect('(')?;
+                let argument = parser.integer()?;
+                parser.expect(')')?;
+                argument
+            };
+            parser.expect(':')?;
+            let count = usize::try_from(parser.integer()?)
+                .map_err(|_| error("invalid count".to_string()))?;
+            if count > MAX_BYTES - len {
+                return Err(too_long());
+            }
+            len += count;
+            match function.as_str() {
+                "num" if count <= 8 => Ok((argument as u64).to_le_bytes()[..count].to_vec()),
+                "num" => Err(error("`num` is at most 8 bytes".to_string())),
+                "repeat" | "padding" => {
+                    let byte = u8::try_from(argument)
+                        .map_err(|_| error(format!("{} is not a byte", argument)))?;
+                    Ok(vec![byte; count])
+                }
+                _ => Err(error(format!("unknown byte function `{}`", function))),
+            }
+        })?;
+        Ok(chunks.concat())
+    }
+
+    fn test_case(&mut self) -> Result<TestCase, ParseError> {
+        let (line, column) = (self.line, self.column);
+        let kind = self.ident()?;
+        self.expect('(')?;
+        let name = self.string()?;
+        self.expect(')')?;
+        self.expect('{')?;
+        let mut r#type = None;
+        let mut value = None;
+        let mut bytes = None;
+        let mut handles = Vec::new();
+        let mut err = None;
+        self.list('}', |parser| {
+            let field = parser.ident()?;
+            parser.expect('=')?;
+            match field.as_str() {
+                "type" => r#type = Some(parser.ident()?),
+                "value" => {
+                    let (type_name, parsed) = parser.value()?;
+                    r#type = r#type.take().or(type_name);
+                    value = Some(parsed);
+                }
+                "bytes" => bytes = Some(parser.bytes()?),
+                "handles" => {
+                    parser.expect('[')?;
+                    handles = parser.list(']', |parser| {
+                        let handle = parser.integer()?;
+                        u32::try_from(handle)
+                            .map(HandleInfo::unknown)
+                            .map_err(|_| parser.error(format!("invalid handle {}", handle)))
+                    })?;
+                }
+                "err" => err = Some(parser.ident()?),
+                _ => return Err(parser.error(format!("unknown field `{}`", field))),
+            }
+            Ok(())
+        })?;
+        let missing = |field: &str| ParseError {
+            line,
+            column,
+            message: format!("`{}` case `{}` is missing `{}`", kind, name, field),
+        };
+        let r#type = r#type.ok_or_else(|| missing("type"))?;
+        let expectation = match kind.as_str() {
+            "success" => Expectation::Success {
+                value: value.ok_or_else(|| missing("value"))?,
+                bytes: bytes.ok_or_else(|| missing("bytes"))?,
+            },
+            "encode_failure" => Expectation::EncodeFailure {
+                value: value.ok_or_else(|| missing("value"))?,
+                err: err.ok_or_else(|| missing("err"))?,
+            },
+            "decode_failure" => Expectation::DecodeFailure {
+                bytes: bytes.ok_or_else(|| missing("bytes"))?,
+                err: err.ok_or_else(|| missing("err"))?,
+            },
+            _ => {
+                return Err(ParseError {
+                    line,
+                    column,
+                    message: format!("unknown test kind `{}`", kind),
+                })
+            }
+        };
+        Ok(TestCase { name, r#type, handles, expectation })
+    }
+}
+
+/// Parses a conformance file into test cases.
+///
+/// A file is a sequence of cases, each one of:
+///
+/// ```text
+/// success("Name") { value = Type { ... }, bytes = [ ... ] }
+/// encode_failure("Name") { value = Type { ... }, err = ERROR_CODE }
+/// decode_failure("Name") { type = Type, bytes = [ ... ], err = ERROR_CODE }
+/// ```
+///
+/// Values are written like FIDL literals: structs, tables, and unions as `{ member: value }`,
+/// optionally prefixed by their type name, enum members as bare identifiers, and vectors and
+/// arrays as `[ ... ]`. Any case may also list the `handles` that accompany its bytes. Error
+/// codes are those of [`ErrorKind::code`](crate::wire::ErrorKind::code), and `//` starts a
+/// comment.
+pub fn parse(source: &str) -> Result<Vec<TestCase>, ParseError> {
+    let mut parser = Parser::new(source)?;
+    let mut cases = Vec::new();
+    while parser.token != Token::Eof {
+        cases.push(parser.test_case()?);
+    }
+    Ok(cases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(list: &str) -> Result<Vec<u8>, String> {
+        let source =
+            format!("decode_failure(\"Case\") {{ type = Point, bytes = {}, err = E }}", list);
+        match parse(&source).map_err(|err| err.message)?.remove(0).expectation {
+            Expectation::DecodeFailure { bytes, .. } => Ok(bytes),
+            expectation => panic!("unexpected {:?}", expectation),
+        }
+    }
+
+    #[test]
+    fn parses_byte_functions() {
+        assert_eq!(bytes("[1, num(258):2, repeat(7):2, padding:1]"), Ok(vec![1, 2, 1, 7, 7, 0]));
+    }
+
+    #[test]
+    fn bounds_byte_lists() {
+        let too_long = Err("byte lists are limited to 65536 bytes".to_string());
+        assert_eq!(bytes("[repeat(1):18446744073709551615]"), too_long);
+        assert_eq!(bytes("[padding:65536, 0]"), too_long);
+        assert_eq!(bytes("[padding:65535, 0]").map(|bytes| bytes.len()), Ok(65536));
+    }
+}
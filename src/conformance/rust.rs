@@ -0,0 +1,331 @@
+//! Emission of conformance tests for the Rust bindings.
+
+use {
+    super::{Expectation, TestCase},
+    crate::{
+        case::{to_snake_case, to_upper_camel_case},
+        layout::table_fields,
+        visit::{Decl, DeclLookup},
+        DeclPath, PrimitiveSubtype, Type, TypeKind,
+    },
+    serde_json::Value,
+    std::fmt::Write,
+};
+
+/// Returns the name of the crate the Rust bindings for `library_name` are generated in.
+pub fn crate_name(library_name: &str) -> String {
+    format!("fidl_{}", library_name.replace('.', "_"))
+}
+
+fn type_name(path: &DeclPath, library_name: &str) -> String {
+    if path.library_name == library_name {
+        path.decl_name.clone()
+    } else {
+        format!("{}::{}", crate_name(&path.library_name), path.decl_name)
+    }
+}
+
+fn bytes_literal(bytes: &[u8]) -> String {
+    let mut out = String::from("[\n");
+    for row in bytes.chunks(8) {
+        let row: Vec<String> = row.iter().map(|byte| format!("0x{:02x},", byte)).collect();
+        writeln!(out, "        {}", row.join(" ")).unwrap();
+    }
+    out.push_str("    ]");
+    out
+}
+
+struct Emitter<'a, L: ?Sized> {
+    lookup: &'a L,
+    library_name: &'a str,
+}
+
+impl<L: DeclLookup + ?Sized> Emitter<'_, L> {
+    /// Returns a Rust expression constructing `value` as `r#type`, or an explanation of why it
+    /// can't be written.
+    fn value(&self, r#type: &Type, value: &Value) -> Result<String, String> {
+        if *r#type.nullable {
+            if value.is_null() {
+                return Ok("None".to_string());
+            }
+            let inner = self.non_null(r#type, value)?;
+            return Ok(match &*r#type.kind {
+                TypeKind::Identifier { .. } if !self.is_handle(r#type) => {
+                    format!("Some(Box::new({}))", inner)
+                }
+                _ => format!("Some({})", inner),
+            });
+        }
+        self.non_null(r#type, value)
+    }
+
+    fn is_handle(&self, r#type: &Type) -> bool {
+        match &*r#type.kind {
+            TypeKind::Identifier { identifier, .. } => {
+                matches!(self.lookup.lookup_decl(identifier), Some(Decl::Protocol(_)))
+            }
+            _ => false,
+        }
+    }
+
+    fn non_null(&self, r#type: &Type, value: &Value) -> Result<String, String> {
+        let mismatch = || format!("{} doesn't match its type", value);
+        match &*r#type.kind {
+            TypeKind::Primitive { subtype } => primitive(subtype, value).ok_or_else(mismatch),
+            TypeKind::Handle { .. } | TypeKind::Request { .. } => {
+                Err("handles are not supported".to_string())
+            }
+            TypeKind::String { .. } => {
+                Ok(format!("String::from({:?})", value.as_str().ok_or_else(mismatch)?))
+            }
+            TypeKind::Vector { element_type, .. } | TypeKind::Array { element_type, .. } => {
+                let items = value.as_array().ok_or_else(mismatch)?;
+                let items = items
+                    .iter()
+                    .map(|item| self.value(element_type, item))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let prefix = if let TypeKind::Vector { .. } = &*r#type.kind { "vec!" } else { "" };
+                Ok(format!("{}[{}]", prefix, items.join(", ")))
+            }
+            TypeKind::Identifier { identifier, .. } => self.decl(identifier, value),
+            TypeKind::UnresolvedRequest { .. } | TypeKind::UnresolvedIdentifier { .. } => {
+                Err("unresolved type".to_string())
+            }
+        }
+    }
+
+    fn decl(&self, identifier: &DeclPath, value: &Value) -> Result<String, String> {
+        let mismatch = || format!("{} doesn't match `{}`", value, identifier);
+        let decl = self
+            .lookup
+            .lookup_decl(identifier)
+            .ok_or_else(|| format!("unknown type `{}`", identifier))?;
+        let name = type_name(identifier, self.library_name);
+        let single = || {
+            let object = value.as_object().filter(|object| object.len() == 1);
+            object.and_then(|object| object.iter().next()).ok_or_else(mismatch)
+        };
+        match decl {
+            Decl::Const(_) => Err(mismatch()),
+            Decl::Protocol(_) => Err("handles are not supported".to_string()),
+            Decl::Enum(_) => {
+                let member = value.as_str().ok_or_else(mismatch)?;
+                Ok(format!("{}::{}", name, to_upper_camel_case(member)))
+            }
+            Decl::Bits(_) => {
+                let bits = value.as_u64().ok_or_else(mismatch)?;
+                Ok(format!("{}::from_bits({}).unwrap()", name, bits))
+            }
+            Decl::Struct(decl) => {
+                let object = value.as_object().ok_or_else(mismatch)?;
+                let members = decl
+                    .members
+                    .iter()
+                    .map(|member| {
+                        let member_value = object.get(member.name.as_str()).unwrap_or(&Value::Null);
+                        let value = self.value(&member.r#type, member_value)?;
+                        Ok(format!("{}: {}", to_snake_case(&member.name), value))
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                Ok(format!("{} {{ {} }}", name, members.join(", ")))
+            }
+            Decl::Table(decl) => {
+                let object = value.as_object().ok_or_else(mismatch)?;
+                let mut fields = Vec::new();
+                for (_, field_name, r#type) in table_fields(decl) {
+                    if let Some(field_value) =
+                        object.get(field_name.as_str()).filter(|value| !value.is_null())
+                    {
+                        let field = self.non_null(r#type, field_value)?;
+                        fields.push(format!("{}: Some({})", to_snake_case(field_name), field));
+                    }
+                }
+                fields.push(format!("..{}::new_empty()", name));
+                Ok(format!("{} {{ {} }}", name, fields.join(", ")))
+            }
+            Decl::Union(decl) => {
+                let (member_name, member_value) = single()?;
+                let member = decl
+                    .members
+                    .iter()
+                    .find(|member| *member.name == *member_name)
+                    .ok_or_else(mismatch)?;
+                let value = self.value(&member.r#type, member_value)?;
+                Ok(format!("{}::{}({})", name, to_upper_camel_case(member_name), value))
+            }
+            Decl::XUnion(decl) => {
+                let (member_name, member_value) = single()?;
+                let member = decl
+                    .members
+                    .iter()
+                    .find(|member| *member.name == *member_name)
+                    .ok_or_else(mismatch)?;
+                let value = self.value(&member.r#type, member_value)?;
+                Ok(format!("{}::{}({})", name, to_upper_camel_case(member_name), value))
+            }
+        }
+    }
+}
+
+fn primitive(subtype: &PrimitiveSubtype, value: &Value) -> Option<String> {
+    let suffix = match subtype {
+        PrimitiveSubtype::Bool => return value.as_bool().map(|value| value.to_string()),
+        PrimitiveSubtype::Float32 | PrimitiveSubtype::Float64 => {
+            let suffix = if let PrimitiveSubtype::Float32 = subtype { "f32" } else { "f64" };
+            return value.as_f64().map(|value| format!("{:?}{}", value, suffix));
+        }
+        PrimitiveSubtype::Int8 => "i8",
+        PrimitiveSubtype::Int16 => "i16",
+        PrimitiveSubtype::Int32 => "i32",
+        PrimitiveSubtype::Int64 => "i64",
+        PrimitiveSubtype::UInt8 => "u8",
+        PrimitiveSubtype::UInt16 => "u16",
+        PrimitiveSubtype::UInt32 => "u32",
+        PrimitiveSubtype::UInt64 => "u64",
+    };
+    match value {
+        Value::Number(number) if number.is_i64() || number.is_u64() => {
+            Some(format!("{}{}", number, suffix))
+        }
+        _ => None,
+    }
+}
+
+/// Returns whether `name` can follow `test_` in a function name.
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Emits a Rust test module exercising the Rust bindings of `library_name` with `cases`.
+///
+/// Success cases get an encode test comparing against the golden bytes and a decode test
+/// comparing against the value; failure cases check that encoding or decoding fails. Cases whose
+/// values can't be written in Rust, such as those with handles, are left out with a comment.
+pub fn emit<L: DeclLookup + ?Sized>(lookup: &L, library_name: &str, cases: &[TestCase]) -> String {
+    let emitter = Emitter { lookup, library_name };
+    let mut out = String::new();
+    writeln!(out, "// Generated from conformance tests. DO NOT EDIT.").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "#![cfg(test)]").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "use fidl::encoding::{{Decodable, Decoder, Encoder}};").unwrap();
+    writeln!(out, "use {}::*;", crate_name(library_name)).unwrap();
+    for case in cases {
+        let path = case.decl_path(library_name);
+        let name = type_name(&path, library_name);
+        let test_name = to_snake_case(&case.name);
+        let r#type = Type::from_decl(path);
+        writeln!(out).unwrap();
+        if !is_identifier(&test_name) {
+            writeln!(out, "// Skipped `{}`: the name isn't a valid identifier.", case.name)
+                .unwrap();
+            continue;
+        }
+        let value = match &case.expectation {
+            Expectation::Success { value, .. } | Expectation::EncodeFailure { value, .. } => {
+                match emitter.value(&r#type, value) {
+                    Ok(value) => Some(value),
+                    Err(reason) => {
+                        writeln!(out, "// Skipped `{}`: {}.", case.name, reason).unwrap();
+                        continue;
+                    }
+                }
+            }
+            Expectation::DecodeFailure { .. } => None,
+        };
+        match (&case.expectation, value) {
+            (Expectation::Success { bytes, .. }, Some(value)) => {
+                writeln!(
+                    out,
+                    "#[test]
+fn test_{test_name}_encode() {{
+    let value = &mut {value};
+    let bytes = &mut Vec::new();
+    Encoder::encode(bytes, &mut Vec::new(), value).unwrap();
+    assert_eq!(*bytes, &{bytes}[..]);
+}}
+
+#[test]
+fn test_{test_name}_decode() {{
+    let value = &mut {name}::new_empty();
+    let bytes = &mut {bytes};
+    Decoder::decode_into(bytes, &mut [], value).unwrap();
+    assert_eq!(*value, {value});
+}}",
+                    test_name = test_name,
+                    value = value,
+                    name = name,
+                    bytes = bytes_literal(bytes)
+                )
+                .unwrap();
+            }
+            (Expectation::EncodeFailure { err, .. }, Some(value)) => {
+                writeln!(
+                    out,
+                    "#[test]
+fn test_{test_name}_encode_failure() {{
+    // Expected error: {err}
+    let value = &mut {value};
+    assert!(Encoder::encode(&mut Vec::new(), &mut Vec::new(), value).is_err());
+}}",
+                    test_name = test_name,
+                    err = err,
+                    value = value
+                )
+                .unwrap();
+            }
+            (Expectation::DecodeFailure { bytes, err }, _) => {
+                writeln!(
+                    out,
+                    "#[test]
+fn test_{test_name}_decode_failure() {{
+    // Expected error: {err}
+    let value = &mut {name}::new_empty();
+    let bytes = &mut {bytes};
+    assert!(Decoder::decode_into(bytes, &mut [], value).is_err());
+}}",
+                    test_name = test_name,
+                    err = err,
+                    name = name,
+                    bytes = bytes_literal(bytes)
+                )
+                .unwrap();
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::fixtures, serde_json::json};
+
+    fn case(name: &str, r#type: &str, value: Value) -> TestCase {
+        TestCase {
+            name: name.to_string(),
+            r#type: r#type.to_string(),
+            handles: Vec::new(),
+            expectation: Expectation::EncodeFailure { value, err: "E".to_string() },
+        }
+    }
+
+    #[test]
+    fn constructs_tables_with_new_empty() {
+        let library = fixtures::library("example");
+        let out =
+            emit(&library, "test.example", &[case("Table", "Settings", json!({"enabled": true}))]);
+        assert!(out.contains(
+            "let value = &mut Settings { enabled: Some(true), ..Settings::new_empty() };"
+        ));
+    }
+
+    #[test]
+    fn skips_names_that_are_not_identifiers() {
+        let library = fixtures::library("example");
+        let point = json!({"x": 1, "y": 2});
+        let out = emit(&library, "test.example", &[case("Café", "Point", point)]);
+        assert!(out.contains("// Skipped `Café`: the name isn't a valid identifier."));
+        assert!(!out.contains("#[test]"));
+    }
+}
@@ -0,0 +1,49 @@
+use super::{Constant, DeclPath, LiteralKind};
+
+/// Parses an integer literal as written in FIDL source: decimal, `0x` hex, or `0b` binary,
+/// optionally negative.
+pub fn parse_integer(literal: &str) -> Option<i128> {
+    let literal = literal.trim();
+    let (negative, digits) = match literal.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, literal),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        i128::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b").or_else(|| digits.strip_prefix("0B")) {
+        i128::from_str_radix(binary, 2).ok()?
+    } else {
+        digits.parse().ok()?
+    };
+    Some(if negative { -value } else { value })
+}
+
+impl Constant {
+    /// Returns the value of a numeric literal constant. Constants that refer to other
+    /// declarations are not resolved.
+    pub fn integer_value(&self) -> Option<i128> {
+        match self {
+            Constant::Literal { literal } if matches!(literal.kind, LiteralKind::Numeric) => {
+                parse_integer(literal.value.as_ref()?)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the declaration an identifier constant refers to. Identifiers of enum and bits
+    /// members, such as `lib/Color.RED`, refer to the enum or bits declaration.
+    pub fn referenced_decl(&self) -> Option<DeclPath> {
+        match self {
+            Constant::Identifier { identifier } => {
+                let (library_name, decl_name) = identifier.split_at(identifier.rfind('/')?);
+                let decl_name = &decl_name[1..];
+                let decl_name = decl_name.split('.').next().unwrap_or(decl_name);
+                Some(DeclPath {
+                    library_name: library_name.to_string(),
+                    decl_name: decl_name.to_string(),
+                })
+            }
+            Constant::Literal { .. } => None,
+        }
+    }
+}
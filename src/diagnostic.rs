@@ -0,0 +1,78 @@
+use {
+    super::{Span, Spanned},
+    std::fmt,
+};
+
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A problem found while checking or analyzing a library, attached to the source location it
+/// concerns when one is known.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// A short, stable, kebab-case identifier for the kind of problem, e.g. `unknown-attribute`.
+    pub code: String,
+    pub message: String,
+    pub span: Option<Span>,
+    /// Additional labeled locations that help explain the problem.
+    pub notes: Vec<Spanned<String>>,
+}
+
+impl Diagnostic {
+    pub fn new(
+        severity: Severity,
+        code: impl Into<String>,
+        message: impl Into<String>,
+        span: Option<Span>,
+    ) -> Self {
+        Diagnostic { severity, code: code.into(), message: message.into(), span, notes: Vec::new() }
+    }
+
+    pub fn error(code: impl Into<String>, message: impl Into<String>, span: Option<Span>) -> Self {
+        Diagnostic::new(Severity::Error, code, message, span)
+    }
+
+    pub fn warning(
+        code: impl Into<String>,
+        message: impl Into<String>,
+        span: Option<Span>,
+    ) -> Self {
+        Diagnostic::new(Severity::Warning, code, message, span)
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>, span: Option<Span>) -> Self {
+        self.notes.push(Spanned { inner: note.into(), span });
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)?;
+        if let Some(span) = &self.span {
+            write!(f, " ({})", span)?;
+        }
+        for note in &self.notes {
+            write!(f, "\n  note: {}", note.inner)?;
+            if let Some(span) = &note.span {
+                write!(f, " ({})", span)?;
+            }
+        }
+        Ok(())
+    }
+}
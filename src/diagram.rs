@@ -0,0 +1,313 @@
+//! Class and sequence diagrams generated from a library, as Mermaid or PlantUML text for
+//! embedding in design documents.
+
+use {
+    crate::{
+        visit::Decl, DeclPath, Library, MethodReqRes, Protocol, Spanned, TableMemberType, Type,
+    },
+    std::fmt::Write,
+};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Format {
+    Mermaid,
+    PlantUml,
+}
+
+/// A field of a class: its label, its name, and its type.
+type Field<'a> = (String, &'a str, &'a Type);
+
+/// Returns the fields of a struct, table, union, or xunion, with its stereotype.
+fn class<'a>(decl: Decl<'a>) -> Option<(&'static str, Vec<Field<'a>>)> {
+    let library_name = &decl.name().library_name;
+    let field = |name: &'a Spanned<String>, r#type: &'a Spanned<Type>| {
+        (format!("{} {}", r#type.display_in(library_name), **name), name.as_str(), &r#type.inner)
+    };
+    Some(match decl {
+        Decl::Struct(decl) => (
+            "struct",
+            decl.members.iter().map(|member| field(&member.name, &member.r#type)).collect(),
+        ),
+        Decl::Table(decl) => (
+            "table",
+            decl.members
+                .iter()
+                .filter_map(|member| match &member.member_type {
+                    TableMemberType::Field { r#type, name, .. } => {
+                        let ordinal = member.ordinal.as_ref()?;
+                        let (label, name, r#type) = field(name, r#type);
+                        Some((format!("{}: {}", **ordinal, label), name, r#type))
+                    }
+                    TableMemberType::Reserved => None,
+                })
+                .collect(),
+        ),
+        Decl::Union(decl) => (
+            "union",
+            decl.members.iter().map(|member| field(&member.name, &member.r#type)).collect(),
+        ),
+        Decl::XUnion(decl) => (
+            "xunion",
+            decl.members.iter().map(|member| field(&member.name, &member.r#type)).collect(),
+        ),
+        _ => return None,
+    })
+}
+
+/// Returns the declarations `r#type` refers to, including through element types.
+fn referenced(r#type: &Type) -> Vec<&DeclPath> {
+    let mut paths = Vec::new();
+    r#type.walk(&mut |r#type| paths.extend(r#type.referenced_decl()));
+    paths
+}
+
+/// Writes a class diagram of the structs, tables, unions, and xunions in `library`, with an
+/// edge for each member that refers to another of them.
+pub fn class_diagram(library: &Library, format: Format) -> String {
+    let classes: Vec<_> = library
+        .decl_names()
+        .into_iter()
+        .filter_map(|name| {
+            let (stereotype, fields) = class(library.lookup(name)?)?;
+            Some((&name.decl_name, stereotype, fields))
+        })
+        .collect();
+    let mut out = String::new();
+    match format {
+        Format::Mermaid => writeln!(out, "classDiagram").unwrap(),
+        Format::PlantUml => writeln!(out, "@startuml").unwrap(),
+    }
+    for (name, stereotype, fields) in &classes {
+        match format {
+            Format::Mermaid => {
+                writeln!(out, "    class {} {{", name).unwrap();
+                writeln!(out, "        <<{}>>", stereotype).unwrap();
+                for (label, _, _) in fields {
+                    // Mermaid writes generic types with tildes.
+                    writeln!(out, "        {}", label.replace(['<', '>'], "~")).unwrap();
+                }
+                writeln!(out, "    }}").unwrap();
+            }
+            Format::PlantUml => {
+                writeln!(out, "class {} <<{}>> {{", name, stereotype).unwrap();
+                for (label, _, _) in fields {
+                    writeln!(out, "    {}", label).unwrap();
+                }
+                writeln!(out, "}}").unwrap();
+            }
+        }
+    }
+    for (name, _, fields) in &classes {
+        for (_, member, r#type) in fields {
+            for path in referenced(r#type) {
+                let is_class = path.library_name == *library.name
+                    && classes.iter().any(|(other, _, _)| **other == path.decl_name);
+                if is_class {
+                    let indent = if format == Format::Mermaid { "    " } else { "" };
+                    writeln!(out, "{}{} --> {} : {}", indent, name, path.decl_name, member)
+                        .unwrap();
+                }
+            }
+        }
+    }
+    if format == Format::PlantUml {
+        writeln!(out, "@enduml").unwrap();
+    }
+    out
+}
+
+fn parameters(message: &MethodReqRes, library_name: &str) -> String {
+    let parameters: Vec<String> = message
+        .parameters
+        .iter()
+        .map(|param| format!("{} {}", param.r#type.display_in(library_name), *param.name))
+        .collect();
+    parameters.join(", ")
+}
+
+/// Writes a sequence diagram of the methods of `protocol` between a client and the server:
+/// each request with its response, if any, and each event.
+pub fn sequence_diagram(protocol: &Protocol, format: Format) -> String {
+    let library_name = &protocol.name.library_name;
+    let mut out = String::new();
+    let (indent, call, reply, one_way) = match format {
+        Format::Mermaid => {
+            writeln!(out, "sequenceDiagram").unwrap();
+            writeln!(out, "    participant Client").unwrap();
+            writeln!(out, "    participant Server as {}", protocol.name.decl_name).unwrap();
+            ("    ", "->>", "-->>", "-)")
+        }
+        Format::PlantUml => {
+            writeln!(out, "@startuml").unwrap();
+            writeln!(out, "participant Client").unwrap();
+            writeln!(out, "participant \"{}\" as Server", protocol.name.decl_name).unwrap();
+            ("", "->", "-->", "->>")
+        }
+    };
+    // Mermaid treats `<` and `>` in messages as markup, so they're written as entities.
+    let escape = |text: String| match format {
+        Format::Mermaid => text.replace('<', "#lt;").replace('>', "#gt;"),
+        Format::PlantUml => text,
+    };
+    for method in &protocol.methods {
+        let name = &*method.name;
+        match (&method.request, &method.response) {
+            (Some(request), response) => {
+                let arrow = if response.is_some() { call } else { one_way };
+                let message = escape(format!("{}({})", name, parameters(request, library_name)));
+                writeln!(out, "{}Client{}Server: {}", indent, arrow, message).unwrap();
+                if let Some(response) = response {
+                    let message = escape(format!("({})", parameters(response, library_name)));
+                    writeln!(out, "{}Server{}Client: {}", indent, reply, message).unwrap();
+                }
+            }
+            (None, Some(event)) => {
+                let message = escape(format!("{}({})", name, parameters(event, library_name)));
+                writeln!(out, "{}Server{}Client: {}", indent, one_way, message).unwrap();
+            }
+            (None, None) => {}
+        }
+    }
+    if format == Format::PlantUml {
+        writeln!(out, "@enduml").unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::fixtures::{self, library_json, struct_json},
+        serde_json::json,
+    };
+
+    #[test]
+    fn writes_mermaid_class_diagram() {
+        let library = fixtures::library("example");
+        assert_eq!(
+            class_diagram(&library, Format::Mermaid),
+            "\
+classDiagram
+    class Point {
+        <<struct>>
+        uint8 x
+        uint32 y
+    }
+    class Node {
+        <<struct>>
+        uint8 a
+        vector~Node~ children
+        uint16 b
+        Color color
+    }
+    class Settings {
+        <<table>>
+        1: bool enabled
+        3: string:10 label
+    }
+    class Value {
+        <<xunion>>
+        int64 num
+        Point point
+    }
+    Node --> Node : children
+    Value --> Point : point
+"
+        );
+    }
+
+    #[test]
+    fn writes_plantuml_class_diagram() {
+        let library = fixtures::library("example");
+        assert_eq!(
+            class_diagram(&library, Format::PlantUml),
+            "\
+@startuml
+class Point <<struct>> {
+    uint8 x
+    uint32 y
+}
+class Node <<struct>> {
+    uint8 a
+    vector<Node> children
+    uint16 b
+    Color color
+}
+class Settings <<table>> {
+    1: bool enabled
+    3: string:10 label
+}
+class Value <<xunion>> {
+    int64 num
+    Point point
+}
+Node --> Node : children
+Value --> Point : point
+@enduml
+"
+        );
+    }
+
+    #[test]
+    fn writes_mermaid_sequence_diagram() {
+        let library = fixtures::library("example");
+        assert_eq!(
+            sequence_diagram(&library.protocols[0], Format::Mermaid),
+            "\
+sequenceDiagram
+    participant Client
+    participant Server as EchoProtocol
+    Client->>Server: Echo(string value, Point p)
+    Server-->>Client: (string:100? response)
+    Server-)Client: OnEvent(handle#lt;vmo, READ | WRITE#gt; vmo)
+"
+        );
+    }
+
+    #[test]
+    fn writes_plantuml_sequence_diagram() {
+        let library = fixtures::library("example");
+        assert_eq!(
+            sequence_diagram(&library.protocols[0], Format::PlantUml),
+            "\
+@startuml
+participant Client
+participant \"EchoProtocol\" as Server
+Client->Server: Echo(string value, Point p)
+Server-->Client: (string:100? response)
+Server->>Client: OnEvent(handle<vmo, READ | WRITE> vmo)
+@enduml
+"
+        );
+    }
+
+    #[test]
+    fn escapes_nested_generics_for_mermaid() {
+        let items = json!({
+            "kind": "vector",
+            "element_type": {
+                "kind": "vector",
+                "element_type": {"kind": "identifier", "identifier": "test.d/Tree", "nullable": true},
+                "maybe_element_count": 5,
+                "nullable": false,
+            },
+            "nullable": false,
+        });
+        let library = library_json(json!({
+            "name": "test.d",
+            "struct_declarations": [struct_json("test.d/Tree", &[("items", items)])],
+        }));
+        assert_eq!(
+            class_diagram(&library, Format::Mermaid),
+            "\
+classDiagram
+    class Tree {
+        <<struct>>
+        vector~vector~Tree?~:5~ items
+    }
+    Tree --> Tree : items
+"
+        );
+    }
+}
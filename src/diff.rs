@@ -0,0 +1,693 @@
+//! Semantic comparison of two versions of a library.
+//!
+//! Declarations are matched by name, and their members by name (or ordinal, for tables). Each is
+//! compared by how it's written in FIDL syntax, so moving a declaration around in its source file
+//! doesn't count as a change. A [`LibraryDiff`] can be written as Markdown for release notes, or
+//! serialized as JSON for tools.
+
+use {
+    crate::{
+        visit::Decl, Attribute, DeclPath, DeclType, Library, LibraryDep, Method, MethodReqRes,
+        Spanned, TableMemberType,
+    },
+    serde::Serialize,
+    std::fmt::Write,
+};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// A change to an attribute of the library, a declaration, or a member.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct AttributeDiff {
+    /// The member the attribute is on, or `None` if it's on the declaration or library itself.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member: Option<String>,
+    pub name: String,
+    pub change: Change,
+    /// The attribute before the change, in FIDL syntax.
+    pub old: Option<String>,
+    /// The attribute after the change, in FIDL syntax.
+    pub new: Option<String>,
+}
+
+/// A change to a member of a declaration: a field, enum or bits member, or protocol method.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct MemberDiff {
+    /// The member's name, or its ordinal for table members.
+    pub name: String,
+    pub change: Change,
+    /// The member before the change in FIDL syntax, e.g. `vector<uint8>:10 data`.
+    pub old: Option<String>,
+    /// The member after the change in FIDL syntax.
+    pub new: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeclDiff {
+    pub name: DeclPath,
+    /// The kind of the declaration, after the change if it was modified.
+    pub kind: DeclType,
+    pub change: Change,
+    /// The declaration's header before the change, e.g. `enum Color : uint32`. Modified
+    /// declarations only have headers if the header changed.
+    pub old: Option<String>,
+    /// The declaration's header after the change.
+    pub new: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<MemberDiff>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<AttributeDiff>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct DependencyDiff {
+    pub name: String,
+    pub change: Change,
+    /// For modified dependencies, the declarations that are newly used.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub added_declarations: Vec<DeclPath>,
+    /// For modified dependencies, the declarations that are no longer used.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub removed_declarations: Vec<DeclPath>,
+}
+
+/// The differences between two versions of a library.
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryDiff {
+    pub library_name: String,
+    pub attributes: Vec<AttributeDiff>,
+    pub dependencies: Vec<DependencyDiff>,
+    /// Changed declarations, grouped by kind and then by change.
+    pub declarations: Vec<DeclDiff>,
+}
+
+fn kind(decl: Decl<'_>) -> DeclType {
+    match decl {
+        Decl::Const(_) => DeclType::Const,
+        Decl::Bits(_) => DeclType::Bits,
+        Decl::Enum(_) => DeclType::Enum,
+        Decl::Protocol(_) => DeclType::Protocol,
+        Decl::Struct(_) => DeclType::Struct,
+        Decl::Table(_) => DeclType::Table,
+        Decl::Union(_) => DeclType::Union,
+        Decl::XUnion(_) => DeclType::XUnion,
+    }
+}
+
+/// Returns the position of `kind` in `Library`'s declaration lists, and its plural title.
+fn kind_info(kind: &DeclType) -> (usize, &'static str) {
+    match kind {
+        DeclType::Const => (0, "Constants"),
+        DeclType::Bits => (1, "Bits"),
+        DeclType::Enum => (2, "Enums"),
+        DeclType::Protocol => (3, "Protocols"),
+        DeclType::Struct => (4, "Structs"),
+        DeclType::Table => (5, "Tables"),
+        DeclType::Union => (6, "Unions"),
+        DeclType::XUnion => (7, "Extensible unions"),
+    }
+}
+
+fn header(decl: Decl<'_>) -> String {
+    let name = &decl.name().decl_name;
+    match decl {
+        Decl::Const(decl) => format!("const {} {} = {}", *decl.r#type, name, *decl.value),
+        Decl::Bits(decl) => match &*decl.r#type {
+            Some(r#type) => format!("bits {} : {}", name, **r#type),
+            None => format!("bits {}", name),
+        },
+        Decl::Enum(decl) => match &*decl.r#type {
+            Some(r#type) => format!("enum {} : {}", name, **r#type),
+            None => format!("enum {}", name),
+        },
+        Decl::Protocol(_) => format!("protocol {}", name),
+        Decl::Struct(_) => format!("struct {}", name),
+        Decl::Table(_) => format!("table {}", name),
+        Decl::Union(_) => format!("union {}", name),
+        Decl::XUnion(_) => format!("xunion {}", name),
+    }
+}
+
+fn parameters(message: &MethodReqRes) -> String {
+    let parameters: Vec<String> = message
+        .parameters
+        .iter()
+        .map(|param| format!("{} {}", *param.r#type, *param.name))
+        .collect();
+    parameters.join(", ")
+}
+
+fn signature(method: &Method) -> String {
+    let name = &*method.name;
+    match (&method.request, &method.response) {
+        (Some(request), Some(response)) => {
+            format!("{}({}) -> ({})", name, parameters(request), parameters(response))
+        }
+        (Some(request), None) => format!("{}({})", name, parameters(request)),
+        (None, Some(response)) => format!("-> {}({})", name, parameters(response)),
+        (None, None) => name.clone(),
+    }
+}
+
+/// A member of a declaration: its key, its rendering in FIDL syntax, and its attributes.
+type Member<'a> = (String, String, &'a [Spanned<Attribute>]);
+
+fn members(decl: Decl<'_>) -> Vec<Member<'_>> {
+    match decl {
+        Decl::Const(_) => Vec::new(),
+        Decl::Bits(decl) => decl
+            .members
+            .iter()
+            .map(|member| {
+                let value =
+                    member.value.as_ref().map(|value| value.to_string()).unwrap_or_default();
+                let rendered = format!("{} = {}", *member.name, value);
+                (member.name.inner.clone(), rendered, &member.attributes[..])
+            })
+            .collect(),
+        Decl::Enum(decl) => decl
+            .members
+            .iter()
+            .map(|member| {
+                let value =
+                    member.value.as_ref().map(|value| value.to_string()).unwrap_or_default();
+                let rendered = format!("{} = {}", *member.name, value);
+                (member.name.inner.clone(), rendered, &member.attributes[..])
+            })
+            .collect(),
+        Decl::Protocol(decl) => decl
+            .methods
+            .iter()
+            .map(|method| (method.name.inner.clone(), signature(method), &method.attributes[..]))
+            .collect(),
+        Decl::Struct(decl) => decl
+            .members
+            .iter()
+            .map(|member| {
+                let mut rendered = format!("{} {}", *member.r#type, *member.name);
+                if let Some(default) = &member.maybe_default_value {
+                    write!(rendered, " = {}", **default).unwrap();
+                }
+                (member.name.inner.clone(), rendered, &member.attributes[..])
+            })
+            .collect(),
+        Decl::Table(decl) => decl
+            .members
+            .iter()
+            .map(|member| {
+                let ordinal =
+                    member.ordinal.as_ref().map(|ordinal| ordinal.to_string()).unwrap_or_default();
+                let rendered = match &member.member_type {
+                    TableMemberType::Reserved => format!("{}: reserved", ordinal),
+                    TableMemberType::Field { r#type, name, .. } => {
+                        format!("{}: {} {}", ordinal, **r#type, **name)
+                    }
+                };
+                (ordinal, rendered, &member.attributes[..])
+            })
+            .collect(),
+        Decl::Union(decl) => decl
+            .members
+            .iter()
+            .map(|member| {
+                let rendered = format!("{} {}", *member.r#type, *member.name);
+                (member.name.inner.clone(), rendered, &member.attributes[..])
+            })
+            .collect(),
+        Decl::XUnion(decl) => decl
+            .members
+            .iter()
+            .map(|member| {
+                let rendered = format!("{} {}", *member.r#type, *member.name);
+                (member.name.inner.clone(), rendered, &member.attributes[..])
+            })
+            .collect(),
+    }
+}
+
+/// Pairs up the items of `old` and `new` that have the same key: first those in `new`, in order,
+/// then those only in `old`.
+fn pair<'a, T>(
+    old: &'a [T],
+    new: &'a [T],
+    key: impl Fn(&T) -> &str,
+) -> Vec<(Option<&'a T>, Option<&'a T>)> {
+    let mut pairs: Vec<_> = new
+        .iter()
+        .map(|new_item| {
+            (old.iter().find(|old_item| key(old_item) == key(new_item)), Some(new_item))
+        })
+        .collect();
+    pairs.extend(
+        old.iter()
+            .filter(|old_item| !new.iter().any(|new_item| key(new_item) == key(old_item)))
+            .map(|old_item| (Some(old_item), None)),
+    );
+    pairs
+}
+
+fn change<T>(old: &Option<T>, new: &Option<T>) -> Change {
+    match (old, new) {
+        (None, _) => Change::Added,
+        (_, None) => Change::Removed,
+        _ => Change::Modified,
+    }
+}
+
+fn attribute_diffs(
+    member: Option<&str>,
+    old: &[Spanned<Attribute>],
+    new: &[Spanned<Attribute>],
+    out: &mut Vec<AttributeDiff>,
+) {
+    for (old, new) in pair(old, new, |attribute| &attribute.name) {
+        let name = old.or(new).map(|attribute| attribute.name.inner.clone()).unwrap_or_default();
+        let old = old.map(|attribute| attribute.to_string());
+        let new = new.map(|attribute| attribute.to_string());
+        if old != new {
+            out.push(AttributeDiff {
+                member: member.map(str::to_string),
+                name,
+                change: change(&old, &new),
+                old,
+                new,
+            });
+        }
+    }
+}
+
+fn decl_diff(old: Decl<'_>, new: Decl<'_>) -> Option<DeclDiff> {
+    let (old_header, new_header) = (header(old), header(new));
+    let (old_members, new_members) = (members(old), members(new));
+    let mut diff = DeclDiff {
+        name: new.name().inner.clone(),
+        kind: kind(new),
+        change: Change::Modified,
+        old: None,
+        new: None,
+        members: Vec::new(),
+        attributes: Vec::new(),
+    };
+    if old_header != new_header {
+        diff.old = Some(old_header);
+        diff.new = Some(new_header);
+    }
+    attribute_diffs(None, old.attributes(), new.attributes(), &mut diff.attributes);
+    for (old_member, new_member) in pair(&old_members, &new_members, |member| &member.0) {
+        let (name, _, _) = old_member.or(new_member)?;
+        let old = old_member.map(|member| member.1.clone());
+        let new = new_member.map(|member| member.1.clone());
+        if old != new {
+            diff.members.push(MemberDiff {
+                name: name.clone(),
+                change: change(&old, &new),
+                old,
+                new,
+            });
+        }
+        if let (Some(old_member), Some(new_member)) = (old_member, new_member) {
+            attribute_diffs(Some(name), old_member.2, new_member.2, &mut diff.attributes);
+        }
+    }
+    if diff.old.is_none() && diff.members.is_empty() && diff.attributes.is_empty() {
+        return None;
+    }
+    Some(diff)
+}
+
+fn dependency_diffs(old: &[LibraryDep], new: &[LibraryDep]) -> Vec<DependencyDiff> {
+    let declarations = |dep: &LibraryDep| -> Vec<DeclPath> {
+        dep.declarations.iter().flat_map(|map| map.keys()).map(|path| path.inner.clone()).collect()
+    };
+    let mut diffs = Vec::new();
+    for (old, new) in pair(old, new, |dep| &dep.name) {
+        let (old_declarations, new_declarations) =
+            (old.map(declarations).unwrap_or_default(), new.map(declarations).unwrap_or_default());
+        let mut diff = DependencyDiff {
+            name: old.or(new).map(|dep| dep.name.clone()).unwrap_or_default(),
+            change: change(&old, &new),
+            added_declarations: Vec::new(),
+            removed_declarations: Vec::new(),
+        };
+        if diff.change == Change::Modified {
+            diff.added_declarations = new_declarations
+                .iter()
+                .filter(|path| !old_declarations.contains(path))
+                .cloned()
+                .collect();
+            diff.removed_declarations = old_declarations
+                .iter()
+                .filter(|path| !new_declarations.contains(path))
+                .cloned()
+                .collect();
+            if diff.added_declarations.is_empty() && diff.removed_declarations.is_empty() {
+                continue;
+            }
+        }
+        diffs.push(diff);
+    }
+    diffs
+}
+
+/// Compares two versions of a library. Declarations are matched by their names within the
+/// library, so a renamed library can still be compared.
+pub fn diff(old: &Library, new: &Library) -> LibraryDiff {
+    fn lookup<'a>(library: &'a Library, decl_name: &str) -> Option<Decl<'a>> {
+        let path =
+            DeclPath { library_name: library.name.inner.clone(), decl_name: decl_name.to_string() };
+        library.lookup(&path)
+    }
+    let mut declarations = Vec::new();
+    for name in new.decl_names() {
+        let new_decl = lookup(new, &name.decl_name).expect("named declarations exist");
+        match lookup(old, &name.decl_name) {
+            Some(old_decl) => declarations.extend(decl_diff(old_decl, new_decl)),
+            None => declarations.push(DeclDiff {
+                name: name.inner.clone(),
+                kind: kind(new_decl),
+                change: Change::Added,
+                old: None,
+                new: Some(header(new_decl)),
+                members: Vec::new(),
+                attributes: Vec::new(),
+            }),
+        }
+    }
+    for name in old.decl_names() {
+        if lookup(new, &name.decl_name).is_none() {
+            let old_decl = lookup(old, &name.decl_name).expect("named declarations exist");
+            declarations.push(DeclDiff {
+                name: name.inner.clone(),
+                kind: kind(old_decl),
+                change: Change::Removed,
+                old: Some(header(old_decl)),
+                new: None,
+                members: Vec::new(),
+                attributes: Vec::new(),
+            });
+        }
+    }
+    declarations.sort_by_key(|diff| (kind_info(&diff.kind).0, diff.change));
+    let mut attributes = Vec::new();
+    attribute_diffs(None, &old.attributes, &new.attributes, &mut attributes);
+    LibraryDiff {
+        library_name: new.name.inner.clone(),
+        attributes,
+        dependencies: dependency_diffs(&old.library_dependencies, &new.library_dependencies),
+        declarations,
+    }
+}
+
+fn attribute_line(diff: &AttributeDiff) -> String {
+    let old = diff.old.as_deref().unwrap_or_default();
+    let new = diff.new.as_deref().unwrap_or_default();
+    let mut line = match diff.change {
+        Change::Added => format!("Added attribute `{}`", new),
+        Change::Removed => format!("Removed attribute `{}`", old),
+        Change::Modified => format!("Changed attribute `{}` to `{}`", old, new),
+    };
+    if let Some(member) = &diff.member {
+        write!(line, " on `{}`", member).unwrap();
+    }
+    line
+}
+
+fn member_line(diff: &MemberDiff) -> String {
+    let old = diff.old.as_deref().unwrap_or_default();
+    let new = diff.new.as_deref().unwrap_or_default();
+    match diff.change {
+        Change::Added => format!("Added `{}`", new),
+        Change::Removed => format!("Removed `{}`", old),
+        Change::Modified => format!("Changed `{}` from `{}` to `{}`", diff.name, old, new),
+    }
+}
+
+fn dependency_line(diff: &DependencyDiff) -> String {
+    let paths = |paths: &[DeclPath]| {
+        paths.iter().map(|path| format!("`{}`", path)).collect::<Vec<_>>().join(", ")
+    };
+    match diff.change {
+        Change::Added => format!("Added `{}`", diff.name),
+        Change::Removed => format!("Removed `{}`", diff.name),
+        Change::Modified => {
+            let mut parts = Vec::new();
+            if !diff.added_declarations.is_empty() {
+                parts.push(format!("now uses {}", paths(&diff.added_declarations)));
+            }
+            if !diff.removed_declarations.is_empty() {
+                parts.push(format!("no longer uses {}", paths(&diff.removed_declarations)));
+            }
+            format!("Changed `{}`: {}", diff.name, parts.join("; "))
+        }
+    }
+}
+
+impl LibraryDiff {
+    /// Returns `true` if the two versions of the library are the same.
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty() && self.dependencies.is_empty() && self.declarations.is_empty()
+    }
+
+    /// Returns the diff as a JSON object.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("diffs have string keys")
+    }
+
+    /// Writes the diff as a Markdown document suitable for release notes, with a section for
+    /// each kind of declaration.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "# Changes to `{}`", self.library_name).unwrap();
+        if self.is_empty() {
+            writeln!(out, "\nNo changes.").unwrap();
+            return out;
+        }
+        if !self.attributes.is_empty() {
+            writeln!(out, "\n## Library attributes\n").unwrap();
+            for diff in &self.attributes {
+                writeln!(out, "- {}", attribute_line(diff)).unwrap();
+            }
+        }
+        if !self.dependencies.is_empty() {
+            writeln!(out, "\n## Dependencies\n").unwrap();
+            for diff in &self.dependencies {
+                writeln!(out, "- {}", dependency_line(diff)).unwrap();
+            }
+        }
+        let mut kind = None;
+        let mut change = None;
+        for diff in &self.declarations {
+            let (index, title) = kind_info(&diff.kind);
+            if kind != Some(index) {
+                writeln!(out, "\n## {}", title).unwrap();
+                kind = Some(index);
+                change = None;
+            }
+            if change != Some(diff.change) {
+                let subtitle = match diff.change {
+                    Change::Added => "Added",
+                    Change::Removed => "Removed",
+                    Change::Modified => "Modified",
+                };
+                writeln!(out, "\n### {}\n", subtitle).unwrap();
+                change = Some(diff.change);
+            }
+            match diff.change {
+                Change::Added => {
+                    writeln!(out, "- `{}`", diff.new.as_deref().unwrap_or_default()).unwrap()
+                }
+                Change::Removed => {
+                    writeln!(out, "- `{}`", diff.old.as_deref().unwrap_or_default()).unwrap()
+                }
+                Change::Modified => {
+                    writeln!(out, "- `{}`", diff.name.decl_name).unwrap();
+                    if let (Some(old), Some(new)) = (&diff.old, &diff.new) {
+                        writeln!(out, "  - Changed `{}` to `{}`", old, new).unwrap();
+                    }
+                    for member in &diff.members {
+                        writeln!(out, "  - {}", member_line(member)).unwrap();
+                    }
+                    for attribute in &diff.attributes {
+                        writeln!(out, "  - {}", attribute_line(attribute)).unwrap();
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            fixtures,
+            span::{FileId, Span},
+        },
+        serde_json::json,
+    };
+
+    fn attributes(json: serde_json::Value) -> Vec<Spanned<Attribute>> {
+        serde_json::from_str(&json.to_string()).unwrap()
+    }
+
+    fn dependency(name: &str, decls: &[&str]) -> LibraryDep {
+        let declarations: serde_json::Map<_, _> =
+            decls.iter().map(|decl| (format!("{}/{}", name, decl), json!("struct"))).collect();
+        serde_json::from_str(&json!({"name": name, "declarations": declarations}).to_string())
+            .unwrap()
+    }
+
+    /// Returns the example library and a changed version of it.
+    fn versions() -> (Library, Library) {
+        let mut old = fixtures::library("example");
+        old.library_dependencies =
+            vec![dependency("test.dep", &["A", "B"]), dependency("test.gone", &[])];
+        let mut new = old.clone();
+        new.attributes = attributes(json!([{"name": "Deprecated", "value": ""}]));
+        new.library_dependencies =
+            vec![dependency("test.dep", &["B", "C"]), dependency("test.new", &[])];
+        let mut line = new.structs[0].clone();
+        line.name.decl_name = "Line".to_string();
+        new.structs.push(line);
+        new.xunions.clear();
+        let point = &mut new.structs[0];
+        point.attributes = attributes(json!([{"name": "Doc", "value": " A point.\n"}]));
+        point.members[1].r#type.inner =
+            fixtures::r#type(json!({"kind": "primitive", "subtype": "uint64"}));
+        point.members[0].attributes = attributes(json!([{"name": "Deprecated", "value": "use y"}]));
+        new.structs[1].members.remove(2);
+        new.enums[0].members.pop();
+        (old, new)
+    }
+
+    #[test]
+    fn writes_markdown() {
+        let (old, new) = versions();
+        assert_eq!(
+            diff(&old, &new).to_markdown(),
+            r#"# Changes to `test.example`
+
+## Library attributes
+
+- Added attribute `[Deprecated]`
+- Removed attribute `[Doc = "A library"]`
+
+## Dependencies
+
+- Changed `test.dep`: now uses `test.dep/C`; no longer uses `test.dep/A`
+- Added `test.new`
+- Removed `test.gone`
+
+## Enums
+
+### Modified
+
+- `Color`
+  - Removed `Green = 2`
+
+## Structs
+
+### Added
+
+- `struct Line`
+
+### Modified
+
+- `Point`
+  - Changed `y` from `uint32 y` to `uint64 y`
+  - Added attribute `[Doc = " A point.\n"]`
+  - Added attribute `[Deprecated = "use y"]` on `x`
+- `Node`
+  - Removed `uint16 b`
+
+## Extensible unions
+
+### Removed
+
+- `xunion Value`
+"#
+        );
+    }
+
+    #[test]
+    fn writes_json() {
+        let (old, new) = versions();
+        let json = diff(&old, &new).to_json();
+        assert_eq!(json["library_name"], "test.example");
+        let changes: Vec<_> = json["declarations"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|decl| (decl["name"].as_str().unwrap(), decl["change"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                ("test.example/Color", "modified"),
+                ("test.example/Line", "added"),
+                ("test.example/Point", "modified"),
+                ("test.example/Node", "modified"),
+                ("test.example/Value", "removed"),
+            ]
+        );
+        assert_eq!(
+            json["declarations"][2]["members"],
+            json!([{"name": "y", "change": "modified", "old": "uint32 y", "new": "uint64 y"}])
+        );
+        assert_eq!(
+            json["declarations"][2]["attributes"][1],
+            json!({
+                "member": "x",
+                "name": "Deprecated",
+                "change": "added",
+                "old": null,
+                "new": "[Deprecated = \"use y\"]",
+            })
+        );
+        assert_eq!(
+            json["dependencies"][0],
+            json!({
+                "name": "test.dep",
+                "change": "modified",
+                "added_declarations": ["test.dep/C"],
+                "removed_declarations": ["test.dep/A"],
+            })
+        );
+    }
+
+    #[test]
+    fn reports_header_changes() {
+        let old = fixtures::library("example");
+        let mut new = old.clone();
+        *new.consts[0].value = serde_json::from_value(
+            json!({"kind": "literal", "literal": {"kind": "numeric", "value": "5"}}),
+        )
+        .unwrap();
+        let diff = diff(&old, &new);
+        assert_eq!(diff.declarations.len(), 1);
+        assert!(diff.declarations[0].old.as_deref().unwrap().starts_with("const "));
+        assert!(diff.declarations[0].new.as_deref().unwrap().ends_with(" = 5"));
+    }
+
+    #[test]
+    fn ignores_spans() {
+        let old = fixtures::library("example");
+        let mut new = old.clone();
+        let span = Some(Span { file_id: FileId(1), start: 10, end: 20 });
+        new.name.span = span;
+        new.structs[0].name.span = span;
+        new.structs[0].members[0].r#type.span = span;
+        new.protocols[0].methods[0].name.span = span;
+        let diff = diff(&old, &new);
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_markdown(), "# Changes to `test.example`\n\nNo changes.\n");
+    }
+}
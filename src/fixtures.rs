@@ -0,0 +1,73 @@
+//! IR fixtures for unit tests, loaded from the `testdata` directory or built from partial JSON.
+
+use {
+    crate::{Library, Type},
+    serde_json::{json, Value},
+};
+
+/// Loads the library in `testdata/<name>.json`.
+pub fn library(name: &str) -> Library {
+    let path = format!("{}/testdata/{}.json", env!("CARGO_MANIFEST_DIR"), name);
+    let json = std::fs::read_to_string(&path).unwrap_or_else(|err| panic!("{}: {}", path, err));
+    serde_json::from_str(&json).unwrap_or_else(|err| panic!("{}: {}", path, err))
+}
+
+/// Parses a type from its JSON IR, e.g. `{"kind": "string"}`.
+pub fn r#type(json: Value) -> Type {
+    // Declaration paths only deserialize from borrowed strings, so go through text.
+    serde_json::from_str(&json.to_string()).unwrap()
+}
+
+/// Builds a library from partial JSON IR, defaulting any missing top-level field to empty.
+pub fn library_json(mut json: Value) -> Library {
+    let object = json.as_object_mut().expect("library JSON must be an object");
+    for key in &[
+        "const_declarations",
+        "bits_declarations",
+        "enum_declarations",
+        "interface_declarations",
+        "struct_declarations",
+        "table_declarations",
+        "union_declarations",
+        "xunion_declarations",
+        "declaration_order",
+        "library_dependencies",
+    ] {
+        object.entry(*key).or_insert_with(|| json!([]));
+    }
+    object.entry("declarations").or_insert_with(|| json!({}));
+    serde_json::from_str(&json.to_string()).unwrap()
+}
+
+/// Returns the JSON IR of a struct named `name` with members of the given types, each laid out
+/// in its own 8-byte slot.
+pub fn struct_json(name: &str, members: &[(&str, Value)]) -> Value {
+    let members: Vec<Value> = members
+        .iter()
+        .enumerate()
+        .map(|(index, (name, r#type))| {
+            json!({
+                "attributes": [],
+                "type": r#type,
+                "name": name,
+                "offset": index * 8,
+                "max_handles": 0,
+                "max_out_of_line": 0,
+            })
+        })
+        .collect();
+    json!({
+        "attributes": [],
+        "name": name,
+        "members": members,
+        "size": std::cmp::max(members.len() * 8, 1),
+        "alignment": 8,
+        "max_handles": 0,
+        "max_out_of_line": 0,
+    })
+}
+
+/// Returns the JSON IR of a non-nullable reference to the declaration at `path`.
+pub fn identifier(path: &str) -> Value {
+    json!({"kind": "identifier", "identifier": path, "nullable": false})
+}
@@ -0,0 +1,294 @@
+use {
+    super::HandleSubtype,
+    serde::{Deserialize, Serialize},
+    std::{fmt, ops},
+};
+
+/// A set of Zircon handle rights, stored as the raw `zx_rights_t` bitmask.
+#[derive(Clone, Copy, Default, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HandleRights(pub u32);
+
+macro_rules! handle_rights {
+    ($($name:ident = $bit:expr,)*) => {
+        impl HandleRights {
+            pub const NONE: HandleRights = HandleRights(0);
+            $(pub const $name: HandleRights = HandleRights($bit);)*
+
+            /// Every named right, paired with the name used for it in FIDL source.
+            pub const NAMED: &'static [(&'static str, HandleRights)] =
+                &[$((stringify!($name), HandleRights::$name),)*];
+        }
+    };
+}
+
+handle_rights! {
+    DUPLICATE = 1 << 0,
+    TRANSFER = 1 << 1,
+    READ = 1 << 2,
+    WRITE = 1 << 3,
+    EXECUTE = 1 << 4,
+    MAP = 1 << 5,
+    GET_PROPERTY = 1 << 6,
+    SET_PROPERTY = 1 << 7,
+    ENUMERATE = 1 << 8,
+    DESTROY = 1 << 9,
+    SET_POLICY = 1 << 10,
+    GET_POLICY = 1 << 11,
+    SIGNAL = 1 << 12,
+    SIGNAL_PEER = 1 << 13,
+    WAIT = 1 << 14,
+    INSPECT = 1 << 15,
+    MANAGE_JOB = 1 << 16,
+    MANAGE_PROCESS = 1 << 17,
+    MANAGE_THREAD = 1 << 18,
+    APPLY_PROFILE = 1 << 19,
+    SAME_RIGHTS = 1 << 31,
+}
+
+impl HandleRights {
+    /// Returns the rights for the given name, as spelled in `HandleRights::NAMED`.
+    pub fn from_name(name: &str) -> Option<HandleRights> {
+        HandleRights::NAMED.iter().find(|(n, _)| *n == name).map(|(_, rights)| *rights)
+    }
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns `true` if every right in `other` is also present in `self`.
+    pub fn contains(self, other: HandleRights) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns the rights in `self` that are missing from `actual`.
+    /// `SAME_RIGHTS` is satisfied by any set of rights.
+    pub fn missing_from(self, actual: HandleRights) -> HandleRights {
+        if self.contains(HandleRights::SAME_RIGHTS) {
+            return HandleRights::NONE;
+        }
+        HandleRights(self.0 & !actual.0)
+    }
+}
+
+impl ops::BitOr for HandleRights {
+    type Output = HandleRights;
+
+    fn bitor(self, other: HandleRights) -> HandleRights {
+        HandleRights(self.0 | other.0)
+    }
+}
+
+impl ops::BitOrAssign for HandleRights {
+    fn bitor_assign(&mut self, other: HandleRights) {
+        self.0 |= other.0;
+    }
+}
+
+impl ops::BitAnd for HandleRights {
+    type Output = HandleRights;
+
+    fn bitand(self, other: HandleRights) -> HandleRights {
+        HandleRights(self.0 & other.0)
+    }
+}
+
+impl fmt::Debug for HandleRights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("NONE");
+        }
+        let mut remaining = self.0;
+        let mut first = true;
+        for (name, rights) in HandleRights::NAMED {
+            if self.contains(*rights) {
+                if !first {
+                    f.write_str(" | ")?;
+                }
+                f.write_str(name)?;
+                remaining &= !rights.0;
+                first = false;
+            }
+        }
+        if remaining != 0 {
+            if !first {
+                f.write_str(" | ")?;
+            }
+            write!(f, "{:#x}", remaining)?;
+        }
+        Ok(())
+    }
+}
+
+macro_rules! obj_types {
+    ($($variant:ident = $obj_type:expr,)*) => {
+        impl HandleSubtype {
+            /// Returns the Zircon object type (`zx_obj_type_t`) for this handle subtype.
+            /// `HandleSubtype::Handle` maps to `ZX_OBJ_TYPE_NONE`, matching any object.
+            pub fn obj_type(&self) -> u32 {
+                match self {
+                    $(HandleSubtype::$variant => $obj_type,)*
+                }
+            }
+
+            /// Returns the handle subtype for the given Zircon object type, if any.
+            pub fn from_obj_type(obj_type: u32) -> Option<HandleSubtype> {
+                match obj_type {
+                    $($obj_type => Some(HandleSubtype::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+obj_types! {
+    Handle = 0,
+    Process = 1,
+    Thread = 2,
+    Vmo = 3,
+    Channel = 4,
+    Event = 5,
+    Port = 6,
+    Interrupt = 9,
+    PciDevice = 11,
+    DebugLog = 12,
+    Socket = 14,
+    Resource = 15,
+    Eventpair = 16,
+    Job = 17,
+    Vmar = 18,
+    Fifo = 19,
+    Guest = 20,
+    VCpu = 21,
+    Timer = 22,
+    Iommu = 23,
+    Bti = 24,
+    Profile = 25,
+    Pmt = 26,
+    SuspendToken = 27,
+    Pager = 28,
+    Exception = 29,
+}
+
+impl HandleSubtype {
+    /// Returns `true` if a handle to an object of type `obj_type` may be sent where this
+    /// subtype is declared.
+    pub fn accepts_obj_type(&self, obj_type: u32) -> bool {
+        let expected = self.obj_type();
+        expected == 0 || expected == obj_type
+    }
+}
+
+/// Implementations of `Serialize` and `Deserialize` for `HandleSubtype` as a Zircon object
+/// type number rather than its FIDL name, for use with `#[serde(with = "...")]`.
+pub mod obj_type_serde {
+    use {
+        super::HandleSubtype,
+        serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer},
+    };
+
+    pub fn serialize<S: Serializer>(
+        subtype: &HandleSubtype,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        subtype.obj_type().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HandleSubtype, D::Error> {
+        let obj_type = u32::deserialize(deserializer)?;
+        HandleSubtype::from_obj_type(obj_type)
+            .ok_or_else(|| D::Error::custom(format!("unknown object type {}", obj_type)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{fixtures, TypeKind},
+        serde_json::json,
+    };
+
+    #[test]
+    fn combines_rights() {
+        let read_write = HandleRights::READ | HandleRights::WRITE;
+        assert_eq!(read_write.bits(), 0b1100);
+        let mut rights = HandleRights::NONE;
+        assert!(rights.is_empty());
+        rights |= HandleRights::READ;
+        rights |= HandleRights::WRITE;
+        assert_eq!(rights, read_write);
+        assert_eq!(read_write & HandleRights::WRITE, HandleRights::WRITE);
+        assert_eq!(read_write & HandleRights::EXECUTE, HandleRights::NONE);
+        assert!(read_write.contains(HandleRights::READ));
+        assert!(read_write.contains(HandleRights::NONE));
+        assert!(!HandleRights::READ.contains(read_write));
+        assert_eq!(HandleRights::from_name("SIGNAL_PEER"), Some(HandleRights::SIGNAL_PEER));
+        assert_eq!(HandleRights::from_name("signal_peer"), None);
+    }
+
+    #[test]
+    fn finds_missing_rights() {
+        let read_write = HandleRights::READ | HandleRights::WRITE;
+        assert_eq!(read_write.missing_from(HandleRights::READ), HandleRights::WRITE);
+        assert_eq!(read_write.missing_from(read_write | HandleRights::MAP), HandleRights::NONE);
+        assert_eq!(read_write.missing_from(HandleRights::NONE), read_write);
+        // `SAME_RIGHTS` asks for whatever rights the handle has.
+        assert_eq!(HandleRights::SAME_RIGHTS.missing_from(HandleRights::NONE), HandleRights::NONE);
+        let same_and_read = HandleRights::SAME_RIGHTS | HandleRights::READ;
+        assert_eq!(same_and_read.missing_from(HandleRights::WRITE), HandleRights::NONE);
+    }
+
+    #[test]
+    fn debug_names_rights() {
+        assert_eq!(format!("{:?}", HandleRights::NONE), "NONE");
+        assert_eq!(format!("{:?}", HandleRights::READ | HandleRights::WRITE), "READ | WRITE");
+        assert_eq!(format!("{:?}", HandleRights::SAME_RIGHTS), "SAME_RIGHTS");
+        assert_eq!(
+            format!("{:?}", HandleRights::DUPLICATE | HandleRights(1 << 24)),
+            "DUPLICATE | 0x1000000"
+        );
+        assert_eq!(format!("{:?}", HandleRights(0x3 << 20)), "0x300000");
+    }
+
+    #[test]
+    fn maps_object_types() {
+        assert_eq!(HandleSubtype::Handle.obj_type(), 0);
+        assert_eq!(HandleSubtype::Vmo.obj_type(), 3);
+        assert_eq!(HandleSubtype::Exception.obj_type(), 29);
+        assert_eq!(HandleSubtype::from_obj_type(17), Some(HandleSubtype::Job));
+        assert_eq!(HandleSubtype::from_obj_type(0), Some(HandleSubtype::Handle));
+        assert_eq!(HandleSubtype::from_obj_type(7), None);
+        assert!(HandleSubtype::Vmo.accepts_obj_type(3));
+        assert!(!HandleSubtype::Vmo.accepts_obj_type(4));
+        assert!(HandleSubtype::Handle.accepts_obj_type(4));
+        assert!(HandleSubtype::Handle.accepts_obj_type(0));
+    }
+
+    #[test]
+    fn round_trips_handle_rights() {
+        let json = json!({"kind": "handle", "nullable": false, "rights": 12, "subtype": "vmo"});
+        let r#type = fixtures::r#type(json.clone());
+        match &*r#type.kind {
+            TypeKind::Handle { subtype, rights } => {
+                assert_eq!(*subtype, HandleSubtype::Vmo);
+                assert_eq!(*rights, Some(HandleRights::READ | HandleRights::WRITE));
+            }
+            kind => panic!("unexpected {:?}", kind),
+        }
+        assert_eq!(serde_json::to_value(&r#type).unwrap(), json);
+
+        // Unrestricted handles leave `rights` out.
+        let json = json!({"kind": "handle", "nullable": false, "subtype": "vmo"});
+        let r#type = fixtures::r#type(json.clone());
+        assert!(matches!(&*r#type.kind, TypeKind::Handle { rights: None, .. }));
+        assert_eq!(serde_json::to_value(&r#type).unwrap(), json);
+    }
+}
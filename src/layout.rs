@@ -0,0 +1,231 @@
+//! Wire-format layout of FIDL types: inline sizes and alignments, and bounds on the out-of-line
+//! bytes and handles a value can carry.
+
+use {
+    crate::{
+        visit::{Decl, DeclLookup},
+        DeclPath, PrimitiveSubtype, Spanned, Table, TableMemberType, Type, TypeKind,
+    },
+    std::{cmp, convert::TryFrom, fmt, ops},
+};
+
+/// The size of a transactional message header.
+pub const HEADER_SIZE: u32 = 16;
+/// Out-of-line objects are aligned to this many bytes.
+pub const OUT_OF_LINE_ALIGNMENT: u32 = 8;
+/// The size of an envelope header: byte count, handle count, and presence.
+pub const ENVELOPE_SIZE: u32 = 16;
+/// The presence marker of an out-of-line object that is present.
+pub const ALLOC_PRESENT: u64 = u64::MAX;
+/// The presence marker of an out-of-line object that is absent.
+pub const ALLOC_ABSENT: u64 = 0;
+/// The presence marker of a handle that is present.
+pub const HANDLE_PRESENT: u32 = u32::MAX;
+/// The presence marker of a handle that is absent.
+pub const HANDLE_ABSENT: u32 = 0;
+
+/// Rounds `size` up to a multiple of `alignment`, saturating at `u32::MAX`.
+pub fn align_to(size: u32, alignment: u32) -> u32 {
+    size.checked_next_multiple_of(alignment.max(1)).unwrap_or(u32::MAX)
+}
+
+/// Rounds `size` up to a multiple of `alignment`, saturating at `u64::MAX`.
+pub fn align_to_u64(size: u64, alignment: u64) -> u64 {
+    size.checked_next_multiple_of(alignment.max(1)).unwrap_or(u64::MAX)
+}
+
+/// The inline size and alignment of a type.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub struct TypeShape {
+    pub inline_size: u32,
+    pub alignment: u32,
+}
+
+impl TypeShape {
+    const fn new(inline_size: u32, alignment: u32) -> Self {
+        TypeShape { inline_size, alignment }
+    }
+
+    /// The shape of vectors, strings, and tables: a count and a presence marker.
+    pub const VECTOR: TypeShape = TypeShape::new(16, 8);
+    /// The shape of nullable structs and unions: a presence marker.
+    pub const POINTER: TypeShape = TypeShape::new(8, 8);
+    /// The shape of handles, `request<P>`, and `P` client ends.
+    pub const HANDLE: TypeShape = TypeShape::new(4, 4);
+    /// The shape of xunions: an ordinal, padding, and an envelope.
+    pub const XUNION: TypeShape = TypeShape::new(24, 8);
+}
+
+impl PrimitiveSubtype {
+    pub fn size(&self) -> u32 {
+        match self {
+            PrimitiveSubtype::Bool | PrimitiveSubtype::Int8 | PrimitiveSubtype::UInt8 => 1,
+            PrimitiveSubtype::Int16 | PrimitiveSubtype::UInt16 => 2,
+            PrimitiveSubtype::Int32 | PrimitiveSubtype::UInt32 | PrimitiveSubtype::Float32 => 4,
+            PrimitiveSubtype::Int64 | PrimitiveSubtype::UInt64 | PrimitiveSubtype::Float64 => 8,
+        }
+    }
+}
+
+/// Returns the primitive underlying an enum or bits declaration.
+pub fn underlying_primitive(decl: Decl<'_>) -> Option<PrimitiveSubtype> {
+    match decl {
+        Decl::Enum(decl) => Some(
+            decl.r#type.as_ref().map_or(PrimitiveSubtype::UInt32, |subtype| (**subtype).clone()),
+        ),
+        Decl::Bits(decl) => match decl.r#type.as_ref().map(|r#type| &*r#type.kind) {
+            Some(TypeKind::Primitive { subtype }) => Some(subtype.clone()),
+            _ => Some(PrimitiveSubtype::UInt32),
+        },
+        _ => None,
+    }
+}
+
+/// Returns the inline shape of `r#type`, or `None` if it refers to a declaration that `lookup`
+/// doesn't know or that isn't a type.
+pub fn type_shape<L: DeclLookup + ?Sized>(lookup: &L, r#type: &Type) -> Option<TypeShape> {
+    Some(match &*r#type.kind {
+        TypeKind::Primitive { subtype } => TypeShape::new(subtype.size(), subtype.size()),
+        TypeKind::Array { element_type, element_count, .. } => {
+            let element = type_shape(lookup, element_type)?;
+            let count = element_count.as_ref()?.as_u64()?;
+            let size = u64::from(element.inline_size).saturating_mul(count);
+            TypeShape::new(u32::try_from(size).unwrap_or(u32::MAX), element.alignment)
+        }
+        TypeKind::Vector { .. } | TypeKind::String { .. } => TypeShape::VECTOR,
+        TypeKind::Handle { .. } | TypeKind::Request { .. } => TypeShape::HANDLE,
+        TypeKind::Identifier { identifier, .. } => {
+            return decl_shape(lookup, identifier, *r#type.nullable)
+        }
+        TypeKind::UnresolvedRequest { .. } | TypeKind::UnresolvedIdentifier { .. } => return None,
+    })
+}
+
+/// Returns the inline shape of a reference to the declaration `path`.
+pub fn decl_shape<L: DeclLookup + ?Sized>(
+    lookup: &L,
+    path: &DeclPath,
+    nullable: bool,
+) -> Option<TypeShape> {
+    let decl = lookup.lookup_decl(path)?;
+    Some(match decl {
+        Decl::Const(_) => return None,
+        Decl::Enum(_) | Decl::Bits(_) => {
+            let size = underlying_primitive(decl)?.size();
+            TypeShape::new(size, size)
+        }
+        Decl::Protocol(_) => TypeShape::HANDLE,
+        Decl::Table(_) => TypeShape::VECTOR,
+        Decl::XUnion(_) => TypeShape::XUNION,
+        Decl::Struct(_) | Decl::Union(_) if nullable => TypeShape::POINTER,
+        Decl::Struct(decl) => match (*decl.size, *decl.alignment) {
+            (Some(size), Some(alignment)) => TypeShape::new(size, alignment),
+            _ => {
+                let mut size = 0;
+                let mut alignment = 1;
+                for member in &decl.members {
+                    let shape = type_shape(lookup, &member.r#type)?;
+                    size = align_to(size, shape.alignment).saturating_add(shape.inline_size);
+                    alignment = cmp::max(alignment, shape.alignment);
+                }
+                TypeShape::new(cmp::max(align_to(size, alignment), 1), alignment)
+            }
+        },
+        Decl::Union(decl) => match (*decl.size, *decl.alignment) {
+            (Some(size), Some(alignment)) => TypeShape::new(size, alignment),
+            _ => {
+                let mut size = 0;
+                let mut alignment = 4;
+                for member in &decl.members {
+                    let shape = type_shape(lookup, &member.r#type)?;
+                    size = cmp::max(size, shape.inline_size);
+                    alignment = cmp::max(alignment, shape.alignment);
+                }
+                let size = align_to(4, alignment).saturating_add(size);
+                TypeShape::new(align_to(size, alignment), alignment)
+            }
+        },
+    })
+}
+
+/// Returns the offset of the data of a static union, after its tag.
+pub fn union_data_offset(alignment: u32) -> u32 {
+    align_to(4, alignment)
+}
+
+/// An upper bound on a quantity, such as the bytes or handles in a message.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Bound {
+    Bounded(u64),
+    Unbounded,
+}
+
+impl Bound {
+    pub const ZERO: Bound = Bound::Bounded(0);
+
+    /// Converts a size from the IR, where `u32::MAX` means unbounded.
+    pub fn from_ir(value: u32) -> Bound {
+        if value == u32::MAX {
+            Bound::Unbounded
+        } else {
+            Bound::Bounded(value.into())
+        }
+    }
+
+    pub fn exceeds(self, limit: u64) -> bool {
+        match self {
+            Bound::Bounded(value) => value > limit,
+            Bound::Unbounded => true,
+        }
+    }
+
+    /// Rounds the bound up to a multiple of `alignment`, saturating like the other arithmetic.
+    pub fn aligned(self, alignment: u64) -> Bound {
+        match self {
+            Bound::Bounded(value) => Bound::Bounded(align_to_u64(value, alignment)),
+            Bound::Unbounded => Bound::Unbounded,
+        }
+    }
+
+    pub fn times(self, count: u64) -> Bound {
+        match self {
+            Bound::Bounded(value) => Bound::Bounded(value.saturating_mul(count)),
+            Bound::Unbounded if count == 0 => Bound::ZERO,
+            Bound::Unbounded => Bound::Unbounded,
+        }
+    }
+}
+
+impl ops::Add for Bound {
+    type Output = Bound;
+
+    fn add(self, other: Bound) -> Bound {
+        match (self, other) {
+            (Bound::Bounded(a), Bound::Bounded(b)) => Bound::Bounded(a.saturating_add(b)),
+            _ => Bound::Unbounded,
+        }
+    }
+}
+
+impl fmt::Display for Bound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Bound::Bounded(value) => write!(f, "{}", value),
+            Bound::Unbounded => f.write_str("unbounded"),
+        }
+    }
+}
+
+/// Returns the members of a table that are not reserved, with their ordinals and types.
+pub fn table_fields(decl: &Table) -> Vec<(u64, &Spanned<String>, &Spanned<Type>)> {
+    decl.members
+        .iter()
+        .filter_map(|member| match &member.member_type {
+            TableMemberType::Field { r#type, name, .. } => {
+                let ordinal = member.ordinal.as_ref().and_then(|ordinal| ordinal.as_u64())?;
+                Some((ordinal, name, r#type))
+            }
+            TableMemberType::Reserved => None,
+        })
+        .collect()
+}
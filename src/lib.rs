@@ -20,6 +20,82 @@ pub use ser_option::SerOption;
 mod span;
 pub use span::{FileId, Span, Spanned};
 
+// Definitions of handle rights and the mapping from handle subtypes to Zircon object types.
+mod handle;
+pub use handle::{obj_type_serde, HandleRights};
+
+// Parsing of availability attributes and filtering of a library to a single API level.
+mod availability;
+pub use availability::{Availability, AvailabilityError, AVAILABLE_ATTRIBUTE, HEAD};
+
+// Extraction of the part of a library reachable from a set of root declarations.
+mod subset;
+pub use subset::SubsetError;
+
+// Diagnostics reported by validation and analysis passes.
+mod diagnostic;
+pub use diagnostic::{Diagnostic, Severity};
+
+// The registry of known attributes, their typed values, and where they may be placed.
+pub mod attributes;
+pub use attributes::{AttributeRegistry, Attributes};
+
+// Helpers for visiting the types used throughout a library.
+pub mod visit;
+
+// Conversions between naming conventions and escaping of reserved words.
+pub mod case;
+
+// A configurable lint engine with built-in FIDL style rules.
+pub mod lint;
+
+// Wire-format layout of types.
+pub mod layout;
+
+// Analyses of declaration structure, layout, and message sizes.
+pub mod analysis;
+
+// Evaluation of literal constants.
+mod constant;
+
+// Printing of types, constants, and attributes in FIDL syntax.
+mod syntax;
+
+// Decoding and annotated dumps of wire-format messages.
+pub mod wire;
+
+// Decoding of recorded channel traffic into transcripts.
+pub mod trace;
+
+// Message transports, including an in-memory fake.
+pub mod transport;
+
+// A client that calls methods by name with JSON arguments.
+pub mod client;
+
+// A mock server that answers any protocol from its IR.
+pub mod server;
+
+// Wire-format conformance tests and emitters for binding tests.
+pub mod conformance;
+
+// Semantic diffs between two versions of a library.
+pub mod diff;
+
+// Mermaid and PlantUML diagrams of declarations and protocols.
+pub mod diagram;
+
+// Translation of libraries into schemas and declarations for other languages.
+pub mod codegen;
+
+// Proptest strategies generating values that conform to IR types.
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+
+// IR fixtures for unit tests.
+#[cfg(test)]
+mod fixtures;
+
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct DeclPath {
     pub library_name: String,
@@ -31,8 +107,8 @@ pub type DeclMap = IndexMap<Spanned<DeclPath>, DeclType>;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Library {
     pub name: Spanned<String>,
-    // FIXME(cramertj) the current IR doesn't include these, but it probably should?
-    #[serde(skip_serializing, skip_deserializing)]
+    // Older IR doesn't include library attributes, so they default to empty.
+    #[serde(rename = "maybe_attributes", default)]
     pub attributes: Vec<Spanned<Attribute>>,
     #[serde(rename = "const_declarations")]
     pub consts: Vec<Spanned<Const>>,
@@ -156,6 +232,7 @@ pub struct TableMember {
 }
 
 #[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum TableMemberType {
     Reserved,
     Field {
@@ -303,7 +380,7 @@ pub struct Type {
     pub nullable: Spanned<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum HandleSubtype {
     Bti,
@@ -378,6 +455,9 @@ pub enum TypeKind {
     },
     Handle {
         subtype: HandleSubtype,
+        /// The rights the handle must carry, if restricted by the declaration.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rights: Option<HandleRights>,
     },
     Request {
         subtype: DeclPath,
@@ -426,3 +506,26 @@ pub struct LibraryDep {
     pub name: String,
     pub declarations: SerOption<DeclMap>,
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json};
+
+    #[test]
+    fn library_attributes_are_maybe_attributes() {
+        let library = fixtures::library("example");
+        assert_eq!(library.attributes.len(), 1);
+        assert_eq!(*library.attributes[0].name, "Doc");
+
+        let mut json = serde_json::to_value(&library).unwrap();
+        assert_eq!(json["maybe_attributes"], json!([{"name": "Doc", "value": "A library"}]));
+        assert_eq!(json.get("attributes"), None);
+        let round_trip: Library = serde_json::from_str(&json.to_string()).unwrap();
+        assert_eq!(round_trip.attributes.doc(), Some("A library"));
+
+        // Older IR without library attributes still parses.
+        json.as_object_mut().unwrap().remove("maybe_attributes");
+        let library: Library = serde_json::from_str(&json.to_string()).unwrap();
+        assert!(library.attributes.is_empty());
+    }
+}
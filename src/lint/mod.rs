@@ -0,0 +1,285 @@
+//! A configurable lint engine for FIDL style rules.
+//!
+//! Rules implement `LintRule` and are run by a `Linter`, which applies the levels and options
+//! read from a TOML configuration such as:
+//!
+//! ```toml
+//! [rules.unbounded-string]
+//! level = "error"
+//!
+//! [rules.protocol-docs]
+//! level = "warning"
+//! only_discoverable = true
+//! ```
+
+use {
+    super::{Diagnostic, Library, Severity, Span},
+    serde::Deserialize,
+    std::{collections::BTreeMap, fmt, fs, io, path::Path},
+};
+
+mod rules;
+pub use rules::{
+    ConstNameCase, DeclNameCase, MemberNameCase, ProtocolDocs, ProtocolNameSuffix, UnboundedString,
+};
+
+/// How findings of a rule are reported.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// The rule is not run.
+    Allow,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RuleConfig {
+    /// Overrides the rule's default level.
+    pub level: Option<Level>,
+    /// Rule-specific options.
+    #[serde(flatten)]
+    pub options: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LintConfig {
+    /// Configuration for each rule, keyed by `LintRule::name`.
+    #[serde(default)]
+    pub rules: BTreeMap<String, RuleConfig>,
+}
+
+#[derive(Debug)]
+pub enum LintConfigError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+}
+
+impl fmt::Display for LintConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LintConfigError::Io(err) => write!(f, "failed to read lint configuration: {}", err),
+            LintConfigError::Toml(err) => write!(f, "invalid lint configuration: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for LintConfigError {}
+
+impl LintConfig {
+    pub fn from_toml(toml: &str) -> Result<LintConfig, LintConfigError> {
+        toml::from_str(toml).map_err(LintConfigError::Toml)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<LintConfig, LintConfigError> {
+        LintConfig::from_toml(&fs::read_to_string(path).map_err(LintConfigError::Io)?)
+    }
+}
+
+/// A single style rule.
+pub trait LintRule {
+    /// The kebab-case name used to configure the rule and as the code of its diagnostics.
+    fn name(&self) -> &str;
+
+    /// The level used when the configuration doesn't set one.
+    fn default_level(&self) -> Level {
+        Level::Warning
+    }
+
+    /// Reports every violation of the rule in `library` through `cx`.
+    fn check(&self, library: &Library, cx: &mut LintContext);
+}
+
+/// The state handed to a `LintRule` while it checks a library.
+pub struct LintContext<'a> {
+    rule: &'a str,
+    severity: Severity,
+    options: Option<&'a BTreeMap<String, toml::Value>>,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl LintContext<'_> {
+    /// Reports a violation at `span`.
+    pub fn report(&mut self, message: impl Into<String>, span: Option<Span>) {
+        self.diagnostics.push(Diagnostic::new(self.severity, self.rule, message, span));
+    }
+
+    /// Returns the value of a rule-specific option from the configuration.
+    pub fn option(&self, name: &str) -> Option<&toml::Value> {
+        self.options.and_then(|options| options.get(name))
+    }
+
+    pub fn bool_option(&self, name: &str, default: bool) -> bool {
+        self.option(name).and_then(toml::Value::as_bool).unwrap_or(default)
+    }
+
+    pub fn str_option<'b>(&'b self, name: &str, default: &'b str) -> &'b str {
+        self.option(name).and_then(toml::Value::as_str).unwrap_or(default)
+    }
+}
+
+/// Runs a set of rules over libraries.
+pub struct Linter {
+    rules: Vec<Box<dyn LintRule>>,
+    config: LintConfig,
+}
+
+impl Linter {
+    /// Creates a linter with the built-in rules.
+    pub fn new(config: LintConfig) -> Self {
+        let mut linter = Linter::without_builtins(config);
+        linter.add_rule(DeclNameCase);
+        linter.add_rule(MemberNameCase);
+        linter.add_rule(ConstNameCase);
+        linter.add_rule(ProtocolNameSuffix);
+        linter.add_rule(UnboundedString);
+        linter.add_rule(ProtocolDocs);
+        linter
+    }
+
+    /// Creates a linter with no rules.
+    pub fn without_builtins(config: LintConfig) -> Self {
+        Linter { rules: Vec::new(), config }
+    }
+
+    pub fn add_rule(&mut self, rule: impl LintRule + 'static) {
+        self.rules.push(Box::new(rule));
+    }
+
+    /// Returns the names of configured rules that the linter doesn't have.
+    pub fn unknown_rules(&self) -> Vec<&str> {
+        self.config
+            .rules
+            .keys()
+            .map(String::as_str)
+            .filter(|name| !self.rules.iter().any(|rule| rule.name() == *name))
+            .collect()
+    }
+
+    pub fn run(&self, library: &Library) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for rule in &self.rules {
+            let config = self.config.rules.get(rule.name());
+            let level = config.and_then(|config| config.level).unwrap_or(rule.default_level());
+            let severity = match level {
+                Level::Allow => continue,
+                Level::Warning => Severity::Warning,
+                Level::Error => Severity::Error,
+            };
+            let mut cx = LintContext {
+                rule: rule.name(),
+                severity,
+                options: config.map(|config| &config.options),
+                diagnostics: &mut diagnostics,
+            };
+            rule.check(library, &mut cx);
+        }
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::fixtures};
+
+    fn codes(linter: &Linter) -> Vec<(Severity, String)> {
+        let library = fixtures::library("example");
+        linter
+            .run(&library)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.code))
+            .collect()
+    }
+
+    #[test]
+    fn reads_levels_and_options() {
+        let config = LintConfig::from_toml(
+            r#"
+            [rules.unbounded-string]
+            level = "error"
+
+            [rules.protocol-docs]
+            level = "allow"
+            only_discoverable = true
+
+            [rules.protocol-name-suffix]
+            suffix = "Service"
+            "#,
+        )
+        .unwrap();
+        let levels: Vec<_> =
+            config.rules.iter().map(|(name, rule)| (name.as_str(), rule.level)).collect();
+        assert_eq!(
+            levels,
+            [
+                ("protocol-docs", Some(Level::Allow)),
+                ("protocol-name-suffix", None),
+                ("unbounded-string", Some(Level::Error)),
+            ]
+        );
+        assert_eq!(
+            config.rules["protocol-docs"].options.get("only_discoverable"),
+            Some(&toml::Value::Boolean(true))
+        );
+        assert_eq!(
+            config.rules["protocol-name-suffix"].options.get("suffix"),
+            Some(&toml::Value::String("Service".to_string()))
+        );
+        assert!(config.rules["unbounded-string"].options.is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_configuration() {
+        let err = LintConfig::from_toml("[rules.unbounded-string]\nlevel = \"fatal\"").unwrap_err();
+        assert!(matches!(err, LintConfigError::Toml(_)));
+        assert!(err.to_string().starts_with("invalid lint configuration: "), "{}", err);
+        assert!(LintConfig::from_toml("").unwrap().rules.is_empty());
+    }
+
+    #[test]
+    fn applies_levels() {
+        let warning = |code: &str| (Severity::Warning, code.to_string());
+        assert_eq!(
+            codes(&Linter::new(LintConfig::default())),
+            [
+                warning("member-name-case"),
+                warning("member-name-case"),
+                warning("const-name-case"),
+                warning("const-name-case"),
+                warning("protocol-name-suffix"),
+                warning("unbounded-string"),
+                warning("protocol-docs"),
+            ]
+        );
+        let config = LintConfig::from_toml(
+            r#"
+            rules.member-name-case.level = "allow"
+            rules.const-name-case.level = "allow"
+            rules.protocol-name-suffix.level = "allow"
+            rules.unbounded-string.level = "error"
+            rules.protocol-docs.level = "warning"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            codes(&Linter::new(config)),
+            [(Severity::Error, "unbounded-string".to_string()), warning("protocol-docs")]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_rules() {
+        let config = LintConfig::from_toml(
+            r#"
+            rules.protocol-docs.level = "allow"
+            rules.no-such-rule.level = "error"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(Linter::new(config.clone()).unknown_rules(), ["no-such-rule"]);
+        assert_eq!(
+            Linter::without_builtins(config).unknown_rules(),
+            ["no-such-rule", "protocol-docs"]
+        );
+    }
+}
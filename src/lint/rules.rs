@@ -0,0 +1,321 @@
+use {
+    super::{LintContext, LintRule},
+    crate::{
+        attributes::Attributes,
+        case::{
+            is_screaming_snake_case, is_snake_case, is_upper_camel_case, to_screaming_snake_case,
+            to_snake_case, to_upper_camel_case,
+        },
+        visit::for_each_type,
+        Library, Span, Spanned, TableMemberType, TypeKind,
+    },
+    std::collections::HashSet,
+};
+
+/// Declaration names other than consts must be `UpperCamelCase`.
+pub struct DeclNameCase;
+
+impl LintRule for DeclNameCase {
+    fn name(&self) -> &str {
+        "decl-name-case"
+    }
+
+    fn check(&self, library: &Library, cx: &mut LintContext) {
+        let consts: HashSet<_> = library.consts.iter().map(|decl| &decl.name.inner).collect();
+        for name in library.decl_names() {
+            if consts.contains(&name.inner) {
+                continue;
+            }
+            if !is_upper_camel_case(&name.decl_name) {
+                cx.report(
+                    format!(
+                        "declaration `{}` should be UpperCamelCase: `{}`",
+                        name.decl_name,
+                        to_upper_camel_case(&name.decl_name)
+                    ),
+                    name.span,
+                );
+            }
+        }
+    }
+}
+
+/// Member, method, and parameter names must be `snake_case`.
+pub struct MemberNameCase;
+
+impl MemberNameCase {
+    fn check_name(&self, kind: &str, name: &Spanned<String>, cx: &mut LintContext) {
+        if !is_snake_case(name) {
+            cx.report(
+                format!("{} `{}` should be snake_case: `{}`", kind, **name, to_snake_case(name)),
+                name.span,
+            );
+        }
+    }
+}
+
+impl LintRule for MemberNameCase {
+    fn name(&self) -> &str {
+        "member-name-case"
+    }
+
+    fn check(&self, library: &Library, cx: &mut LintContext) {
+        for decl in &library.protocols {
+            for method in &decl.methods {
+                self.check_name("method", &method.name, cx);
+                for (_, message) in method.messages() {
+                    for param in &message.parameters {
+                        self.check_name("parameter", &param.name, cx);
+                    }
+                }
+            }
+        }
+        for decl in &library.structs {
+            for member in &decl.members {
+                self.check_name("struct member", &member.name, cx);
+            }
+        }
+        for decl in &library.tables {
+            for member in &decl.members {
+                if let TableMemberType::Field { name, .. } = &member.member_type {
+                    self.check_name("table member", name, cx);
+                }
+            }
+        }
+        for decl in &library.unions {
+            for member in &decl.members {
+                self.check_name("union member", &member.name, cx);
+            }
+        }
+        for decl in &library.xunions {
+            for member in &decl.members {
+                self.check_name("xunion member", &member.name, cx);
+            }
+        }
+    }
+}
+
+/// Const, enum member, and bits member names must be `SCREAMING_SNAKE_CASE`.
+pub struct ConstNameCase;
+
+impl ConstNameCase {
+    fn check_name(&self, kind: &str, name: &str, span: Option<Span>, cx: &mut LintContext) {
+        if !is_screaming_snake_case(name) {
+            cx.report(
+                format!(
+                    "{} `{}` should be SCREAMING_SNAKE_CASE: `{}`",
+                    kind,
+                    name,
+                    to_screaming_snake_case(name)
+                ),
+                span,
+            );
+        }
+    }
+}
+
+impl LintRule for ConstNameCase {
+    fn name(&self) -> &str {
+        "const-name-case"
+    }
+
+    fn check(&self, library: &Library, cx: &mut LintContext) {
+        for decl in &library.consts {
+            self.check_name("const", &decl.name.decl_name, decl.name.span, cx);
+        }
+        for decl in &library.enums {
+            for member in &decl.members {
+                self.check_name("enum member", &member.name, member.name.span, cx);
+            }
+        }
+        for decl in &library.bits {
+            for member in &decl.members {
+                self.check_name("bits member", &member.name, member.name.span, cx);
+            }
+        }
+    }
+}
+
+/// Protocol names must not end in a redundant suffix, `Protocol` by default.
+///
+/// Options: `suffix` (string).
+pub struct ProtocolNameSuffix;
+
+impl LintRule for ProtocolNameSuffix {
+    fn name(&self) -> &str {
+        "protocol-name-suffix"
+    }
+
+    fn check(&self, library: &Library, cx: &mut LintContext) {
+        let suffix = cx.str_option("suffix", "Protocol").to_string();
+        for decl in &library.protocols {
+            if decl.name.decl_name.ends_with(&suffix) {
+                cx.report(
+                    format!("protocol `{}` should not end in `{}`", decl.name.decl_name, suffix),
+                    decl.name.span,
+                );
+            }
+        }
+    }
+}
+
+/// Strings must have a maximum length.
+pub struct UnboundedString;
+
+impl LintRule for UnboundedString {
+    fn name(&self) -> &str {
+        "unbounded-string"
+    }
+
+    fn check(&self, library: &Library, cx: &mut LintContext) {
+        let mut unbounded = Vec::new();
+        for_each_type(library, |site, r#type| {
+            r#type.walk(&mut |nested| {
+                if let TypeKind::String { maybe_element_count: None, .. } = &*nested.kind {
+                    unbounded.push((site.to_string(), r#type.span));
+                }
+            });
+        });
+        for (site, span) in unbounded {
+            cx.report(format!("`{}` uses a string without a maximum length", site), span);
+        }
+    }
+}
+
+/// Protocols must have doc comments.
+///
+/// Options: `only_discoverable` (bool), to only require docs on `[Discoverable]` protocols.
+pub struct ProtocolDocs;
+
+impl LintRule for ProtocolDocs {
+    fn name(&self) -> &str {
+        "protocol-docs"
+    }
+
+    fn check(&self, library: &Library, cx: &mut LintContext) {
+        let only_discoverable = cx.bool_option("only_discoverable", false);
+        for decl in &library.protocols {
+            if only_discoverable && !decl.attributes.is_discoverable() {
+                continue;
+            }
+            if decl.attributes.doc().map_or(true, |doc| doc.trim().is_empty()) {
+                cx.report(
+                    format!("protocol `{}` has no doc comment", decl.name.decl_name),
+                    decl.name.span,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            fixtures::{self, library_json, struct_json},
+            lint::{LintConfig, Linter},
+        },
+        serde_json::json,
+    };
+
+    fn lint(rule: impl LintRule + 'static, library: &Library) -> Vec<String> {
+        lint_with(rule, "", library)
+    }
+
+    /// Runs `rule` alone, configured by the TOML `config`.
+    fn lint_with(rule: impl LintRule + 'static, config: &str, library: &Library) -> Vec<String> {
+        let mut linter = Linter::without_builtins(LintConfig::from_toml(config).unwrap());
+        linter.add_rule(rule);
+        linter.run(library).into_iter().map(|diagnostic| diagnostic.message).collect()
+    }
+
+    #[test]
+    fn decl_name_case_skips_consts() {
+        let mut library = fixtures::library("example");
+        assert_eq!(lint(DeclNameCase, &library), Vec::<String>::new());
+        library.structs[0].name.decl_name = "point".to_string();
+        assert_eq!(
+            lint(DeclNameCase, &library),
+            ["declaration `point` should be UpperCamelCase: `Point`"]
+        );
+    }
+
+    #[test]
+    fn member_name_case() {
+        let mut library = fixtures::library("example");
+        assert_eq!(
+            lint(MemberNameCase, &library),
+            [
+                "method `Echo` should be snake_case: `echo`",
+                "method `OnEvent` should be snake_case: `on_event`",
+            ]
+        );
+        library.structs[0].members[0].name.inner = "xPos".to_string();
+        library.xunions[0].members[0].name.inner = "Num".to_string();
+        assert_eq!(
+            lint(MemberNameCase, &library),
+            [
+                "method `Echo` should be snake_case: `echo`",
+                "method `OnEvent` should be snake_case: `on_event`",
+                "struct member `xPos` should be snake_case: `x_pos`",
+                "xunion member `Num` should be snake_case: `num`",
+            ]
+        );
+    }
+
+    #[test]
+    fn const_name_case() {
+        let library = fixtures::library("example");
+        assert_eq!(
+            lint(ConstNameCase, &library),
+            [
+                "const `maxCount` should be SCREAMING_SNAKE_CASE: `MAX_COUNT`",
+                "enum member `Green` should be SCREAMING_SNAKE_CASE: `GREEN`",
+            ]
+        );
+    }
+
+    #[test]
+    fn protocol_name_suffix_option() {
+        let library = fixtures::library("example");
+        assert_eq!(
+            lint(ProtocolNameSuffix, &library),
+            ["protocol `EchoProtocol` should not end in `Protocol`"]
+        );
+        let config = "rules.protocol-name-suffix.suffix = \"Service\"";
+        assert_eq!(lint_with(ProtocolNameSuffix, config, &library), Vec::<String>::new());
+    }
+
+    #[test]
+    fn protocol_docs_option() {
+        let library = fixtures::library("example");
+        assert_eq!(lint(ProtocolDocs, &library), ["protocol `EchoProtocol` has no doc comment"]);
+        let config = "rules.protocol-docs.only_discoverable = true";
+        assert_eq!(lint_with(ProtocolDocs, config, &library), Vec::<String>::new());
+    }
+
+    #[test]
+    fn unbounded_string() {
+        let library = fixtures::library("example");
+        assert_eq!(
+            lint(UnboundedString, &library),
+            ["`test.example/EchoProtocol.Echo.request.value` uses a string without a maximum length"]
+        );
+        let names = json!({
+            "kind": "vector",
+            "element_type": {"kind": "string", "nullable": false},
+            "maybe_element_count": 4,
+            "nullable": false,
+        });
+        let label = json!({"kind": "string", "maybe_element_count": 10, "nullable": false});
+        let library = library_json(json!({
+            "name": "test.lint",
+            "struct_declarations": [struct_json("test.lint/Names", &[("names", names), ("label", label)])],
+        }));
+        assert_eq!(
+            lint(UnboundedString, &library),
+            ["`test.lint/Names.names` uses a string without a maximum length"]
+        );
+    }
+}
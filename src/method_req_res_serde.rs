@@ -37,7 +37,7 @@ impl<'de> de::Visitor<'de> for ReqResVisitor {
         let mut has: Option<bool> = None;
         let mut params: Option<Vec<Spanned<Parameter>>> = None;
         let mut size: Option<u32> = None;
-        while let Some(key) = map.next_key()? {
+        while let Some(key) = map.next_key::<String>()? {
             if key == self.has_key {
                 if has.is_some() {
                     return Err(de::Error::duplicate_field(self.has_key));
@@ -50,11 +50,13 @@ impl<'de> de::Visitor<'de> for ReqResVisitor {
                 params = Some(map.next_value()?);
             } else if key == self.size_key {
                 if size.is_some() {
-                    return Err(de::Error::duplicate_field(self.params_key));
+                    return Err(de::Error::duplicate_field(self.size_key));
                 }
                 size = Some(map.next_value()?);
             } else {
-                return Err(de::Error::unknown_field(key, &[]));
+                // Both the request and the response are flattened into the method, so each
+                // sees the other's keys.
+                map.next_value::<de::IgnoredAny>()?;
             }
         }
         let has = has.ok_or_else(|| de::Error::missing_field(self.has_key))?;
@@ -0,0 +1,423 @@
+//! A mock server that answers requests for any protocol using only its IR.
+
+use {
+    crate::{
+        transport::Transport,
+        visit::{Decl, DeclLookup},
+        wire::{self, decode_message, encode_message, ErrorKind, Message, TransactionHeader},
+        Method, MethodReqRes, PrimitiveSubtype, Protocol, Type, TypeKind,
+    },
+    serde_json::{Map, Value},
+    std::{collections::HashMap, fmt, io},
+};
+
+#[derive(Debug)]
+pub enum ServerError {
+    /// The protocol has no method with the given name.
+    UnknownMethod(String),
+    /// The method isn't an event, so it can't be sent by the server.
+    NotAnEvent(String),
+    /// The method has no ordinal in the IR.
+    MissingOrdinal(String),
+    /// A message arrived whose ordinal doesn't belong to a method of the protocol that takes
+    /// requests.
+    UnexpectedMessage {
+        txid: u32,
+        ordinal: u64,
+    },
+    Wire(wire::Error),
+    Transport(io::Error),
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServerError::UnknownMethod(name) => write!(f, "unknown method `{}`", name),
+            ServerError::NotAnEvent(name) => write!(f, "`{}` is not an event", name),
+            ServerError::MissingOrdinal(name) => write!(f, "`{}` has no ordinal", name),
+            ServerError::UnexpectedMessage { txid, ordinal } => {
+                write!(f, "unexpected message with txid {:#x} and ordinal {:#x}", txid, ordinal)
+            }
+            ServerError::Wire(err) => write!(f, "{}", err),
+            ServerError::Transport(err) => write!(f, "transport error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+impl From<wire::Error> for ServerError {
+    fn from(err: wire::Error) -> Self {
+        ServerError::Wire(err)
+    }
+}
+
+impl From<io::Error> for ServerError {
+    fn from(err: io::Error) -> Self {
+        ServerError::Transport(err)
+    }
+}
+
+/// Generates placeholder values that conform to types.
+struct Defaults<'a, L: ?Sized> {
+    lookup: &'a L,
+    next_handle: u32,
+    depth: u32,
+}
+
+impl<L: DeclLookup + ?Sized> Defaults<'_, L> {
+    fn handle(&mut self) -> Value {
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        handle.into()
+    }
+
+    fn value(&mut self, r#type: &Type) -> Result<Value, wire::Error> {
+        if *r#type.nullable {
+            return Ok(Value::Null);
+        }
+        self.depth += 1;
+        if self.depth > wire::MAX_DEPTH {
+            let kind = ErrorKind::DepthExceeded;
+            return Err(wire::Error { kind, path: String::new(), offset: None });
+        }
+        let value = self.non_null(r#type);
+        self.depth -= 1;
+        value
+    }
+
+    fn non_null(&mut self, r#type: &Type) -> Result<Value, wire::Error> {
+        let unknown = |what: String| wire::Error {
+            kind: ErrorKind::UnknownLayout { what },
+            path: String::new(),
+            offset: None,
+        };
+        Ok(match &*r#type.kind {
+            TypeKind::Primitive { subtype } => match subtype {
+                PrimitiveSubtype::Bool => false.into(),
+                PrimitiveSubtype::Float32 | PrimitiveSubtype::Float64 => 0.0.into(),
+                _ => 0.into(),
+            },
+            TypeKind::Handle { .. } | TypeKind::Request { .. } => self.handle(),
+            TypeKind::String { .. } => "".into(),
+            TypeKind::Vector { .. } => Value::Array(Vec::new()),
+            TypeKind::Array { element_type, element_count, .. } => {
+                let count = element_count
+                    .as_ref()
+                    .and_then(|count| count.as_u64())
+                    .ok_or_else(|| unknown("array size".to_string()))?;
+                (0..count).map(|_| self.value(element_type)).collect::<Result<_, _>>()?
+            }
+            TypeKind::Identifier { identifier, .. } => {
+                match self
+                    .lookup
+                    .lookup_decl(identifier)
+                    .ok_or_else(|| unknown(identifier.to_string()))?
+                {
+                    Decl::Const(_) => return Err(unknown(identifier.to_string())),
+                    Decl::Protocol(_) => self.handle(),
+                    Decl::Enum(decl) => match decl.members.first() {
+                        Some(member) => member.name.inner.clone().into(),
+                        None => 0.into(),
+                    },
+                    Decl::Bits(_) => 0.into(),
+                    Decl::Struct(decl) => {
+                        let mut object = Map::new();
+                        for member in &decl.members {
+                            object.insert(member.name.inner.clone(), self.value(&member.r#type)?);
+                        }
+                        Value::Object(object)
+                    }
+                    Decl::Table(_) => Value::Object(Map::new()),
+                    Decl::Union(decl) => {
+                        let member =
+                            decl.members.first().ok_or_else(|| unknown(identifier.to_string()))?;
+                        let mut object = Map::new();
+                        object.insert(member.name.inner.clone(), self.value(&member.r#type)?);
+                        Value::Object(object)
+                    }
+                    Decl::XUnion(decl) => {
+                        let member =
+                            decl.members.first().ok_or_else(|| unknown(identifier.to_string()))?;
+                        let mut object = Map::new();
+                        object.insert(member.name.inner.clone(), self.value(&member.r#type)?);
+                        Value::Object(object)
+                    }
+                }
+            }
+            TypeKind::UnresolvedRequest { .. } | TypeKind::UnresolvedIdentifier { .. } => {
+                return Err(unknown("unresolved type".to_string()))
+            }
+        })
+    }
+}
+
+/// Returns a placeholder value of `r#type`: zero, empty, or absent where possible, the first
+/// member of enums and unions, and made-up handle values counting up from 1.
+pub fn default_value<L: DeclLookup + ?Sized>(
+    lookup: &L,
+    r#type: &Type,
+) -> Result<Value, wire::Error> {
+    Defaults { lookup, next_handle: 1, depth: 0 }.value(r#type)
+}
+
+/// Returns a placeholder message with a value for every parameter, as in [`default_value`].
+pub fn default_message<L: DeclLookup + ?Sized>(
+    lookup: &L,
+    message: &MethodReqRes,
+) -> Result<Value, wire::Error> {
+    let mut defaults = Defaults { lookup, next_handle: 1, depth: 0 };
+    let mut object = Map::new();
+    for param in &message.parameters {
+        object.insert(param.name.inner.clone(), defaults.value(&param.r#type)?);
+    }
+    Ok(Value::Object(object))
+}
+
+/// A request received by a [`MockServer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub method: String,
+    pub txid: u32,
+    pub request: Value,
+}
+
+type Handler<'a> = Box<dyn FnMut(&Value) -> Value + Send + 'a>;
+
+/// An in-process server for one protocol that decodes requests by ordinal and answers them.
+///
+/// Requests are passed to the handler registered for their method with [`MockServer::on`], which
+/// returns the response as a JSON object keyed by parameter name. Two-way methods without a
+/// handler are answered with [`default_message`]. Every request is recorded and can be inspected
+/// with [`MockServer::calls`].
+pub struct MockServer<'a, L: ?Sized> {
+    lookup: &'a L,
+    protocol: &'a Protocol,
+    handlers: HashMap<String, Handler<'a>>,
+    calls: Vec<Call>,
+}
+
+impl<'a, L: DeclLookup + ?Sized> MockServer<'a, L> {
+    /// Creates a server for `protocol`, resolving the types it uses through `lookup`.
+    pub fn new(lookup: &'a L, protocol: &'a Protocol) -> Self {
+        MockServer { lookup, protocol, handlers: HashMap::new(), calls: Vec::new() }
+    }
+
+    /// Registers `handler` to answer requests for the method `name`, replacing any previous
+    /// handler. Handlers for one-way methods are called, but their results are ignored.
+    pub fn on(
+        &mut self,
+        name: &str,
+        handler: impl FnMut(&Value) -> Value + Send + 'a,
+    ) -> Result<&mut Self, ServerError> {
+        self.method(name)?;
+        self.handlers.insert(name.to_string(), Box::new(handler));
+        Ok(self)
+    }
+
+    /// Returns the requests received so far.
+    pub fn calls(&self) -> &[Call] {
+        &self.calls
+    }
+
+    fn method(&self, name: &str) -> Result<&'a Method, ServerError> {
+        self.protocol
+            .methods
+            .iter()
+            .map(|method| &**method)
+            .find(|method| *method.name == name)
+            .ok_or_else(|| ServerError::UnknownMethod(name.to_string()))
+    }
+
+    fn ordinal(method: &Method) -> Result<u64, ServerError> {
+        method
+            .ordinal
+            .or(*method.generated_ordinal)
+            .ok_or_else(|| ServerError::MissingOrdinal(method.name.inner.clone()))
+    }
+
+    /// Decodes and dispatches one request, returning the response to send if the method is
+    /// two-way.
+    pub fn handle(&mut self, message: &Message) -> Result<Option<Message>, ServerError> {
+        let header = TransactionHeader::decode(&message.bytes)?;
+        let unexpected =
+            || ServerError::UnexpectedMessage { txid: header.txid, ordinal: header.ordinal };
+        let method = self
+            .protocol
+            .methods
+            .iter()
+            .find(|method| {
+                *method.ordinal == Some(header.ordinal)
+                    || *method.generated_ordinal == Some(header.ordinal)
+            })
+            .ok_or_else(unexpected)?;
+        let request = method.request.as_ref().ok_or_else(unexpected)?;
+        let value = decode_message(self.lookup, request, &message.bytes, &message.handles)?.value;
+        let name = method.name.inner.clone();
+        let result = self.handlers.get_mut(&name).map(|handler| handler(&value));
+        self.calls.push(Call { method: name, txid: header.txid, request: value });
+        let response = match &method.response {
+            Some(response) => response,
+            None => return Ok(None),
+        };
+        let result = match result {
+            Some(result) => result,
+            None => default_message(self.lookup, response)?,
+        };
+        let header = TransactionHeader::new(header.txid, header.ordinal);
+        Ok(Some(encode_message(self.lookup, &header, response, &result)?))
+    }
+
+    /// Receives, handles, and answers one request.
+    pub fn serve_one(&mut self, transport: &mut impl Transport) -> Result<(), ServerError> {
+        let message = transport.receive()?;
+        if let Some(response) = self.handle(&message)? {
+            transport.send(response)?;
+        }
+        Ok(())
+    }
+
+    /// Serves requests until the client closes its end of the transport.
+    pub fn serve(&mut self, transport: &mut impl Transport) -> Result<(), ServerError> {
+        loop {
+            match self.serve_one(transport) {
+                Err(ServerError::Transport(err))
+                    if err.kind() == io::ErrorKind::BrokenPipe
+                        || err.kind() == io::ErrorKind::UnexpectedEof =>
+                {
+                    return Ok(())
+                }
+                result => result?,
+            }
+        }
+    }
+
+    /// Sends the event `name` with the parameters in `value`.
+    pub fn send_event(
+        &self,
+        transport: &mut impl Transport,
+        name: &str,
+        value: &Value,
+    ) -> Result<(), ServerError> {
+        let method = self.method(name)?;
+        if !method.is_event() {
+            return Err(ServerError::NotAnEvent(name.to_string()));
+        }
+        let header = TransactionHeader::new(0, Self::ordinal(method)?);
+        let response = method.response.as_ref().expect("events have a response");
+        transport.send(encode_message(self.lookup, &header, response, value)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            client::{DynamicClient, Event},
+            fixtures,
+            transport::{MemoryTransport, StreamTransport},
+        },
+        serde_json::json,
+    };
+
+    #[test]
+    fn generates_default_values() {
+        let library = fixtures::library("example");
+        let default = |r#type: Value| default_value(&library, &fixtures::r#type(r#type)).unwrap();
+        assert_eq!(
+            default(json!({"kind": "identifier", "identifier": "test.example/Node"})),
+            json!({"a": 0, "children": [], "b": 0, "color": "RED"})
+        );
+        assert_eq!(
+            default(json!({"kind": "identifier", "identifier": "test.example/Value"})),
+            json!({"num": 0})
+        );
+        assert_eq!(
+            default(json!({"kind": "identifier", "identifier": "test.example/Settings"})),
+            json!({})
+        );
+        assert_eq!(default(json!({"kind": "string", "nullable": true})), Value::Null);
+        assert_eq!(
+            default(json!({
+                "kind": "array",
+                "element_type": {"kind": "handle", "subtype": "vmo"},
+                "element_count": 2,
+            })),
+            json!([1, 2])
+        );
+
+        let methods = &library.protocols[0].methods;
+        let request = methods[0].request.as_ref().unwrap();
+        assert_eq!(
+            default_message(&library, request).unwrap(),
+            json!({"value": "", "p": {"x": 0, "y": 0}})
+        );
+        let event = methods[1].response.as_ref().unwrap();
+        assert_eq!(default_message(&library, event).unwrap(), json!({"vmo": 1}));
+    }
+
+    #[test]
+    fn serves_one_request() {
+        let library = fixtures::library("example");
+        let protocol = &library.protocols[0];
+        let (client_end, mut server_end) = MemoryTransport::pair();
+        let mut client = DynamicClient::new(&library, protocol, client_end);
+        let mut server = MockServer::new(&library, protocol);
+        server.on("Echo", |request| json!({"response": request["value"]})).unwrap();
+
+        let args = json!({"value": "hi", "p": {"x": 1, "y": 2}});
+        let request = client.encode_request("Echo", 7, &args).unwrap();
+        client.transport().send(request).unwrap();
+        server.serve_one(&mut server_end).unwrap();
+        let response = client.transport().try_receive().unwrap().unwrap();
+        assert_eq!(TransactionHeader::decode(&response.bytes).unwrap().txid, 7);
+        assert_eq!(client.decode_response("Echo", &response).unwrap(), json!({"response": "hi"}));
+        assert_eq!(server.calls(), [Call { method: "Echo".to_string(), txid: 7, request: args }]);
+    }
+
+    #[test]
+    fn rejects_unknown_methods_and_non_events() {
+        let library = fixtures::library("example");
+        let mut server = MockServer::new(&library, &library.protocols[0]);
+        assert!(matches!(
+            server.on("Missing", |_| Value::Null),
+            Err(ServerError::UnknownMethod(_))
+        ));
+        let (mut transport, _client_end) = MemoryTransport::pair();
+        assert!(matches!(
+            server.send_event(&mut transport, "Echo", &json!({})),
+            Err(ServerError::NotAnEvent(_))
+        ));
+        let event = server.handle(&Message {
+            bytes: TransactionHeader::new(0, 99).encode().to_vec(),
+            handles: Vec::new(),
+        });
+        assert!(matches!(event, Err(ServerError::UnexpectedMessage { txid: 0, ordinal: 99 })));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn serves_until_closed_over_socketpair() {
+        let library = fixtures::library("example");
+        let protocol = &library.protocols[0];
+        let (client_end, mut server_end) = StreamTransport::pair().unwrap();
+        let mut server = MockServer::new(&library, protocol);
+        std::thread::scope(|scope| {
+            let served = scope.spawn(move || {
+                server.send_event(&mut server_end, "OnEvent", &json!({"vmo": 5})).unwrap();
+                server.serve(&mut server_end).map(|()| server.calls().len())
+            });
+            let mut client = DynamicClient::new(&library, protocol, client_end);
+            let args = json!({"value": "hi", "p": {"x": 1, "y": 2}});
+            assert_eq!(client.call("Echo", &args).unwrap(), json!({"response": null}));
+            assert_eq!(
+                client.next_event().unwrap(),
+                Event { method: "OnEvent".to_string(), value: json!({"vmo": 5}) }
+            );
+            drop(client);
+            assert_eq!(served.join().unwrap().unwrap(), 1);
+        });
+    }
+}
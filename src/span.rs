@@ -1,6 +1,7 @@
 use {
     serde::{Deserialize, Deserializer, Serialize, Serializer},
     std::{
+        fmt,
         hash::{Hash, Hasher},
         ops::{Deref, DerefMut},
     },
@@ -16,6 +17,12 @@ pub struct Span {
     pub end: u32,
 }
 
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "file {}, bytes {}..{}", self.file_id.0, self.start, self.end)
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct Spanned<T> {
     pub inner: T,
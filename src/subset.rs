@@ -0,0 +1,188 @@
+use {
+    super::{DeclPath, Library},
+    std::{collections::HashSet, fmt},
+};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SubsetError {
+    /// A root was not declared in the library.
+    UnknownRoot { path: DeclPath },
+}
+
+impl fmt::Display for SubsetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SubsetError::UnknownRoot { path } => write!(f, "unknown declaration `{}`", path),
+        }
+    }
+}
+
+impl std::error::Error for SubsetError {}
+
+impl Library {
+    /// Returns the declarations of this library reachable from `roots` through type and constant
+    /// references, along with the declarations of other libraries they refer to directly.
+    pub fn reachable(&self, roots: &[DeclPath]) -> Result<HashSet<DeclPath>, SubsetError> {
+        let mut reachable = HashSet::new();
+        let mut stack = Vec::new();
+        for root in roots {
+            if self.lookup(root).is_none() {
+                return Err(SubsetError::UnknownRoot { path: root.clone() });
+            }
+            if reachable.insert(root.clone()) {
+                stack.push(root.clone());
+            }
+        }
+        while let Some(path) = stack.pop() {
+            // References into other libraries are kept but not followed, since only their
+            // names are known here.
+            let decl = match self.lookup(&path) {
+                Some(decl) => decl,
+                None => continue,
+            };
+            for reference in decl.references() {
+                if reachable.insert(reference.clone()) {
+                    stack.push(reference);
+                }
+            }
+        }
+        Ok(reachable)
+    }
+
+    /// Returns a copy of the library containing only the declarations reachable from `roots`,
+    /// e.g. a single protocol and the types its methods use.
+    ///
+    /// `declarations`, `declaration_order`, and `library_dependencies` are pruned to match, with
+    /// each dependency keeping only the declarations that are still referenced.
+    pub fn subset(&self, roots: &[DeclPath]) -> Result<Library, SubsetError> {
+        let reachable = self.reachable(roots)?;
+        let mut library = self.clone();
+
+        macro_rules! retain_decls {
+            ($($decls:ident),*) => {$(
+                library.$decls.retain(|decl| reachable.contains(&decl.name));
+            )*};
+        }
+        retain_decls!(consts, bits, enums, protocols, structs, tables, unions, xunions);
+
+        library.declarations.retain(|path, _| reachable.contains(&path.inner));
+        let reachable_names: HashSet<String> = reachable
+            .iter()
+            .map(|path| [&path.library_name, "/", &path.decl_name].concat())
+            .collect();
+        library.declaration_order.retain(|name| reachable_names.contains(name));
+        for dep in &mut library.library_dependencies {
+            if let Some(declarations) = &mut *dep.declarations {
+                declarations.retain(|path, _| reachable.contains(&path.inner));
+            }
+        }
+        library.library_dependencies.retain(|dep| {
+            reachable.iter().any(|path| path.library_name == dep.name)
+                && dep.declarations.as_ref().map_or(true, |declarations| !declarations.is_empty())
+        });
+        Ok(library)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::fixtures::{identifier, library_json, struct_json},
+        serde_json::json,
+    };
+
+    fn path(decl_name: &str) -> DeclPath {
+        DeclPath { library_name: "test.sub".to_string(), decl_name: decl_name.to_string() }
+    }
+
+    /// `Root` reaches `Server` through a request, `Inner` through an identifier, and `Max`
+    /// through a default value; `Inner` refers to `test.dep/Ext`. `Unused` is unreachable.
+    fn library() -> Library {
+        let mut root = struct_json(
+            "test.sub/Root",
+            &[
+                ("server", json!({"kind": "request", "subtype": "test.sub/Server"})),
+                ("inner", identifier("test.sub/Inner")),
+                ("count", json!({"kind": "primitive", "subtype": "uint32"})),
+            ],
+        );
+        root["members"][2]["maybe_default_value"] =
+            json!({"kind": "identifier", "identifier": "test.sub/Max"});
+        let names = ["Max", "Server", "Root", "Inner", "Unused"];
+        library_json(json!({
+            "name": "test.sub",
+            "const_declarations": [{
+                "attributes": [],
+                "type": {"kind": "primitive", "subtype": "uint32"},
+                "name": "test.sub/Max",
+                "value": {"kind": "literal", "literal": {"kind": "numeric", "value": "10"}},
+            }],
+            "interface_declarations": [
+                {"name": "test.sub/Server", "attributes": [], "methods": []},
+            ],
+            "struct_declarations": [
+                root,
+                struct_json("test.sub/Inner", &[("ext", identifier("test.dep/Ext"))]),
+                struct_json("test.sub/Unused", &[("other", identifier("test.other/Thing"))]),
+            ],
+            "declaration_order": names.iter().map(|name| format!("test.sub/{}", name)).collect::<Vec<_>>(),
+            "declarations": {
+                "test.sub/Max": "const",
+                "test.sub/Server": "interface",
+                "test.sub/Root": "struct",
+                "test.sub/Inner": "struct",
+                "test.sub/Unused": "struct",
+            },
+            "library_dependencies": [
+                {"name": "test.dep", "declarations": {"test.dep/Ext": "struct", "test.dep/Gone": "struct"}},
+                {"name": "test.other", "declarations": {"test.other/Thing": "struct"}},
+            ],
+        }))
+    }
+
+    #[test]
+    fn follows_requests_identifiers_and_constants() {
+        let reachable = library().reachable(&[path("Root")]).unwrap();
+        let mut names: Vec<_> = reachable.iter().map(ToString::to_string).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            ["test.dep/Ext", "test.sub/Inner", "test.sub/Max", "test.sub/Root", "test.sub/Server",]
+        );
+    }
+
+    #[test]
+    fn prunes_declarations_and_dependencies() {
+        let subset = library().subset(&[path("Root")]).unwrap();
+        let names: Vec<_> =
+            subset.decl_names().iter().map(|name| name.decl_name.as_str()).collect();
+        assert_eq!(names, ["Max", "Server", "Root", "Inner"]);
+        assert_eq!(
+            subset.declaration_order,
+            ["test.sub/Max", "test.sub/Server", "test.sub/Root", "test.sub/Inner"]
+        );
+        assert!(!subset.declarations.keys().any(|path| path.decl_name == "Unused"));
+        assert_eq!(subset.declarations.len(), 4);
+        assert_eq!(subset.library_dependencies.len(), 1);
+        let dep = &subset.library_dependencies[0];
+        assert_eq!(dep.name, "test.dep");
+        let kept: Vec<_> =
+            dep.declarations.as_ref().unwrap().keys().map(|path| path.to_string()).collect();
+        assert_eq!(kept, ["test.dep/Ext"]);
+    }
+
+    #[test]
+    fn rejects_unknown_roots() {
+        let library = library();
+        for root in &[
+            path("Missing"),
+            DeclPath { library_name: "test.dep".to_string(), decl_name: "Ext".to_string() },
+        ] {
+            assert_eq!(
+                library.subset(&[path("Root"), root.clone()]).unwrap_err(),
+                SubsetError::UnknownRoot { path: root.clone() }
+            );
+        }
+    }
+}
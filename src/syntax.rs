@@ -0,0 +1,232 @@
+//! Printing of types, constants, and attributes as they would be written in FIDL source, for
+//! diagnostics, diffs, and generated comments.
+
+use {
+    super::{Attribute, Constant, DeclPath, HandleSubtype, PrimitiveSubtype, Type, TypeKind},
+    std::fmt,
+};
+
+impl fmt::Display for PrimitiveSubtype {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            PrimitiveSubtype::Bool => "bool",
+            PrimitiveSubtype::Int8 => "int8",
+            PrimitiveSubtype::Int16 => "int16",
+            PrimitiveSubtype::Int32 => "int32",
+            PrimitiveSubtype::Int64 => "int64",
+            PrimitiveSubtype::UInt8 => "uint8",
+            PrimitiveSubtype::UInt16 => "uint16",
+            PrimitiveSubtype::UInt32 => "uint32",
+            PrimitiveSubtype::UInt64 => "uint64",
+            PrimitiveSubtype::Float32 => "float32",
+            PrimitiveSubtype::Float64 => "float64",
+        })
+    }
+}
+
+impl fmt::Display for HandleSubtype {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The serialized names are the ones used in FIDL source.
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::String(name)) => f.write_str(&name),
+            _ => Err(fmt::Error),
+        }
+    }
+}
+
+/// A type printed in FIDL syntax, with declarations in `library_name` referred to by their
+/// unqualified names.
+struct InLibrary<'a> {
+    r#type: &'a Type,
+    library_name: Option<&'a str>,
+}
+
+impl Type {
+    /// Returns the type printed as it would be written in FIDL source in `library_name`, e.g.
+    /// `vector<Node>:10?` rather than `vector<fuchsia.example.Node>:10?`.
+    pub fn display_in<'a>(&'a self, library_name: &'a str) -> impl fmt::Display + 'a {
+        InLibrary { r#type: self, library_name: Some(library_name) }
+    }
+}
+
+impl<'a> InLibrary<'a> {
+    fn element(&self, r#type: &'a Type) -> Self {
+        InLibrary { r#type, library_name: self.library_name }
+    }
+
+    fn write_path(&self, f: &mut fmt::Formatter, path: &DeclPath) -> fmt::Result {
+        if self.library_name == Some(path.library_name.as_str()) {
+            f.write_str(&path.decl_name)
+        } else {
+            write!(f, "{}.{}", path.library_name, path.decl_name)
+        }
+    }
+}
+
+impl fmt::Display for InLibrary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let r#type = self.r#type;
+        match &*r#type.kind {
+            TypeKind::Array { element_type, element_count, .. } => {
+                write!(f, "array<{}>", self.element(element_type))?;
+                if let Some(count) = &**element_count {
+                    write!(f, ":{}", **count)?;
+                }
+            }
+            TypeKind::Vector { element_type, maybe_element_count, .. } => {
+                write!(f, "vector<{}>", self.element(element_type))?;
+                if let Some(count) = maybe_element_count {
+                    write!(f, ":{}", **count)?;
+                }
+            }
+            TypeKind::String { maybe_element_count, .. } => {
+                f.write_str("string")?;
+                if let Some(count) = maybe_element_count {
+                    write!(f, ":{}", **count)?;
+                }
+            }
+            TypeKind::Handle { subtype: HandleSubtype::Handle, rights: None } => {
+                f.write_str("handle")?
+            }
+            TypeKind::Handle { subtype, rights: None } => write!(f, "handle<{}>", subtype)?,
+            TypeKind::Handle { subtype, rights: Some(rights) } => {
+                write!(f, "handle<{}, {:?}>", subtype, rights)?
+            }
+            TypeKind::Request { subtype, .. } => {
+                f.write_str("request<")?;
+                self.write_path(f, subtype)?;
+                f.write_str(">")?;
+            }
+            TypeKind::Primitive { subtype } => write!(f, "{}", subtype)?,
+            TypeKind::Identifier { identifier, .. } => self.write_path(f, identifier)?,
+            TypeKind::UnresolvedRequest { unresolved } => write!(f, "request<{}>", ***unresolved)?,
+            TypeKind::UnresolvedIdentifier { unresolved } => f.write_str(unresolved)?,
+        }
+        if *r#type.nullable {
+            f.write_str("?")?;
+        }
+        Ok(())
+    }
+}
+
+/// Types are printed as they would be written in FIDL source, with declarations referred to by
+/// their fully-qualified names, e.g. `vector<fuchsia.mem.Buffer>:10?`.
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        InLibrary { r#type: self, library_name: None }.fmt(f)
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Constant::Identifier { identifier } => f.write_str(identifier),
+            Constant::Literal { literal } => match &literal.value {
+                Some(value) => f.write_str(value),
+                None => f.write_str("default"),
+            },
+        }
+    }
+}
+
+/// Attributes are printed in FIDL source syntax, e.g. `[Transport = "Channel"]`.
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self.value {
+            Some(value) if !value.is_empty() => write!(f, "[{} = {:?}]", *self.name, **value),
+            _ => write!(f, "[{}]", *self.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{fixtures, Spanned},
+        serde_json::json,
+    };
+
+    fn constant(json: serde_json::Value) -> Constant {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn attribute(json: serde_json::Value) -> Spanned<Attribute> {
+        serde_json::from_str(&json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn displays_types() {
+        let cases = [
+            (json!({"kind": "primitive", "subtype": "uint8"}), "uint8"),
+            (json!({"kind": "string", "maybe_element_count": 10, "nullable": true}), "string:10?"),
+            (
+                json!({
+                    "kind": "vector",
+                    "element_type": {"kind": "identifier", "identifier": "test.example/Node"},
+                    "maybe_element_count": 4,
+                }),
+                "vector<test.example.Node>:4",
+            ),
+            (
+                json!({
+                    "kind": "array",
+                    "element_type": {"kind": "primitive", "subtype": "float64"},
+                    "element_count": 3,
+                }),
+                "array<float64>:3",
+            ),
+            (json!({"kind": "handle", "subtype": "handle"}), "handle"),
+            (json!({"kind": "handle", "subtype": "vmo", "nullable": true}), "handle<vmo>?"),
+            (
+                json!({"kind": "handle", "subtype": "channel", "rights": 12}),
+                "handle<channel, READ | WRITE>",
+            ),
+            (
+                json!({"kind": "request", "subtype": "test.example/EchoProtocol"}),
+                "request<test.example.EchoProtocol>",
+            ),
+        ];
+        for (json, expected) in cases.iter() {
+            assert_eq!(fixtures::r#type(json.clone()).to_string(), *expected);
+        }
+    }
+
+    #[test]
+    fn displays_types_in_their_library() {
+        let r#type = fixtures::r#type(json!({
+            "kind": "vector",
+            "element_type": {"kind": "identifier", "identifier": "test.example/Node", "nullable": true},
+        }));
+        assert_eq!(r#type.display_in("test.example").to_string(), "vector<Node?>");
+        assert_eq!(r#type.display_in("test.other").to_string(), "vector<test.example.Node?>");
+    }
+
+    #[test]
+    fn displays_constants() {
+        let identifier =
+            constant(json!({"kind": "identifier", "identifier": "test.example/maxCount"}));
+        assert_eq!(identifier.to_string(), "test.example/maxCount");
+        let literal =
+            constant(json!({"kind": "literal", "literal": {"kind": "string", "value": "\"hi\""}}));
+        assert_eq!(literal.to_string(), "\"hi\"");
+        let default = constant(json!({"kind": "literal", "literal": {"kind": "default"}}));
+        assert_eq!(default.to_string(), "default");
+    }
+
+    #[test]
+    fn displays_attributes() {
+        assert_eq!(
+            attribute(json!({"name": "Transport", "value": "Channel"})).to_string(),
+            "[Transport = \"Channel\"]"
+        );
+        assert_eq!(
+            attribute(json!({"name": "Doc", "value": " Says \"hi\".\n"})).to_string(),
+            "[Doc = \" Says \\\"hi\\\".\\n\"]"
+        );
+        assert_eq!(
+            attribute(json!({"name": "Discoverable", "value": ""})).to_string(),
+            "[Discoverable]"
+        );
+    }
+}
@@ -0,0 +1,437 @@
+//! Decoding of recorded channel traffic.
+//!
+//! A trace is a file of JSON lines, one per message read from or written to a channel:
+//!
+//! ```json
+//! {"direction": "write", "timestamp": 1000, "channel": 7, "bytes": "01000000 00000001 ...", "handles": []}
+//! ```
+//!
+//! `timestamp` is in nanoseconds, `channel` optionally identifies the channel so transaction ids
+//! on different channels aren't confused, `bytes` is hex with optional whitespace, and each handle
+//! is a `{"handle", "obj_type", "rights"}` object as in `wire::HandleInfo`.
+
+use {
+    crate::{
+        visit::Direction,
+        wire::{
+            identify, Error, HandleInfo, Identified, MessageKind, OrdinalIndex, TransactionHeader,
+        },
+        Library,
+    },
+    serde::{Deserialize, Serialize},
+    serde_json::json,
+    std::{
+        collections::HashMap,
+        fmt,
+        io::{self, BufRead},
+    },
+};
+
+/// Whether a recorded message was written to or read from the channel.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceDirection {
+    Write,
+    Read,
+}
+
+impl fmt::Display for TraceDirection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            TraceDirection::Write => "write",
+            TraceDirection::Read => "read",
+        })
+    }
+}
+
+/// One recorded message.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub direction: TraceDirection,
+    /// The time the message was recorded, in nanoseconds.
+    pub timestamp: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<u64>,
+    #[serde(with = "hex_serde")]
+    pub bytes: Vec<u8>,
+    #[serde(default)]
+    pub handles: Vec<HandleInfo>,
+}
+
+mod hex_serde {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        let digits: Vec<u8> = hex
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_digit(16).map(|digit| digit as u8))
+            .collect::<Option<_>>()
+            .ok_or_else(|| D::Error::custom("invalid hex digit"))?;
+        if digits.len() % 2 != 0 {
+            return Err(D::Error::custom("odd number of hex digits"));
+        }
+        Ok(digits.chunks(2).map(|pair| pair[0] << 4 | pair[1]).collect())
+    }
+}
+
+#[derive(Debug)]
+pub enum TraceError {
+    Io(io::Error),
+    /// A line of the trace wasn't a valid record.
+    Parse {
+        line: usize,
+        error: serde_json::Error,
+    },
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TraceError::Io(err) => write!(f, "failed to read trace: {}", err),
+            TraceError::Parse { line, error } => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+impl From<io::Error> for TraceError {
+    fn from(err: io::Error) -> Self {
+        TraceError::Io(err)
+    }
+}
+
+/// Reads records from JSON lines, skipping blank lines.
+pub fn read_records(reader: impl BufRead) -> Result<Vec<Record>, TraceError> {
+    let mut records = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(&line)
+            .map_err(|error| TraceError::Parse { line: i + 1, error })?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// A decoded record.
+#[derive(Debug, Clone)]
+pub struct TraceEntry<'a> {
+    pub record: Record,
+    pub message: Result<Identified<'a>, Error>,
+    /// For a request, the index of its response; for a response, the index of its request.
+    pub pair: Option<usize>,
+}
+
+impl TraceEntry<'_> {
+    fn txid(&self) -> Option<u32> {
+        match &self.message {
+            Ok(message) => Some(message.header.txid),
+            Err(_) => TransactionHeader::decode(&self.record.bytes).ok().map(|header| header.txid),
+        }
+    }
+}
+
+/// Decodes every record against `libraries`, pairing the requests of two-way methods with their
+/// responses by transaction id.
+pub fn decode_trace(libraries: &[Library], records: Vec<Record>) -> Vec<TraceEntry<'_>> {
+    let index = OrdinalIndex::new(libraries);
+    let mut pending: HashMap<(Option<u64>, u32), (usize, u64)> = HashMap::new();
+    let mut entries: Vec<TraceEntry<'_>> = Vec::with_capacity(records.len());
+    for record in records {
+        let header = TransactionHeader::decode(&record.bytes).ok();
+        let request = header.and_then(|header| {
+            let key = (record.channel, header.txid);
+            match pending.get(&key) {
+                Some(&(request, ordinal)) if header.txid != 0 && ordinal == header.ordinal => {
+                    pending.remove(&key);
+                    Some(request)
+                }
+                _ => None,
+            }
+        });
+        let direction = request.map(|_| Direction::Response);
+        let message = identify(libraries, &index, &record.bytes, &record.handles, direction);
+        let this = entries.len();
+        if let Some(request) = request {
+            entries[request].pair = Some(this);
+        } else if let Ok(message) = &message {
+            let two_way = message.method.method.response.is_some();
+            if message.kind == MessageKind::Request && two_way && message.header.txid != 0 {
+                let key = (record.channel, message.header.txid);
+                pending.insert(key, (this, message.header.ordinal));
+            }
+        }
+        entries.push(TraceEntry { record, message, pair: request });
+    }
+    entries
+}
+
+fn format_timestamp(nanos: u64) -> String {
+    format!("{}.{:06}", nanos / 1_000_000_000, nanos % 1_000_000_000 / 1_000)
+}
+
+/// Formats decoded entries as a human-readable transcript.
+pub fn transcript(entries: &[TraceEntry<'_>]) -> String {
+    let mut out = String::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let record = &entry.record;
+        let arrow = match record.direction {
+            TraceDirection::Write => "->",
+            TraceDirection::Read => "<-",
+        };
+        let channel =
+            record.channel.map(|channel| format!(" channel {}", channel)).unwrap_or_default();
+        let txid = entry.txid().map(|txid| format!(" txid {:#x}", txid)).unwrap_or_default();
+        out.push_str(&format!(
+            "#{} {} {} {}{}{}",
+            i,
+            format_timestamp(record.timestamp),
+            arrow,
+            record.direction,
+            channel,
+            txid
+        ));
+        match &entry.message {
+            Ok(message) => {
+                out.push_str(&format!(
+                    " {} {}.{}",
+                    message.kind, *message.method.protocol.name, *message.method.method.name
+                ));
+                if let Some(pair) = entry.pair {
+                    let other = &entries[pair].record;
+                    if message.kind == MessageKind::Response {
+                        let latency = record.timestamp.saturating_sub(other.timestamp);
+                        out.push_str(&format!(" (to #{}, {}us)", pair, latency / 1_000));
+                    } else {
+                        out.push_str(&format!(" (answered by #{})", pair));
+                    }
+                }
+                out.push('\n');
+                let value = serde_json::to_string_pretty(&message.decoded.value).unwrap();
+                for line in value.lines() {
+                    out.push_str("    ");
+                    out.push_str(line);
+                    out.push('\n');
+                }
+            }
+            Err(err) => out.push_str(&format!(" undecodable: {}\n", err)),
+        }
+    }
+    out
+}
+
+/// Converts a decoded entry to a JSON object for machine consumption.
+pub fn to_json(entries: &[TraceEntry<'_>], index: usize) -> serde_json::Value {
+    let entry = &entries[index];
+    let record = &entry.record;
+    let mut value = json!({
+        "index": index,
+        "timestamp": record.timestamp,
+        "direction": record.direction,
+        "channel": record.channel,
+        "txid": entry.txid(),
+        "pair": entry.pair,
+    });
+    let object = value.as_object_mut().unwrap();
+    match &entry.message {
+        Ok(message) => {
+            object.insert("kind".to_string(), message.kind.to_string().into());
+            object.insert("protocol".to_string(), message.method.protocol.name.to_string().into());
+            object.insert("method".to_string(), message.method.method.name.inner.clone().into());
+            object.insert("value".to_string(), message.decoded.value.clone());
+            if let (MessageKind::Response, Some(pair)) = (message.kind, entry.pair) {
+                let latency = record.timestamp.saturating_sub(entries[pair].record.timestamp);
+                object.insert("latency_ns".to_string(), latency.into());
+            }
+        }
+        Err(err) => {
+            object.insert("error".to_string(), err.to_string().into());
+        }
+    }
+    value
+}
+
+/// Formats decoded entries as JSON lines, one object per entry.
+pub fn json_lines(entries: &[TraceEntry<'_>]) -> String {
+    let mut out = String::new();
+    for i in 0..entries.len() {
+        out.push_str(&to_json(entries, i).to_string());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{fixtures, wire::encode_message},
+        serde_json::Value,
+    };
+
+    /// Records the message of `method` in `library` that goes in `direction`.
+    fn record(
+        library: &Library,
+        direction: TraceDirection,
+        timestamp: u64,
+        txid: u32,
+        method: usize,
+        value: Value,
+    ) -> Record {
+        let method = &library.protocols[0].methods[method];
+        let message = match direction {
+            TraceDirection::Write => method.request.as_ref(),
+            TraceDirection::Read => method.response.as_ref(),
+        };
+        let header = TransactionHeader::new(txid, method.ordinal.unwrap());
+        let encoded = encode_message(library, &header, message.unwrap(), &value).unwrap();
+        Record {
+            direction,
+            timestamp,
+            channel: None,
+            bytes: encoded.bytes,
+            handles: encoded.handles,
+        }
+    }
+
+    #[test]
+    fn reads_hex_with_whitespace() {
+        let trace = r#"{"direction": "write", "timestamp": 5, "channel": 7, "bytes": "01 0a\tFF"}
+
+{"direction": "read", "timestamp": 6, "bytes": "", "handles": [{"handle": 1, "obj_type": 3, "rights": 0}]}
+"#;
+        let records = read_records(trace.as_bytes()).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].bytes, [0x01, 0x0a, 0xff]);
+        assert_eq!(records[0].channel, Some(7));
+        assert_eq!(records[1].direction, TraceDirection::Read);
+        assert_eq!(records[1].handles.len(), 1);
+    }
+
+    #[test]
+    fn rejects_malformed_hex() {
+        for (bytes, message) in
+            [("010", "odd number of hex digits"), ("0g", "invalid hex digit")].iter()
+        {
+            let trace = format!(
+                "{{\"direction\": \"write\", \"timestamp\": 0, \"bytes\": \"00\"}}\n\
+                 {{\"direction\": \"write\", \"timestamp\": 0, \"bytes\": \"{}\"}}\n",
+                bytes
+            );
+            match read_records(trace.as_bytes()) {
+                Err(TraceError::Parse { line: 2, error }) => {
+                    assert!(error.to_string().contains(message), "{}", error)
+                }
+                other => panic!("expected a parse error on line 2, got {:?}", other),
+            }
+        }
+    }
+
+    fn records(library: &Library) -> Vec<Record> {
+        vec![
+            record(
+                library,
+                TraceDirection::Write,
+                1_000_000,
+                1,
+                0,
+                json!({"value": "hi", "p": {"x": 1, "y": 2}}),
+            ),
+            record(library, TraceDirection::Read, 1_500_000, 0, 1, json!({"vmo": 4})),
+            record(library, TraceDirection::Read, 3_000_000, 1, 0, json!({"response": "hi"})),
+            record(library, TraceDirection::Read, 4_000_000, 5, 0, json!({"response": null})),
+        ]
+    }
+
+    #[test]
+    fn pairs_requests_with_responses() {
+        let libraries = [fixtures::library("example")];
+        let entries = decode_trace(&libraries, records(&libraries[0]));
+        let kinds: Vec<_> =
+            entries.iter().map(|entry| entry.message.as_ref().unwrap().kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                MessageKind::Request,
+                MessageKind::Event,
+                MessageKind::Response,
+                MessageKind::Response
+            ]
+        );
+        let pairs: Vec<_> = entries.iter().map(|entry| entry.pair).collect();
+        assert_eq!(pairs, [Some(2), None, Some(0), None]);
+    }
+
+    #[test]
+    fn writes_transcript() {
+        let libraries = [fixtures::library("example")];
+        let mut records = records(&libraries[0]);
+        records.truncate(3);
+        records.push(Record {
+            direction: TraceDirection::Read,
+            timestamp: 5_000_000_123,
+            channel: Some(3),
+            bytes: vec![1, 2],
+            handles: Vec::new(),
+        });
+        let entries = decode_trace(&libraries, records);
+        assert_eq!(
+            transcript(&entries),
+            r#"#0 0.001000 -> write txid 0x1 request test.example/EchoProtocol.Echo (answered by #2)
+    {
+      "p": {
+        "x": 1,
+        "y": 2
+      },
+      "value": "hi"
+    }
+#1 0.001500 <- read txid 0x0 event test.example/EchoProtocol.OnEvent
+    {
+      "vmo": 4
+    }
+#2 0.003000 <- read txid 0x1 response test.example/EchoProtocol.Echo (to #0, 2000us)
+    {
+      "response": "hi"
+    }
+#3 5.000000 <- read channel 3 undecodable: `header`: message is too short at offset 0x0
+"#
+        );
+    }
+
+    #[test]
+    fn writes_json_lines() {
+        let libraries = [fixtures::library("example")];
+        let entries = decode_trace(&libraries, records(&libraries[0]));
+        let lines: Vec<Value> =
+            json_lines(&entries).lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[2],
+            json!({
+                "index": 2,
+                "timestamp": 3_000_000,
+                "direction": "read",
+                "channel": null,
+                "txid": 1,
+                "pair": 0,
+                "kind": "response",
+                "protocol": "test.example/EchoProtocol",
+                "method": "Echo",
+                "value": {"response": "hi"},
+                "latency_ns": 2_000_000,
+            })
+        );
+        assert_eq!(lines[3]["pair"], Value::Null);
+        assert!(lines[3].get("latency_ns").is_none());
+    }
+}
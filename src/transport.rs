@@ -0,0 +1,203 @@
+//! Transports that carry encoded messages between a client and a server.
+
+use {
+    crate::{
+        analysis::message_size::{CHANNEL_MAX_MSG_BYTES, CHANNEL_MAX_MSG_HANDLES},
+        wire::{HandleInfo, Message},
+        HandleRights,
+    },
+    std::{
+        io::{self, Read, Write},
+        sync::mpsc::{channel, Receiver, Sender},
+    },
+};
+
+/// A bidirectional, message-oriented connection, such as one end of a Zircon channel.
+pub trait Transport {
+    fn send(&mut self, message: Message) -> io::Result<()>;
+
+    /// Blocks until a message arrives.
+    fn receive(&mut self) -> io::Result<Message>;
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn send(&mut self, message: Message) -> io::Result<()> {
+        (**self).send(message)
+    }
+
+    fn receive(&mut self) -> io::Result<Message> {
+        (**self).receive()
+    }
+}
+
+/// One end of an in-process connection created by [`MemoryTransport::pair`].
+///
+/// Messages sent on one end are received on the other in order. Once either end is dropped,
+/// operations on the other fail with `BrokenPipe`.
+#[derive(Debug)]
+pub struct MemoryTransport {
+    sender: Sender<Message>,
+    receiver: Receiver<Message>,
+}
+
+impl MemoryTransport {
+    pub fn pair() -> (MemoryTransport, MemoryTransport) {
+        let (a_sender, b_receiver) = channel();
+        let (b_sender, a_receiver) = channel();
+        (
+            MemoryTransport { sender: a_sender, receiver: a_receiver },
+            MemoryTransport { sender: b_sender, receiver: b_receiver },
+        )
+    }
+
+    /// Returns the next message if one has already arrived.
+    pub fn try_receive(&mut self) -> io::Result<Option<Message>> {
+        match self.receiver.try_recv() {
+            Ok(message) => Ok(Some(message)),
+            Err(std::sync::mpsc::TryRecvError::Empty) => Ok(None),
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => Err(closed()),
+        }
+    }
+}
+
+fn closed() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "peer closed")
+}
+
+impl Transport for MemoryTransport {
+    fn send(&mut self, message: Message) -> io::Result<()> {
+        self.sender.send(message).map_err(|_| closed())
+    }
+
+    fn receive(&mut self) -> io::Result<Message> {
+        self.receiver.recv().map_err(|_| closed())
+    }
+}
+
+/// A transport over a byte stream, such as one end of a Unix socketpair.
+///
+/// Each message is framed as its byte count and handle count as little-endian `u32`s, followed by
+/// the bytes, followed by each handle's value, object type, and rights as `u32`s. Handles are only
+/// described, not transferred. Messages larger than a Zircon channel allows are refused with
+/// `InvalidInput` when sent and rejected with `InvalidData` when received.
+#[derive(Debug)]
+pub struct StreamTransport<S> {
+    stream: S,
+}
+
+impl<S: Read + Write> StreamTransport<S> {
+    pub fn new(stream: S) -> Self {
+        StreamTransport { stream }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+#[cfg(unix)]
+impl StreamTransport<std::os::unix::net::UnixStream> {
+    /// Creates a connected pair of transports over a Unix socketpair.
+    pub fn pair() -> io::Result<(Self, Self)> {
+        let (a, b) = std::os::unix::net::UnixStream::pair()?;
+        Ok((StreamTransport::new(a), StreamTransport::new(b)))
+    }
+}
+
+/// Returns an error of `kind` if a message of `num_bytes` and `num_handles` exceeds the limits
+/// of a Zircon channel.
+fn check_limits(kind: io::ErrorKind, num_bytes: u64, num_handles: u64) -> io::Result<()> {
+    if num_bytes > CHANNEL_MAX_MSG_BYTES || num_handles > CHANNEL_MAX_MSG_HANDLES {
+        return Err(io::Error::new(
+            kind,
+            format!(
+                "message of {} bytes and {} handles exceeds the channel limits",
+                num_bytes, num_handles
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn read_u32(stream: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    stream.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+impl<S: Read + Write> Transport for StreamTransport<S> {
+    fn send(&mut self, message: Message) -> io::Result<()> {
+        let (num_bytes, num_handles) = (message.bytes.len(), message.handles.len());
+        check_limits(io::ErrorKind::InvalidInput, num_bytes as u64, num_handles as u64)?;
+        let mut frame = Vec::with_capacity(8 + num_bytes + 12 * num_handles);
+        frame.extend_from_slice(&(num_bytes as u32).to_le_bytes());
+        frame.extend_from_slice(&(num_handles as u32).to_le_bytes());
+        frame.extend_from_slice(&message.bytes);
+        for handle in &message.handles {
+            frame.extend_from_slice(&handle.handle.to_le_bytes());
+            frame.extend_from_slice(&handle.obj_type.to_le_bytes());
+            frame.extend_from_slice(&handle.rights.bits().to_le_bytes());
+        }
+        self.stream.write_all(&frame)?;
+        self.stream.flush()
+    }
+
+    fn receive(&mut self) -> io::Result<Message> {
+        let num_bytes = read_u32(&mut self.stream)?;
+        let num_handles = read_u32(&mut self.stream)?;
+        check_limits(io::ErrorKind::InvalidData, num_bytes.into(), num_handles.into())?;
+        let (num_bytes, num_handles) = (num_bytes as usize, num_handles as usize);
+        let mut bytes = vec![0; num_bytes];
+        self.stream.read_exact(&mut bytes)?;
+        let mut handles = Vec::with_capacity(num_handles);
+        for _ in 0..num_handles {
+            handles.push(HandleInfo {
+                handle: read_u32(&mut self.stream)?,
+                obj_type: read_u32(&mut self.stream)?,
+                rights: HandleRights(read_u32(&mut self.stream)?),
+            });
+        }
+        Ok(Message { bytes, handles })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::io::Cursor};
+
+    #[test]
+    fn stream_round_trips_messages() {
+        let mut transport = StreamTransport::new(Cursor::new(Vec::new()));
+        let handle = HandleInfo { handle: 5, obj_type: 3, rights: HandleRights::SAME_RIGHTS };
+        let message = Message { bytes: vec![1, 2, 3], handles: vec![handle] };
+        transport.send(message.clone()).unwrap();
+        transport.stream.set_position(0);
+        assert_eq!(transport.receive().unwrap(), message);
+    }
+
+    #[test]
+    fn stream_rejects_oversized_frames() {
+        for &(num_bytes, num_handles) in [(65537u32, 0u32), (u32::MAX, 0), (0, 65)].iter() {
+            let mut frame = num_bytes.to_le_bytes().to_vec();
+            frame.extend_from_slice(&num_handles.to_le_bytes());
+            let mut transport = StreamTransport::new(Cursor::new(frame));
+            let err = transport.receive().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn stream_refuses_to_send_oversized_messages() {
+        let handle = HandleInfo { handle: 1, obj_type: 0, rights: HandleRights::SAME_RIGHTS };
+        let oversized = [
+            Message { bytes: vec![0; 65537], handles: Vec::new() },
+            Message { bytes: Vec::new(), handles: vec![handle; 65] },
+        ];
+        for message in oversized.iter() {
+            let mut transport = StreamTransport::new(Cursor::new(Vec::new()));
+            let err = transport.send(message.clone()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert!(transport.into_inner().into_inner().is_empty());
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use {
+    super::{
+        Attribute, Bits, Const, Constant, DeclPath, Enum, Library, Method, MethodReqRes, Protocol,
+        Spanned, Struct, Table, TableMemberType, Type, TypeKind, Union, XUnion,
+    },
+    std::fmt,
+};
+
+/// The location of a type within a library: the declaration that contains it and, for types of
+/// members and parameters, the member's name.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct Site<'a> {
+    pub decl: &'a Spanned<DeclPath>,
+    /// The member name, or `Method.request.param` / `Method.response.param` for parameters.
+    pub member: Option<String>,
+}
+
+impl fmt::Display for DeclPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.library_name, self.decl_name)
+    }
+}
+
+impl fmt::Display for Site<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", **self.decl)?;
+        if let Some(member) = &self.member {
+            write!(f, ".{}", member)?;
+        }
+        Ok(())
+    }
+}
+
+/// Which half of a method a set of parameters belongs to.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum Direction {
+    Request,
+    Response,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Direction::Request => "request",
+            Direction::Response => "response",
+        })
+    }
+}
+
+impl Method {
+    /// Returns the request and response of the method, whichever are present.
+    pub fn messages(&self) -> impl Iterator<Item = (Direction, &Spanned<MethodReqRes>)> {
+        let request = self.request.as_ref().map(|request| (Direction::Request, request));
+        let response = self.response.as_ref().map(|response| (Direction::Response, response));
+        request.into_iter().chain(response)
+    }
+
+    /// Returns `true` if the method is an event, sent by the server without a request.
+    pub fn is_event(&self) -> bool {
+        self.request.is_none() && self.response.is_some()
+    }
+}
+
+impl Type {
+    /// Returns the non-nullable type referring to the declaration at `path`.
+    pub fn from_decl(path: DeclPath) -> Type {
+        Type {
+            kind: Spanned::without_span(TypeKind::Identifier {
+                identifier: Spanned::without_span(path),
+                unresolved: None,
+            }),
+            nullable: Spanned::without_span(false),
+        }
+    }
+
+    /// Calls `f` with this type and, recursively, the element types of arrays and vectors.
+    pub fn walk<'a>(&'a self, f: &mut impl FnMut(&'a Type)) {
+        f(self);
+        match &*self.kind {
+            TypeKind::Array { element_type, .. } | TypeKind::Vector { element_type, .. } => {
+                element_type.walk(f)
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the declaration this type refers to directly, if any. `request<P>` types refer
+    /// to the protocol `P`.
+    pub fn referenced_decl(&self) -> Option<&DeclPath> {
+        match &*self.kind {
+            TypeKind::Identifier { identifier, .. } => Some(identifier),
+            TypeKind::Request { subtype, .. } => Some(subtype),
+            _ => None,
+        }
+    }
+}
+
+/// Calls `f` with the type of every const, bits, member, and method parameter in `library`,
+/// in declaration-list order. Nested element types are not visited; use `Type::walk` for those.
+pub fn for_each_type<'a>(library: &'a Library, mut f: impl FnMut(Site<'a>, &'a Spanned<Type>)) {
+    let site = |decl, member: &str| Site { decl, member: Some(member.to_string()) };
+    for decl in &library.consts {
+        f(Site { decl: &decl.name, member: None }, &decl.r#type);
+    }
+    for decl in &library.bits {
+        if let Some(r#type) = &*decl.r#type {
+            f(Site { decl: &decl.name, member: None }, r#type);
+        }
+    }
+    for decl in &library.protocols {
+        for method in &decl.methods {
+            for (direction, message) in method.messages() {
+                for param in &message.parameters {
+                    let member = format!("{}.{}.{}", *method.name, direction, *param.name);
+                    f(site(&decl.name, &member), &param.r#type);
+                }
+            }
+        }
+    }
+    for decl in &library.structs {
+        for member in &decl.members {
+            f(site(&decl.name, &member.name), &member.r#type);
+        }
+    }
+    for decl in &library.tables {
+        for member in &decl.members {
+            if let TableMemberType::Field { r#type, name, .. } = &member.member_type {
+                f(site(&decl.name, name), r#type);
+            }
+        }
+    }
+    for decl in &library.unions {
+        for member in &decl.members {
+            f(site(&decl.name, &member.name), &member.r#type);
+        }
+    }
+    for decl in &library.xunions {
+        for member in &decl.members {
+            f(site(&decl.name, &member.name), &member.r#type);
+        }
+    }
+}
+
+impl Library {
+    /// Returns the names of every declaration in the library, in declaration-list order.
+    pub fn decl_names(&self) -> Vec<&Spanned<DeclPath>> {
+        let mut names = Vec::new();
+        names.extend(self.consts.iter().map(|decl| &decl.name));
+        names.extend(self.bits.iter().map(|decl| &decl.name));
+        names.extend(self.enums.iter().map(|decl| &decl.name));
+        names.extend(self.protocols.iter().map(|decl| &decl.name));
+        names.extend(self.structs.iter().map(|decl| &decl.name));
+        names.extend(self.tables.iter().map(|decl| &decl.name));
+        names.extend(self.unions.iter().map(|decl| &decl.name));
+        names.extend(self.xunions.iter().map(|decl| &decl.name));
+        names
+    }
+}
+
+/// A reference to any declaration in a library.
+#[derive(Debug, Clone, Copy)]
+pub enum Decl<'a> {
+    Const(&'a Const),
+    Bits(&'a Bits),
+    Enum(&'a Enum),
+    Protocol(&'a Protocol),
+    Struct(&'a Struct),
+    Table(&'a Table),
+    Union(&'a Union),
+    XUnion(&'a XUnion),
+}
+
+impl<'a> Decl<'a> {
+    pub fn name(&self) -> &'a Spanned<DeclPath> {
+        match self {
+            Decl::Const(decl) => &decl.name,
+            Decl::Bits(decl) => &decl.name,
+            Decl::Enum(decl) => &decl.name,
+            Decl::Protocol(decl) => &decl.name,
+            Decl::Struct(decl) => &decl.name,
+            Decl::Table(decl) => &decl.name,
+            Decl::Union(decl) => &decl.name,
+            Decl::XUnion(decl) => &decl.name,
+        }
+    }
+
+    pub fn attributes(&self) -> &'a [Spanned<Attribute>] {
+        match self {
+            Decl::Const(decl) => &decl.attributes,
+            Decl::Bits(decl) => &decl.attributes,
+            Decl::Enum(decl) => &decl.attributes,
+            Decl::Protocol(decl) => &decl.attributes,
+            Decl::Struct(decl) => &decl.attributes,
+            Decl::Table(decl) => &decl.attributes,
+            Decl::Union(decl) => &decl.attributes,
+            Decl::XUnion(decl) => &decl.attributes,
+        }
+    }
+
+    /// Returns the declarations this one refers to, through the types of its members and
+    /// parameters (including element types) and through constants, in order of first reference.
+    pub fn references(&self) -> Vec<DeclPath> {
+        let mut types: Vec<&Type> = Vec::new();
+        let mut constants: Vec<&Constant> = Vec::new();
+        match self {
+            Decl::Const(decl) => {
+                types.push(&decl.r#type);
+                constants.push(&decl.value);
+            }
+            Decl::Bits(decl) => {
+                types.extend(decl.r#type.iter().map(|r#type| &r#type.inner));
+                constants.extend(
+                    decl.members
+                        .iter()
+                        .filter_map(|member| member.value.as_ref())
+                        .map(|value| &value.inner),
+                );
+            }
+            Decl::Enum(decl) => {
+                constants.extend(
+                    decl.members
+                        .iter()
+                        .filter_map(|member| member.value.as_ref())
+                        .map(|value| &value.inner),
+                );
+            }
+            Decl::Protocol(decl) => {
+                for method in &decl.methods {
+                    for (_, message) in method.messages() {
+                        types.extend(message.parameters.iter().map(|param| &param.r#type.inner));
+                    }
+                }
+            }
+            Decl::Struct(decl) => {
+                for member in &decl.members {
+                    types.push(&member.r#type);
+                    constants.extend(member.maybe_default_value.as_ref().map(|value| &value.inner));
+                }
+            }
+            Decl::Table(decl) => {
+                for member in &decl.members {
+                    if let TableMemberType::Field { r#type, maybe_default_value, .. } =
+                        &member.member_type
+                    {
+                        types.push(r#type);
+                        constants.extend(maybe_default_value.as_ref().map(|value| &value.inner));
+                    }
+                }
+            }
+            Decl::Union(decl) => {
+                types.extend(decl.members.iter().map(|member| &member.r#type.inner))
+            }
+            Decl::XUnion(decl) => {
+                types.extend(decl.members.iter().map(|member| &member.r#type.inner))
+            }
+        }
+        let mut references = Vec::new();
+        let mut add = |path: &DeclPath| {
+            if !references.contains(path) {
+                references.push(path.clone());
+            }
+        };
+        for r#type in types {
+            r#type.walk(&mut |r#type| {
+                if let Some(path) = r#type.referenced_decl() {
+                    add(path);
+                }
+            });
+        }
+        for constant in constants {
+            if let Some(path) = constant.referenced_decl() {
+                add(&path);
+            }
+        }
+        references
+    }
+}
+
+impl Library {
+    /// Finds the declaration named `path` in this library.
+    pub fn lookup(&self, path: &DeclPath) -> Option<Decl<'_>> {
+        fn find<'a, T>(
+            decls: &'a [Spanned<T>],
+            name: impl Fn(&T) -> &DeclPath,
+            path: &DeclPath,
+        ) -> Option<&'a T> {
+            decls.iter().map(|decl| &decl.inner).find(|decl| name(decl) == path)
+        }
+        if path.library_name != *self.name {
+            return None;
+        }
+        None.or_else(|| find(&self.consts, |d| &d.name, path).map(Decl::Const))
+            .or_else(|| find(&self.bits, |d| &d.name, path).map(Decl::Bits))
+            .or_else(|| find(&self.enums, |d| &d.name, path).map(Decl::Enum))
+            .or_else(|| find(&self.protocols, |d| &d.name, path).map(Decl::Protocol))
+            .or_else(|| find(&self.structs, |d| &d.name, path).map(Decl::Struct))
+            .or_else(|| find(&self.tables, |d| &d.name, path).map(Decl::Table))
+            .or_else(|| find(&self.unions, |d| &d.name, path).map(Decl::Union))
+            .or_else(|| find(&self.xunions, |d| &d.name, path).map(Decl::XUnion))
+    }
+}
+
+/// A source of declarations, either a single library or a set of libraries that depend on each
+/// other.
+pub trait DeclLookup {
+    fn lookup_decl(&self, path: &DeclPath) -> Option<Decl<'_>>;
+}
+
+impl DeclLookup for Library {
+    fn lookup_decl(&self, path: &DeclPath) -> Option<Decl<'_>> {
+        self.lookup(path)
+    }
+}
+
+impl DeclLookup for [Library] {
+    fn lookup_decl(&self, path: &DeclPath) -> Option<Decl<'_>> {
+        self.iter().find(|library| *library.name == path.library_name)?.lookup(path)
+    }
+}
+
+/// A library along with the libraries it depends on, looked up in that order.
+#[derive(Debug, Clone, Copy)]
+pub struct WithDeps<'a> {
+    pub library: &'a Library,
+    pub deps: &'a [Library],
+}
+
+impl DeclLookup for WithDeps<'_> {
+    fn lookup_decl(&self, path: &DeclPath) -> Option<Decl<'_>> {
+        self.library.lookup(path).or_else(|| self.deps.lookup_decl(path))
+    }
+}
@@ -0,0 +1,776 @@
+use {
+    super::{join, Error, ErrorKind, HandleInfo, Region, RegionKind, MAX_DEPTH},
+    crate::{
+        layout::{
+            align_to, decl_shape, table_fields, type_shape, underlying_primitive,
+            union_data_offset, ALLOC_ABSENT, ALLOC_PRESENT, ENVELOPE_SIZE, HANDLE_ABSENT,
+            HANDLE_PRESENT, HEADER_SIZE, OUT_OF_LINE_ALIGNMENT,
+        },
+        visit::{Decl, DeclLookup},
+        DeclPath, HandleRights, HandleSubtype, MethodReqRes, PrimitiveSubtype, Spanned, Struct,
+        Table, Type, TypeKind, Union, XUnion,
+    },
+    serde_json::{Map, Value},
+    std::convert::TryFrom,
+};
+
+/// A decoded value along with the labeled byte ranges it was decoded from.
+#[derive(Debug, Clone)]
+pub struct Decoded {
+    pub value: Value,
+    pub regions: Vec<Region>,
+}
+
+pub(super) struct Decoder<'a, L: ?Sized> {
+    lookup: &'a L,
+    bytes: &'a [u8],
+    handles: &'a [HandleInfo],
+    next_out_of_line: usize,
+    next_handle: usize,
+    depth: u32,
+    pub(super) regions: Vec<Region>,
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+pub(super) fn error(kind: ErrorKind, path: &str, offset: impl Into<Option<usize>>) -> Error {
+    Error { kind, path: path.to_string(), offset: offset.into() }
+}
+
+pub(super) fn unknown_layout(what: impl ToString, path: &str) -> Error {
+    error(ErrorKind::UnknownLayout { what: what.to_string() }, path, None)
+}
+
+impl<'a, L: DeclLookup + ?Sized> Decoder<'a, L> {
+    pub(super) fn new(lookup: &'a L, bytes: &'a [u8], handles: &'a [HandleInfo]) -> Self {
+        Decoder {
+            lookup,
+            bytes,
+            handles,
+            next_out_of_line: 0,
+            next_handle: 0,
+            depth: 0,
+            regions: Vec::new(),
+        }
+    }
+
+    fn label(&mut self, offset: usize, len: usize, path: &str, kind: RegionKind) {
+        if len > 0 {
+            self.regions.push(Region { offset, len, path: path.to_string(), kind });
+        }
+    }
+
+    fn read(&self, offset: usize, len: usize, path: &str) -> Result<&'a [u8]> {
+        let bytes: &'a [u8] = self.bytes;
+        offset
+            .checked_add(len)
+            .and_then(|end| bytes.get(offset..end))
+            .ok_or_else(|| error(ErrorKind::OutOfBounds, path, offset))
+    }
+
+    fn read_u32(&self, offset: usize, path: &str) -> Result<u32> {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(self.read(offset, 4, path)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn read_u64(&self, offset: usize, path: &str) -> Result<u64> {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(self.read(offset, 8, path)?);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    /// Checks that `len` bytes at `offset` are zero and labels them as padding.
+    fn padding(&mut self, offset: usize, len: usize, path: &str) -> Result<()> {
+        if self.read(offset, len, path)?.iter().any(|byte| *byte != 0) {
+            return Err(error(ErrorKind::NonZeroPadding, path, offset));
+        }
+        self.label(offset, len, path, RegionKind::Padding);
+        Ok(())
+    }
+
+    /// Reserves the next out-of-line object of `size` bytes, returning its offset.
+    fn claim(&mut self, size: usize, path: &str) -> Result<usize> {
+        let offset = self.next_out_of_line;
+        let (end, aligned) = offset
+            .checked_add(size)
+            .and_then(|end| {
+                Some((end, end.checked_next_multiple_of(OUT_OF_LINE_ALIGNMENT as usize)?))
+            })
+            .ok_or_else(|| error(ErrorKind::OutOfBounds, path, offset))?;
+        self.read(offset, aligned - offset, path)?;
+        self.next_out_of_line = aligned;
+        self.padding(end, aligned - end, path)?;
+        Ok(offset)
+    }
+
+    /// Returns the size of `count` out-of-line elements of `element_size` bytes, checking that
+    /// they fit in the rest of the message before anything is allocated for them.
+    fn out_of_line_size(&self, element_size: usize, count: u64, path: &str) -> Result<usize> {
+        let offset = self.next_out_of_line;
+        usize::try_from(count)
+            .ok()
+            .and_then(|count| count.checked_mul(element_size))
+            .filter(|size| *size <= self.bytes.len().saturating_sub(offset))
+            .ok_or_else(|| error(ErrorKind::OutOfBounds, path, offset))
+    }
+
+    fn enter(&mut self, path: &str) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(error(ErrorKind::DepthExceeded, path, None));
+        }
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Reads an 8-byte presence marker.
+    fn presence(&mut self, offset: usize, path: &str) -> Result<bool> {
+        let present = match self.read_u64(offset, path)? {
+            ALLOC_PRESENT => true,
+            ALLOC_ABSENT => false,
+            _ => return Err(error(ErrorKind::InvalidPresence, path, offset)),
+        };
+        self.label(offset, 8, path, RegionKind::Presence);
+        Ok(present)
+    }
+
+    /// Checks that the whole message was used.
+    pub(super) fn finish(&self) -> Result<()> {
+        if self.next_out_of_line < self.bytes.len() {
+            let count = self.bytes.len() - self.next_out_of_line;
+            return Err(error(ErrorKind::ExtraBytes { count }, "", self.next_out_of_line));
+        }
+        if self.next_handle < self.handles.len() {
+            let count = self.handles.len() - self.next_handle;
+            return Err(error(ErrorKind::ExtraHandles { count }, "", None));
+        }
+        Ok(())
+    }
+
+    fn primitive(
+        &mut self,
+        subtype: &PrimitiveSubtype,
+        offset: usize,
+        path: &str,
+    ) -> Result<Value> {
+        let size = subtype.size() as usize;
+        let bytes = self.read(offset, size, path)?;
+        let mut buf = [0; 8];
+        buf[..size].copy_from_slice(bytes);
+        let raw = u64::from_le_bytes(buf);
+        let value = match subtype {
+            PrimitiveSubtype::Bool => match raw {
+                0 => Value::Bool(false),
+                1 => Value::Bool(true),
+                _ => return Err(error(ErrorKind::InvalidBool { value: raw as u8 }, path, offset)),
+            },
+            PrimitiveSubtype::Int8 => (raw as i8).into(),
+            PrimitiveSubtype::Int16 => (raw as i16).into(),
+            PrimitiveSubtype::Int32 => (raw as i32).into(),
+            PrimitiveSubtype::Int64 => (raw as i64).into(),
+            PrimitiveSubtype::UInt8 => (raw as u8).into(),
+            PrimitiveSubtype::UInt16 => (raw as u16).into(),
+            PrimitiveSubtype::UInt32 => (raw as u32).into(),
+            PrimitiveSubtype::UInt64 => raw.into(),
+            PrimitiveSubtype::Float32 => f64::from(f32::from_bits(raw as u32)).into(),
+            PrimitiveSubtype::Float64 => f64::from_bits(raw).into(),
+        };
+        self.label(offset, size, path, RegionKind::Value);
+        Ok(value)
+    }
+
+    fn handle(
+        &mut self,
+        subtype: HandleSubtype,
+        rights: Option<HandleRights>,
+        nullable: bool,
+        offset: usize,
+        path: &str,
+    ) -> Result<Value> {
+        let presence = self.read_u32(offset, path)?;
+        self.label(offset, 4, path, RegionKind::Presence);
+        match presence {
+            HANDLE_ABSENT if nullable => return Ok(Value::Null),
+            HANDLE_ABSENT => return Err(error(ErrorKind::UnexpectedNull, path, offset)),
+            HANDLE_PRESENT => {}
+            _ => return Err(error(ErrorKind::InvalidPresence, path, offset)),
+        }
+        let info = *self
+            .handles
+            .get(self.next_handle)
+            .ok_or_else(|| error(ErrorKind::MissingHandle, path, offset))?;
+        self.next_handle += 1;
+        if info.obj_type != 0 && !subtype.accepts_obj_type(info.obj_type) {
+            let kind = ErrorKind::WrongHandleType { expected: subtype, actual: info.obj_type };
+            return Err(error(kind, path, offset));
+        }
+        // Handles recorded with `SAME_RIGHTS` have unknown rights, so they can't be checked.
+        if let Some(rights) = rights.filter(|_| !info.rights.contains(HandleRights::SAME_RIGHTS)) {
+            let missing = rights.missing_from(info.rights);
+            if !missing.is_empty() {
+                return Err(error(ErrorKind::MissingRights { missing }, path, offset));
+            }
+        }
+        Ok(info.handle.into())
+    }
+
+    /// Reads the count and presence of a vector or string, checking the count against `bound`.
+    fn vector_header(
+        &mut self,
+        bound: Option<u64>,
+        nullable: bool,
+        offset: usize,
+        path: &str,
+    ) -> Result<Option<u64>> {
+        let count = self.read_u64(offset, path)?;
+        self.label(offset, 8, path, RegionKind::Count);
+        if !self.presence(offset + 8, path)? {
+            if !nullable {
+                return Err(error(ErrorKind::UnexpectedNull, path, offset));
+            }
+            if count != 0 {
+                return Err(error(ErrorKind::InvalidPresence, path, offset));
+            }
+            return Ok(None);
+        }
+        if let Some(bound) = bound {
+            if count > bound {
+                return Err(error(ErrorKind::ExceedsBound { count, bound }, path, offset));
+            }
+        }
+        Ok(Some(count))
+    }
+
+    /// Decodes `count` consecutive elements of `element_type` at `offset`.
+    fn elements(
+        &mut self,
+        element_type: &Type,
+        count: u64,
+        offset: usize,
+        path: &str,
+    ) -> Result<Value> {
+        let shape = type_shape(self.lookup, element_type)
+            .ok_or_else(|| unknown_layout("element type", path))?;
+        let size = shape.inline_size as usize;
+        // Check that the elements are in bounds before allocating room for them.
+        let len = usize::try_from(count)
+            .ok()
+            .and_then(|count| count.checked_mul(size))
+            .ok_or_else(|| error(ErrorKind::OutOfBounds, path, offset))?;
+        self.read(offset, len, path)?;
+        let count = count as usize;
+        if let TypeKind::Primitive { subtype } = &*element_type.kind {
+            // Label runs of primitives as a whole rather than element by element.
+            let regions = self.regions.len();
+            let mut items = Vec::with_capacity(count);
+            for i in 0..count {
+                items.push(self.primitive(subtype, offset + i * size, path)?);
+            }
+            self.regions.truncate(regions);
+            let label = format!("{}[0..{}]", path, count);
+            self.label(offset, len, &label, RegionKind::Value);
+            return Ok(Value::Array(items));
+        }
+        let mut items = Vec::with_capacity(count);
+        for i in 0..count {
+            let element_path = format!("{}[{}]", path, i);
+            items.push(self.inline(element_type, offset + i * size, &element_path)?);
+        }
+        Ok(Value::Array(items))
+    }
+
+    /// Decodes a value of `r#type` stored inline at `offset`, along with its out-of-line parts.
+    pub(super) fn inline(&mut self, r#type: &Type, offset: usize, path: &str) -> Result<Value> {
+        let nullable = *r#type.nullable;
+        match &*r#type.kind {
+            TypeKind::Primitive { subtype } => self.primitive(subtype, offset, path),
+            TypeKind::Handle { subtype, rights } => {
+                self.handle(*subtype, *rights, nullable, offset, path)
+            }
+            TypeKind::Request { .. } => {
+                self.handle(HandleSubtype::Channel, None, nullable, offset, path)
+            }
+            TypeKind::Array { element_type, element_count, .. } => {
+                let count = element_count
+                    .as_ref()
+                    .and_then(|count| count.as_u64())
+                    .ok_or_else(|| unknown_layout("array size", path))?;
+                self.elements(element_type, count, offset, path)
+            }
+            TypeKind::String { maybe_element_count, .. } => {
+                let bound = maybe_element_count.as_ref().and_then(|count| count.as_u64());
+                let count = match self.vector_header(bound, nullable, offset, path)? {
+                    Some(count) => self.out_of_line_size(1, count, path)?,
+                    None => return Ok(Value::Null),
+                };
+                let body = self.claim(count, path)?;
+                let bytes = self.read(body, count, path)?;
+                let string = std::str::from_utf8(bytes)
+                    .map_err(|_| error(ErrorKind::InvalidUtf8, path, body))?;
+                self.label(body, count, path, RegionKind::Value);
+                Ok(string.into())
+            }
+            TypeKind::Vector { element_type, maybe_element_count, .. } => {
+                let bound = maybe_element_count.as_ref().and_then(|count| count.as_u64());
+                let count = match self.vector_header(bound, nullable, offset, path)? {
+                    Some(count) => count,
+                    None => return Ok(Value::Null),
+                };
+                let shape = type_shape(self.lookup, element_type)
+                    .ok_or_else(|| unknown_layout("element type", path))?;
+                self.enter(path)?;
+                let size = self.out_of_line_size(shape.inline_size as usize, count, path)?;
+                let body = self.claim(size, path)?;
+                let items = self.elements(element_type, count, body, path)?;
+                self.leave();
+                Ok(items)
+            }
+            TypeKind::Identifier { identifier, .. } => {
+                self.identifier(identifier, nullable, offset, path)
+            }
+            TypeKind::UnresolvedRequest { .. } | TypeKind::UnresolvedIdentifier { .. } => {
+                Err(unknown_layout("unresolved type", path))
+            }
+        }
+    }
+
+    fn identifier(
+        &mut self,
+        identifier: &DeclPath,
+        nullable: bool,
+        offset: usize,
+        path: &str,
+    ) -> Result<Value> {
+        let decl =
+            self.lookup.lookup_decl(identifier).ok_or_else(|| unknown_layout(identifier, path))?;
+        match decl {
+            Decl::Const(_) => Err(unknown_layout(identifier, path)),
+            Decl::Protocol(_) => self.handle(HandleSubtype::Channel, None, nullable, offset, path),
+            Decl::Enum(decl) => {
+                let subtype =
+                    underlying_primitive(Decl::Enum(decl)).unwrap_or(PrimitiveSubtype::UInt32);
+                let raw = self.primitive(&subtype, offset, path)?;
+                let value = number_to_i128(&raw);
+                let member = decl.members.iter().find(|member| {
+                    member.value.as_ref().and_then(|value| value.integer_value()) == value
+                });
+                match (member, value) {
+                    (Some(member), _) => Ok(member.name.inner.clone().into()),
+                    (None, Some(value)) => {
+                        Err(error(ErrorKind::InvalidEnumValue { value }, path, offset))
+                    }
+                    (None, None) => Ok(raw),
+                }
+            }
+            Decl::Bits(decl) => {
+                let subtype =
+                    underlying_primitive(Decl::Bits(decl)).unwrap_or(PrimitiveSubtype::UInt32);
+                let raw = self.primitive(&subtype, offset, path)?;
+                let value = number_to_i128(&raw).unwrap_or(0) as u64;
+                let mask = decl
+                    .members
+                    .iter()
+                    .filter_map(|member| member.value.as_ref()?.integer_value())
+                    .fold(0u64, |mask, bit| mask | bit as u64);
+                if value & !mask != 0 {
+                    return Err(error(ErrorKind::InvalidBitsValue { value }, path, offset));
+                }
+                Ok(raw)
+            }
+            Decl::Struct(decl) => {
+                if !nullable {
+                    return self.structure(decl, offset, path);
+                }
+                if !self.presence(offset, path)? {
+                    return Ok(Value::Null);
+                }
+                let size = decl_shape(self.lookup, identifier, false)
+                    .ok_or_else(|| unknown_layout(identifier, path))?
+                    .inline_size;
+                self.enter(path)?;
+                let body = self.claim(size as usize, path)?;
+                let value = self.structure(decl, body, path)?;
+                self.leave();
+                Ok(value)
+            }
+            Decl::Union(decl) => {
+                let size = decl_shape(self.lookup, identifier, false)
+                    .ok_or_else(|| unknown_layout(identifier, path))?;
+                if !nullable {
+                    return self.union(decl, size.inline_size, size.alignment, offset, path);
+                }
+                if !self.presence(offset, path)? {
+                    return Ok(Value::Null);
+                }
+                self.enter(path)?;
+                let body = self.claim(size.inline_size as usize, path)?;
+                let value = self.union(decl, size.inline_size, size.alignment, body, path)?;
+                self.leave();
+                Ok(value)
+            }
+            Decl::Table(decl) => self.table(decl, offset, path),
+            Decl::XUnion(decl) => self.xunion(decl, nullable, offset, path),
+        }
+    }
+
+    /// Decodes members laid out at `offset` like a struct, labeling the padding between them. Padding
+    /// is attributed to the member it follows, or to the struct itself if no member precedes it.
+    pub(super) fn members<'b>(
+        &mut self,
+        members: impl Iterator<Item = (&'b Spanned<String>, Option<u32>, &'b Spanned<Type>)>,
+        offset: usize,
+        start: usize,
+        size: usize,
+        path: &str,
+    ) -> Result<Value> {
+        let mut object = Map::new();
+        let mut end = start;
+        let mut previous: Option<String> = None;
+        for (name, member_offset, r#type) in members {
+            let shape = type_shape(self.lookup, r#type)
+                .ok_or_else(|| unknown_layout("member type", path))?;
+            let member_offset = match member_offset {
+                Some(member_offset) => member_offset as usize,
+                None => align_to(end as u32, shape.alignment) as usize,
+            };
+            let member_path = join(path, name);
+            if member_offset > end {
+                self.padding(
+                    offset + end,
+                    member_offset - end,
+                    previous.as_deref().unwrap_or(path),
+                )?;
+            }
+            let value = self.inline(r#type, offset + member_offset, &member_path)?;
+            object.insert(name.inner.clone(), value);
+            end = member_offset + shape.inline_size as usize;
+            previous = Some(member_path);
+        }
+        if size > end {
+            self.padding(offset + end, size - end, path)?;
+        }
+        Ok(Value::Object(object))
+    }
+
+    fn structure(&mut self, decl: &Struct, offset: usize, path: &str) -> Result<Value> {
+        let size = decl_shape(self.lookup, &decl.name, false)
+            .ok_or_else(|| unknown_layout(&*decl.name, path))?
+            .inline_size as usize;
+        let members =
+            decl.members.iter().map(|member| (&member.name, *member.offset, &member.r#type));
+        self.members(members, offset, 0, size, path)
+    }
+
+    fn union(
+        &mut self,
+        decl: &Union,
+        size: u32,
+        alignment: u32,
+        offset: usize,
+        path: &str,
+    ) -> Result<Value> {
+        let tag = self.read_u32(offset, path)?;
+        self.label(offset, 4, path, RegionKind::Tag);
+        let member = decl
+            .members
+            .get(tag as usize)
+            .ok_or_else(|| error(ErrorKind::InvalidUnionTag { tag }, path, offset))?;
+        let data_offset = member.offset.unwrap_or_else(|| union_data_offset(alignment)) as usize;
+        let member_path = join(path, &member.name);
+        let shape = type_shape(self.lookup, &member.r#type)
+            .ok_or_else(|| unknown_layout("member type", path))?;
+        self.padding(offset + 4, data_offset - 4, path)?;
+        let value = self.inline(&member.r#type, offset + data_offset, &member_path)?;
+        let end = data_offset + shape.inline_size as usize;
+        self.padding(offset + end, size as usize - end, path)?;
+        let mut object = Map::new();
+        object.insert(member.name.inner.clone(), value);
+        Ok(Value::Object(object))
+    }
+
+    /// Decodes the envelope at `offset`, reading its contents as `r#type` if it is known and checking
+    /// that its byte and handle counts match.
+    fn envelope(
+        &mut self,
+        offset: usize,
+        path: &str,
+        r#type: Option<&Type>,
+    ) -> Result<Option<Value>> {
+        let num_bytes = self.read_u32(offset, path)? as usize;
+        let num_handles = self.read_u32(offset + 4, path)? as usize;
+        self.label(offset, 8, path, RegionKind::Envelope);
+        if !self.presence(offset + 8, path)? {
+            if num_bytes != 0 || num_handles != 0 {
+                return Err(error(ErrorKind::EnvelopeMismatch, path, offset));
+            }
+            return Ok(None);
+        }
+        self.enter(path)?;
+        let start = self.next_out_of_line;
+        let first_handle = self.next_handle;
+        let value = match r#type {
+            Some(r#type) => {
+                let shape = type_shape(self.lookup, r#type)
+                    .ok_or_else(|| unknown_layout("member type", path))?;
+                let body = self.claim(shape.inline_size as usize, path)?;
+                self.inline(r#type, body, path)?
+            }
+            None => {
+                let body = self.claim(num_bytes, path)?;
+                self.label(body, num_bytes, path, RegionKind::Unknown);
+                self.next_handle += num_handles;
+                if self.next_handle > self.handles.len() {
+                    return Err(error(ErrorKind::MissingHandle, path, offset));
+                }
+                Value::Null
+            }
+        };
+        self.leave();
+        if self.next_out_of_line - start != num_bytes
+            || self.next_handle - first_handle != num_handles
+        {
+            return Err(error(ErrorKind::EnvelopeMismatch, path, offset));
+        }
+        Ok(Some(value))
+    }
+
+    fn table(&mut self, decl: &Table, offset: usize, path: &str) -> Result<Value> {
+        let count = match self.vector_header(None, false, offset, path)? {
+            Some(count) => count,
+            None => return Ok(Value::Null),
+        };
+        let size = self.out_of_line_size(ENVELOPE_SIZE as usize, count, path)?;
+        let count = count as usize;
+        self.enter(path)?;
+        let body = self.claim(size, path)?;
+        let fields = table_fields(decl);
+        let mut object = Map::new();
+        for i in 0..count {
+            let ordinal = i as u64 + 1;
+            let field = fields.iter().find(|(field_ordinal, _, _)| *field_ordinal == ordinal);
+            let field_path = match field {
+                Some((_, name, _)) => join(path, name),
+                None => join(path, &format!("<{}>", ordinal)),
+            };
+            let envelope = body + i * ENVELOPE_SIZE as usize;
+            let value =
+                self.envelope(envelope, &field_path, field.map(|(_, _, r#type)| &***r#type))?;
+            if let (Some((_, name, _)), Some(value)) = (field, value) {
+                object.insert(name.inner.clone(), value);
+            }
+        }
+        self.leave();
+        Ok(Value::Object(object))
+    }
+
+    fn xunion(
+        &mut self,
+        decl: &XUnion,
+        nullable: bool,
+        offset: usize,
+        path: &str,
+    ) -> Result<Value> {
+        let ordinal = self.read_u32(offset, path)?;
+        self.label(offset, 4, path, RegionKind::Tag);
+        self.padding(offset + 4, 4, path)?;
+        if ordinal == 0 {
+            if !nullable {
+                return Err(error(ErrorKind::UnexpectedNull, path, offset));
+            }
+            return match self.envelope(offset + 8, path, None)? {
+                None => Ok(Value::Null),
+                Some(_) => Err(error(ErrorKind::InvalidPresence, path, offset)),
+            };
+        }
+        let member = decl.members.iter().find(|member| *member.ordinal == Some(u64::from(ordinal)));
+        let member_path = match member {
+            Some(member) => join(path, &member.name),
+            None => join(path, &format!("<{}>", ordinal)),
+        };
+        let value = self
+            .envelope(offset + 8, &member_path, member.map(|member| &*member.r#type))?
+            .ok_or_else(|| error(ErrorKind::UnexpectedNull, &member_path, offset))?;
+        let mut object = Map::new();
+        match member {
+            Some(member) => object.insert(member.name.inner.clone(), value),
+            None => object.insert("$unknown_ordinal".to_string(), ordinal.into()),
+        };
+        Ok(Value::Object(object))
+    }
+
+    /// Decodes a message header and parameters.
+    pub(super) fn message(&mut self, message: &MethodReqRes) -> Result<Value> {
+        self.read(0, HEADER_SIZE as usize, "header")?;
+        self.label(0, 4, "header.txid", RegionKind::Header);
+        self.label(4, 3, "header.flags", RegionKind::Header);
+        self.label(7, 1, "header.magic", RegionKind::Header);
+        self.label(8, 8, "header.ordinal", RegionKind::Header);
+        let size = message_size(self.lookup, message)? as usize;
+        self.read(0, size, "")?;
+        self.next_out_of_line = size;
+        let params =
+            message.parameters.iter().map(|param| (&param.name, *param.offset, &param.r#type));
+        self.members(params, 0, HEADER_SIZE as usize, size, "")
+    }
+
+    /// Decodes a standalone value of `r#type` starting at offset 0.
+    pub(super) fn top_level(&mut self, r#type: &Type) -> Result<Value> {
+        let shape = type_shape(self.lookup, r#type).ok_or_else(|| unknown_layout("type", ""))?;
+        let offset = self.claim(shape.inline_size as usize, "")?;
+        self.inline(r#type, offset, "")
+    }
+}
+
+/// Returns the inline size of a message, including its header.
+pub(super) fn message_size<L: DeclLookup + ?Sized>(
+    lookup: &L,
+    message: &MethodReqRes,
+) -> Result<u32> {
+    if let Some(size) = *message.size {
+        return Ok(size);
+    }
+    let mut end = HEADER_SIZE;
+    for param in &message.parameters {
+        let shape = type_shape(lookup, &param.r#type)
+            .ok_or_else(|| unknown_layout("parameter type", &param.name))?;
+        end = align_to(end, shape.alignment).saturating_add(shape.inline_size);
+    }
+    Ok(align_to(end, OUT_OF_LINE_ALIGNMENT))
+}
+
+pub(super) fn number_to_i128(value: &Value) -> Option<i128> {
+    match value {
+        Value::Number(number) => {
+            number.as_i64().map(i128::from).or_else(|| number.as_u64().map(i128::from))
+        }
+        _ => None,
+    }
+}
+
+/// Decodes a request or response message, including its header.
+pub fn decode_message<L: DeclLookup + ?Sized>(
+    lookup: &L,
+    message: &MethodReqRes,
+    bytes: &[u8],
+    handles: &[HandleInfo],
+) -> std::result::Result<Decoded, Error> {
+    let mut decoder = Decoder::new(lookup, bytes, handles);
+    let value = decoder.message(message)?;
+    decoder.finish()?;
+    Ok(Decoded { value, regions: decoder.regions })
+}
+
+/// Decodes a standalone value of `r#type`, such as a struct or table, from the start of `bytes`.
+pub fn decode_value<L: DeclLookup + ?Sized>(
+    lookup: &L,
+    r#type: &Type,
+    bytes: &[u8],
+    handles: &[HandleInfo],
+) -> std::result::Result<Decoded, Error> {
+    let mut decoder = Decoder::new(lookup, bytes, handles);
+    let value = decoder.top_level(r#type)?;
+    decoder.finish()?;
+    Ok(Decoded { value, regions: decoder.regions })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{fixtures, wire::ErrorKind},
+        serde_json::json,
+    };
+
+    fn decode(r#type: Value, bytes: &[u8]) -> std::result::Result<Value, ErrorKind> {
+        let library = fixtures::library("example");
+        let r#type = fixtures::r#type(r#type);
+        decode_value(&library, &r#type, bytes, &[])
+            .map(|decoded| decoded.value)
+            .map_err(|err| err.kind)
+    }
+
+    /// Returns a vector or string header with `count` elements, marked present.
+    fn header(count: u64) -> Vec<u8> {
+        let mut bytes = count.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&ALLOC_PRESENT.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn decodes_struct() {
+        let bytes = [7, 0, 0, 0, 9, 0, 0, 0];
+        let value =
+            decode(json!({"kind": "identifier", "identifier": "test.example/Point"}), &bytes);
+        assert_eq!(value, Ok(json!({"x": 7, "y": 9})));
+    }
+
+    #[test]
+    fn decodes_string() {
+        let mut bytes = header(2);
+        bytes.extend_from_slice(b"hi\0\0\0\0\0\0");
+        assert_eq!(decode(json!({"kind": "string"}), &bytes), Ok(json!("hi")));
+    }
+
+    #[test]
+    fn decodes_table_skipping_reserved_and_absent_fields() {
+        let mut bytes = header(1);
+        // The envelope of `enabled`, holding a single byte padded to 8.
+        bytes.extend_from_slice(&8u32.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&ALLOC_PRESENT.to_le_bytes());
+        bytes.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        let value =
+            decode(json!({"kind": "identifier", "identifier": "test.example/Settings"}), &bytes);
+        assert_eq!(value, Ok(json!({"enabled": true})));
+    }
+
+    #[test]
+    fn rejects_nonzero_padding() {
+        let bytes = [7, 1, 0, 0, 9, 0, 0, 0];
+        let value =
+            decode(json!({"kind": "identifier", "identifier": "test.example/Point"}), &bytes);
+        assert_eq!(value, Err(ErrorKind::NonZeroPadding));
+    }
+
+    #[test]
+    fn rejects_string_count_beyond_message() {
+        let value = decode(json!({"kind": "string"}), &header(1 << 32));
+        assert_eq!(value, Err(ErrorKind::OutOfBounds));
+    }
+
+    #[test]
+    fn rejects_string_count_that_overflows_alignment() {
+        let value = decode(json!({"kind": "string"}), &header(u64::MAX - 3));
+        assert_eq!(value, Err(ErrorKind::OutOfBounds));
+    }
+
+    #[test]
+    fn rejects_vector_count_whose_size_overflows() {
+        let r#type = json!({
+            "kind": "vector",
+            "element_type": {"kind": "primitive", "subtype": "uint64"},
+        });
+        assert_eq!(decode(r#type, &header(1 << 61)), Err(ErrorKind::OutOfBounds));
+    }
+
+    #[test]
+    fn rejects_vector_count_beyond_message() {
+        let r#type = json!({
+            "kind": "vector",
+            "element_type": {"kind": "identifier", "identifier": "test.example/Point"},
+        });
+        let mut bytes = header(1 << 40);
+        bytes.extend_from_slice(&[0; 8]);
+        assert_eq!(decode(r#type, &bytes), Err(ErrorKind::OutOfBounds));
+    }
+
+    #[test]
+    fn rejects_table_count_whose_size_overflows() {
+        let r#type = json!({"kind": "identifier", "identifier": "test.example/Settings"});
+        assert_eq!(decode(r#type, &header(1 << 62)), Err(ErrorKind::OutOfBounds));
+    }
+}
@@ -0,0 +1,230 @@
+use {
+    super::{decode_message, Decoded, Error, ErrorKind, HandleInfo, TransactionHeader},
+    crate::{
+        visit::{DeclLookup, Direction},
+        Library, Method, MethodReqRes, Protocol,
+    },
+    std::{collections::HashMap, fmt},
+};
+
+/// A method along with the protocol and library that declare it.
+#[derive(Debug, Clone, Copy)]
+pub struct MethodRef<'a> {
+    pub library: &'a Library,
+    pub protocol: &'a Protocol,
+    pub method: &'a Method,
+}
+
+/// An index from method ordinals to the methods they identify across a set of libraries.
+///
+/// Both the `ordinal` and the `generated_ordinal` of every method are indexed, so messages sent
+/// using either are found. When two methods share an ordinal, as happens when a protocol composes
+/// another, the first one declared wins, and primary ordinals take precedence over generated ones.
+#[derive(Debug, Clone)]
+pub struct OrdinalIndex<'a> {
+    methods: HashMap<u64, MethodRef<'a>>,
+}
+
+impl<'a> OrdinalIndex<'a> {
+    pub fn new(libraries: &'a [Library]) -> Self {
+        let mut methods = HashMap::new();
+        let all = || {
+            libraries.iter().flat_map(|library| {
+                library.protocols.iter().flat_map(move |protocol| {
+                    protocol.methods.iter().map(move |method| MethodRef {
+                        library,
+                        protocol,
+                        method,
+                    })
+                })
+            })
+        };
+        for method in all() {
+            if let Some(ordinal) = *method.method.ordinal {
+                methods.entry(ordinal).or_insert(method);
+            }
+        }
+        for method in all() {
+            if let Some(ordinal) = *method.method.generated_ordinal {
+                methods.entry(ordinal).or_insert(method);
+            }
+        }
+        OrdinalIndex { methods }
+    }
+
+    pub fn get(&self, ordinal: u64) -> Option<MethodRef<'a>> {
+        self.methods.get(&ordinal).copied()
+    }
+}
+
+/// What a message is with respect to its method.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum MessageKind {
+    Request,
+    Response,
+    Event,
+}
+
+impl MessageKind {
+    pub fn direction(self) -> Direction {
+        match self {
+            MessageKind::Request => Direction::Request,
+            MessageKind::Response | MessageKind::Event => Direction::Response,
+        }
+    }
+}
+
+impl fmt::Display for MessageKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            MessageKind::Request => "request",
+            MessageKind::Response => "response",
+            MessageKind::Event => "event",
+        })
+    }
+}
+
+/// A message that has been matched to its method and decoded.
+#[derive(Debug, Clone)]
+pub struct Identified<'a> {
+    pub header: TransactionHeader,
+    pub method: MethodRef<'a>,
+    pub kind: MessageKind,
+    pub decoded: Decoded,
+}
+
+fn message_kinds(method: &Method) -> Vec<(MessageKind, &MethodReqRes)> {
+    let request = method.request.as_ref().map(|request| (MessageKind::Request, &**request));
+    let response = method.response.as_ref().map(|response| {
+        let kind = if method.is_event() { MessageKind::Event } else { MessageKind::Response };
+        (kind, &**response)
+    });
+    request.into_iter().chain(response).collect()
+}
+
+/// Identifies a message by the ordinal in its header and decodes it as the matching request,
+/// response, or event.
+///
+/// Requests and responses of two-way methods can't be told apart by their headers alone. If
+/// `direction` is known it is used; otherwise the message is decoded as a request, then as a
+/// response if that fails.
+pub fn identify<'a, L: DeclLookup + ?Sized>(
+    lookup: &L,
+    index: &OrdinalIndex<'a>,
+    bytes: &[u8],
+    handles: &[HandleInfo],
+    direction: Option<Direction>,
+) -> Result<Identified<'a>, Error> {
+    let header = TransactionHeader::decode(bytes)?;
+    let method = index.get(header.ordinal).ok_or_else(|| Error {
+        kind: ErrorKind::UnknownOrdinal { ordinal: header.ordinal },
+        path: "header.ordinal".to_string(),
+        offset: Some(8),
+    })?;
+    let candidates: Vec<_> = message_kinds(method.method)
+        .into_iter()
+        .filter(|(kind, _)| direction.map_or(true, |direction| kind.direction() == direction))
+        .collect();
+    let mut last_error = None;
+    for (kind, message) in candidates {
+        match decode_message(lookup, message, bytes, handles) {
+            Ok(decoded) => return Ok(Identified { header, method, kind, decoded }),
+            Err(err) => last_error = Some(err),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| Error {
+        kind: ErrorKind::InvalidValue {
+            reason: format!("method `{}` has no such message", *method.method.name),
+        },
+        path: String::new(),
+        offset: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{fixtures, wire::encode_message},
+        serde_json::{json, Value},
+    };
+
+    fn encode(
+        library: &Library,
+        txid: u32,
+        ordinal: u64,
+        kind: MessageKind,
+        value: Value,
+    ) -> Vec<u8> {
+        let method = OrdinalIndex::new(std::slice::from_ref(library)).get(ordinal).unwrap().method;
+        let (_, message) =
+            message_kinds(method).into_iter().find(|(candidate, _)| *candidate == kind).unwrap();
+        let header = TransactionHeader::new(txid, ordinal);
+        encode_message(library, &header, message, &value).unwrap().bytes
+    }
+
+    #[test]
+    fn indexes_ordinals_and_generated_ordinals() {
+        let libraries = [fixtures::library("example")];
+        let index = OrdinalIndex::new(&libraries);
+        assert_eq!(*index.get(1234).unwrap().method.name, "Echo");
+        assert_eq!(*index.get(5678).unwrap().method.name, "Echo");
+        assert_eq!(*index.get(99).unwrap().method.name, "OnEvent");
+        assert!(index.get(1).is_none());
+    }
+
+    #[test]
+    fn prefers_ordinals_over_generated_ordinals() {
+        let mut library = fixtures::library("example");
+        // Give `OnEvent` the generated ordinal of `Echo` as its primary one.
+        *library.protocols[0].methods[1].ordinal = Some(5678);
+        let libraries = [library];
+        let index = OrdinalIndex::new(&libraries);
+        assert_eq!(*index.get(5678).unwrap().method.name, "OnEvent");
+        assert_eq!(*index.get(1234).unwrap().method.name, "Echo");
+    }
+
+    #[test]
+    fn identifies_requests_responses_and_events() {
+        let libraries = [fixtures::library("example")];
+        let library = &libraries[0];
+        let index = OrdinalIndex::new(&libraries);
+
+        let request = encode(
+            library,
+            1,
+            1234,
+            MessageKind::Request,
+            json!({"value": "hi", "p": {"x": 1, "y": 2}}),
+        );
+        let identified = identify(library, &index, &request, &[], None).unwrap();
+        assert_eq!((identified.kind, identified.header.txid), (MessageKind::Request, 1));
+        assert_eq!(identified.decoded.value, json!({"value": "hi", "p": {"x": 1, "y": 2}}));
+
+        let response = encode(library, 1, 1234, MessageKind::Response, json!({"response": "hi"}));
+        let identified =
+            identify(library, &index, &response, &[], Some(Direction::Response)).unwrap();
+        assert_eq!(identified.kind, MessageKind::Response);
+        assert_eq!(identified.decoded.value, json!({"response": "hi"}));
+        // Without a direction, the response is first tried as a request.
+        assert_eq!(
+            identify(library, &index, &response, &[], None).unwrap().kind,
+            MessageKind::Response
+        );
+
+        let event = encode(library, 0, 99, MessageKind::Event, json!({"vmo": 3}));
+        let handles = [HandleInfo::unknown(3)];
+        let identified = identify(library, &index, &event, &handles, None).unwrap();
+        assert_eq!(identified.kind, MessageKind::Event);
+        assert_eq!(*identified.method.method.name, "OnEvent");
+    }
+
+    #[test]
+    fn reports_unknown_ordinals() {
+        let libraries = [fixtures::library("example")];
+        let index = OrdinalIndex::new(&libraries);
+        let bytes = TransactionHeader::new(1, 7).encode();
+        let err = identify(&libraries[0], &index, &bytes, &[], None).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnknownOrdinal { ordinal: 7 });
+    }
+}
@@ -0,0 +1,612 @@
+use {
+    super::{
+        decode::{error, message_size, number_to_i128, unknown_layout},
+        join, Error, ErrorKind, HandleInfo, Message, TransactionHeader, MAX_DEPTH,
+    },
+    crate::{
+        layout::{
+            align_to, decl_shape, table_fields, type_shape, underlying_primitive,
+            union_data_offset, ALLOC_PRESENT, ENVELOPE_SIZE, HANDLE_PRESENT, HEADER_SIZE,
+            OUT_OF_LINE_ALIGNMENT,
+        },
+        visit::{Decl, DeclLookup},
+        Bits, DeclPath, Enum, HandleRights, HandleSubtype, MethodReqRes, PrimitiveSubtype, Spanned,
+        Struct, Table, Type, TypeKind, Union, XUnion,
+    },
+    serde_json::{Map, Value},
+};
+
+type Result<T> = std::result::Result<T, Error>;
+
+fn invalid(reason: impl Into<String>, path: &str) -> Error {
+    error(ErrorKind::InvalidValue { reason: reason.into() }, path, None)
+}
+
+fn as_object<'v>(value: &'v Value, path: &str) -> Result<&'v Map<String, Value>> {
+    value.as_object().ok_or_else(|| invalid(format!("expected an object, got {}", value), path))
+}
+
+/// Returns the single member set in a union or xunion value.
+fn single_member<'v>(value: &'v Value, path: &str) -> Result<(&'v String, &'v Value)> {
+    let object = as_object(value, path)?;
+    let mut members = object.iter();
+    match (members.next(), members.next()) {
+        (Some(member), None) => Ok(member),
+        _ => Err(invalid("expected exactly one member", path)),
+    }
+}
+
+fn integer_range(subtype: &PrimitiveSubtype) -> Option<(i128, i128)> {
+    Some(match subtype {
+        PrimitiveSubtype::Int8 => (i8::MIN.into(), i8::MAX.into()),
+        PrimitiveSubtype::Int16 => (i16::MIN.into(), i16::MAX.into()),
+        PrimitiveSubtype::Int32 => (i32::MIN.into(), i32::MAX.into()),
+        PrimitiveSubtype::Int64 => (i64::MIN.into(), i64::MAX.into()),
+        PrimitiveSubtype::UInt8 => (0, u8::MAX.into()),
+        PrimitiveSubtype::UInt16 => (0, u16::MAX.into()),
+        PrimitiveSubtype::UInt32 => (0, u32::MAX.into()),
+        PrimitiveSubtype::UInt64 => (0, u64::MAX.into()),
+        PrimitiveSubtype::Bool | PrimitiveSubtype::Float32 | PrimitiveSubtype::Float64 => {
+            return None
+        }
+    })
+}
+
+struct Encoder<'a, L: ?Sized> {
+    lookup: &'a L,
+    bytes: Vec<u8>,
+    handles: Vec<HandleInfo>,
+    depth: u32,
+}
+
+impl<'a, L: DeclLookup + ?Sized> Encoder<'a, L> {
+    fn new(lookup: &'a L) -> Self {
+        Encoder { lookup, bytes: Vec::new(), handles: Vec::new(), depth: 0 }
+    }
+
+    fn write(&mut self, offset: usize, data: &[u8]) {
+        self.bytes[offset..offset + data.len()].copy_from_slice(data);
+    }
+
+    /// Reserves a zeroed out-of-line object of `size` bytes, returning its offset.
+    fn alloc(&mut self, size: usize) -> usize {
+        let offset = self.bytes.len();
+        let end = (offset + size).next_multiple_of(OUT_OF_LINE_ALIGNMENT as usize);
+        self.bytes.resize(end, 0);
+        offset
+    }
+
+    fn enter(&mut self, path: &str) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(error(ErrorKind::DepthExceeded, path, None));
+        }
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn shape(&self, r#type: &Type, path: &str) -> Result<(usize, usize)> {
+        let shape = type_shape(self.lookup, r#type).ok_or_else(|| unknown_layout("type", path))?;
+        Ok((shape.inline_size as usize, shape.alignment as usize))
+    }
+
+    fn primitive(
+        &mut self,
+        subtype: &PrimitiveSubtype,
+        value: &Value,
+        offset: usize,
+        path: &str,
+    ) -> Result<()> {
+        let size = subtype.size() as usize;
+        let raw = match (subtype, value) {
+            (PrimitiveSubtype::Bool, Value::Bool(value)) => u64::from(*value),
+            (PrimitiveSubtype::Float32, Value::Number(number)) => {
+                let value = number.as_f64().ok_or_else(|| invalid("expected a number", path))?;
+                u64::from((value as f32).to_bits())
+            }
+            (PrimitiveSubtype::Float64, Value::Number(number)) => {
+                number.as_f64().ok_or_else(|| invalid("expected a number", path))?.to_bits()
+            }
+            (_, Value::Number(_)) => {
+                let (min, max) = integer_range(subtype)
+                    .ok_or_else(|| invalid(format!("expected a {:?}", subtype), path))?;
+                let value =
+                    number_to_i128(value).ok_or_else(|| invalid("expected an integer", path))?;
+                if value < min || value > max {
+                    return Err(invalid(
+                        format!("{} is out of range for {:?}", value, subtype),
+                        path,
+                    ));
+                }
+                value as u64
+            }
+            _ => return Err(invalid(format!("expected a {:?}, got {}", subtype, value), path)),
+        };
+        self.write(offset, &raw.to_le_bytes()[..size]);
+        Ok(())
+    }
+
+    fn handle(
+        &mut self,
+        subtype: HandleSubtype,
+        rights: Option<HandleRights>,
+        nullable: bool,
+        value: &Value,
+        offset: usize,
+        path: &str,
+    ) -> Result<()> {
+        match value {
+            Value::Null if nullable => Ok(()),
+            Value::Null => Err(error(ErrorKind::UnexpectedNull, path, None)),
+            _ => {
+                let handle = value
+                    .as_u64()
+                    .filter(|handle| *handle != 0 && *handle <= u64::from(u32::MAX))
+                    .ok_or_else(|| invalid(format!("expected a handle, got {}", value), path))?;
+                self.write(offset, &HANDLE_PRESENT.to_le_bytes());
+                self.handles.push(HandleInfo {
+                    handle: handle as u32,
+                    obj_type: subtype.obj_type(),
+                    rights: rights.unwrap_or(HandleRights::SAME_RIGHTS),
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes the count and presence of a vector or string after checking `count` against
+    /// `bound`.
+    fn vector_header(
+        &mut self,
+        count: usize,
+        bound: Option<u64>,
+        offset: usize,
+        path: &str,
+    ) -> Result<()> {
+        let count = count as u64;
+        if let Some(bound) = bound {
+            if count > bound {
+                return Err(error(ErrorKind::ExceedsBound { count, bound }, path, None));
+            }
+        }
+        self.write(offset, &count.to_le_bytes());
+        self.write(offset + 8, &ALLOC_PRESENT.to_le_bytes());
+        Ok(())
+    }
+
+    fn elements(
+        &mut self,
+        element_type: &Type,
+        items: &[Value],
+        offset: usize,
+        path: &str,
+    ) -> Result<()> {
+        let (size, _) = self.shape(element_type, path)?;
+        for (i, item) in items.iter().enumerate() {
+            let element_path = format!("{}[{}]", path, i);
+            self.inline(element_type, item, offset + i * size, &element_path)?;
+        }
+        Ok(())
+    }
+
+    /// Encodes `value` as `r#type` at `offset`, appending its out-of-line parts.
+    fn inline(&mut self, r#type: &Type, value: &Value, offset: usize, path: &str) -> Result<()> {
+        let nullable = *r#type.nullable;
+        match &*r#type.kind {
+            TypeKind::Primitive { subtype } => self.primitive(subtype, value, offset, path),
+            TypeKind::Handle { subtype, rights } => {
+                self.handle(*subtype, *rights, nullable, value, offset, path)
+            }
+            TypeKind::Request { .. } => {
+                self.handle(HandleSubtype::Channel, None, nullable, value, offset, path)
+            }
+            TypeKind::Array { element_type, element_count, .. } => {
+                let count = element_count
+                    .as_ref()
+                    .and_then(|count| count.as_u64())
+                    .ok_or_else(|| unknown_layout("array size", path))?;
+                let items = value
+                    .as_array()
+                    .filter(|items| items.len() as u64 == count)
+                    .ok_or_else(|| invalid(format!("expected an array of {}", count), path))?;
+                self.elements(element_type, items, offset, path)
+            }
+            TypeKind::String { maybe_element_count, .. } => {
+                let string = match value {
+                    Value::Null if nullable => return Ok(()),
+                    Value::Null => return Err(error(ErrorKind::UnexpectedNull, path, None)),
+                    Value::String(string) => string,
+                    _ => return Err(invalid(format!("expected a string, got {}", value), path)),
+                };
+                let bound = maybe_element_count.as_ref().and_then(|count| count.as_u64());
+                self.vector_header(string.len(), bound, offset, path)?;
+                let body = self.alloc(string.len());
+                self.write(body, string.as_bytes());
+                Ok(())
+            }
+            TypeKind::Vector { element_type, maybe_element_count, .. } => {
+                let items = match value {
+                    Value::Null if nullable => return Ok(()),
+                    Value::Null => return Err(error(ErrorKind::UnexpectedNull, path, None)),
+                    Value::Array(items) => items,
+                    _ => return Err(invalid(format!("expected an array, got {}", value), path)),
+                };
+                let bound = maybe_element_count.as_ref().and_then(|count| count.as_u64());
+                self.vector_header(items.len(), bound, offset, path)?;
+                let (size, _) = self.shape(element_type, path)?;
+                self.enter(path)?;
+                let body = self.alloc(size * items.len());
+                self.elements(element_type, items, body, path)?;
+                self.leave();
+                Ok(())
+            }
+            TypeKind::Identifier { identifier, .. } => {
+                self.identifier(identifier, nullable, value, offset, path)
+            }
+            TypeKind::UnresolvedRequest { .. } | TypeKind::UnresolvedIdentifier { .. } => {
+                Err(unknown_layout("unresolved type", path))
+            }
+        }
+    }
+
+    fn identifier(
+        &mut self,
+        identifier: &DeclPath,
+        nullable: bool,
+        value: &Value,
+        offset: usize,
+        path: &str,
+    ) -> Result<()> {
+        let decl =
+            self.lookup.lookup_decl(identifier).ok_or_else(|| unknown_layout(identifier, path))?;
+        match decl {
+            Decl::Const(_) => Err(unknown_layout(identifier, path)),
+            Decl::Protocol(_) => {
+                self.handle(HandleSubtype::Channel, None, nullable, value, offset, path)
+            }
+            Decl::Enum(decl) => self.enumeration(decl, value, offset, path),
+            Decl::Bits(decl) => self.bits(decl, value, offset, path),
+            Decl::Struct(decl) => {
+                if !nullable {
+                    return self.structure(decl, value, offset, path);
+                }
+                if value.is_null() {
+                    return Ok(());
+                }
+                let size = decl_shape(self.lookup, identifier, false)
+                    .ok_or_else(|| unknown_layout(identifier, path))?
+                    .inline_size;
+                self.write(offset, &ALLOC_PRESENT.to_le_bytes());
+                self.enter(path)?;
+                let body = self.alloc(size as usize);
+                self.structure(decl, value, body, path)?;
+                self.leave();
+                Ok(())
+            }
+            Decl::Union(decl) => {
+                let shape = decl_shape(self.lookup, identifier, false)
+                    .ok_or_else(|| unknown_layout(identifier, path))?;
+                if !nullable {
+                    return self.union(decl, shape.alignment, value, offset, path);
+                }
+                if value.is_null() {
+                    return Ok(());
+                }
+                self.write(offset, &ALLOC_PRESENT.to_le_bytes());
+                self.enter(path)?;
+                let body = self.alloc(shape.inline_size as usize);
+                self.union(decl, shape.alignment, value, body, path)?;
+                self.leave();
+                Ok(())
+            }
+            Decl::Table(decl) => self.table(decl, value, offset, path),
+            Decl::XUnion(decl) => self.xunion(decl, nullable, value, offset, path),
+        }
+    }
+
+    /// Encodes an enum given either a member name or its numeric value.
+    fn enumeration(&mut self, decl: &Enum, value: &Value, offset: usize, path: &str) -> Result<()> {
+        let subtype = underlying_primitive(Decl::Enum(decl)).unwrap_or(PrimitiveSubtype::UInt32);
+        let member_value = |member: &Spanned<crate::EnumMember>| {
+            member.value.as_ref().and_then(|value| value.integer_value())
+        };
+        let raw = match value {
+            Value::String(name) => decl
+                .members
+                .iter()
+                .find(|member| *member.name == *name)
+                .and_then(member_value)
+                .ok_or_else(|| invalid(format!("unknown enum member `{}`", name), path))?,
+            _ => {
+                let raw = number_to_i128(value)
+                    .ok_or_else(|| invalid(format!("expected an enum, got {}", value), path))?;
+                if !decl.members.iter().any(|member| member_value(member) == Some(raw)) {
+                    return Err(error(ErrorKind::InvalidEnumValue { value: raw }, path, None));
+                }
+                raw
+            }
+        };
+        self.primitive(&subtype, &number_value(raw), offset, path)
+    }
+
+    /// Encodes bits given either their numeric value or an array of member names.
+    fn bits(&mut self, decl: &Bits, value: &Value, offset: usize, path: &str) -> Result<()> {
+        let subtype = underlying_primitive(Decl::Bits(decl)).unwrap_or(PrimitiveSubtype::UInt32);
+        let member_value = |name: &str| {
+            decl.members
+                .iter()
+                .find(|member| *member.name == name)
+                .and_then(|member| member.value.as_ref()?.integer_value())
+        };
+        let raw = match value {
+            Value::Array(names) => names.iter().try_fold(0i128, |raw, name| {
+                name.as_str()
+                    .and_then(member_value)
+                    .map(|bit| raw | bit)
+                    .ok_or_else(|| invalid(format!("unknown bits member {}", name), path))
+            })?,
+            _ => number_to_i128(value)
+                .ok_or_else(|| invalid(format!("expected bits, got {}", value), path))?,
+        };
+        let mask = decl
+            .members
+            .iter()
+            .filter_map(|member| member.value.as_ref()?.integer_value())
+            .fold(0i128, |mask, bit| mask | bit);
+        if raw & !mask != 0 {
+            return Err(error(ErrorKind::InvalidBitsValue { value: raw as u64 }, path, None));
+        }
+        self.primitive(&subtype, &number_value(raw), offset, path)
+    }
+
+    /// Encodes the members of `value` laid out at `offset` like a struct. Nullable members may be
+    /// omitted from `value`.
+    fn members<'b>(
+        &mut self,
+        members: impl Iterator<Item = (&'b Spanned<String>, Option<u32>, &'b Spanned<Type>)>,
+        value: &Value,
+        offset: usize,
+        start: usize,
+        path: &str,
+    ) -> Result<()> {
+        let object = as_object(value, path)?;
+        let mut end = start;
+        let mut known = Vec::new();
+        for (name, member_offset, r#type) in members {
+            let (size, alignment) = self.shape(r#type, path)?;
+            let member_offset = match member_offset {
+                Some(member_offset) => member_offset as usize,
+                None => align_to(end as u32, alignment as u32) as usize,
+            };
+            let member_path = join(path, name);
+            let member_value = match object.get(name.as_str()) {
+                Some(member_value) => member_value,
+                None if *r#type.nullable => &Value::Null,
+                None => return Err(invalid("missing member", &member_path)),
+            };
+            self.inline(r#type, member_value, offset + member_offset, &member_path)?;
+            end = member_offset + size;
+            known.push(name.as_str());
+        }
+        if let Some(unknown) = object.keys().find(|key| !known.contains(&key.as_str())) {
+            return Err(invalid(format!("unknown member `{}`", unknown), path));
+        }
+        Ok(())
+    }
+
+    fn structure(&mut self, decl: &Struct, value: &Value, offset: usize, path: &str) -> Result<()> {
+        let members =
+            decl.members.iter().map(|member| (&member.name, *member.offset, &member.r#type));
+        self.members(members, value, offset, 0, path)
+    }
+
+    fn union(
+        &mut self,
+        decl: &Union,
+        alignment: u32,
+        value: &Value,
+        offset: usize,
+        path: &str,
+    ) -> Result<()> {
+        let (name, member_value) = single_member(value, path)?;
+        let tag = decl
+            .members
+            .iter()
+            .position(|member| *member.name == *name)
+            .ok_or_else(|| invalid(format!("unknown member `{}`", name), path))?;
+        let member = &decl.members[tag];
+        self.write(offset, &(tag as u32).to_le_bytes());
+        let data_offset = member.offset.unwrap_or_else(|| union_data_offset(alignment)) as usize;
+        self.inline(&member.r#type, member_value, offset + data_offset, &join(path, name))
+    }
+
+    /// Encodes `value` as `r#type` in a new envelope whose header is at `offset`.
+    fn envelope(&mut self, r#type: &Type, value: &Value, offset: usize, path: &str) -> Result<()> {
+        self.enter(path)?;
+        let start = self.bytes.len();
+        let first_handle = self.handles.len();
+        let (size, _) = self.shape(r#type, path)?;
+        let body = self.alloc(size);
+        self.inline(r#type, value, body, path)?;
+        self.leave();
+        let num_bytes = (self.bytes.len() - start) as u32;
+        let num_handles = (self.handles.len() - first_handle) as u32;
+        self.write(offset, &num_bytes.to_le_bytes());
+        self.write(offset + 4, &num_handles.to_le_bytes());
+        self.write(offset + 8, &ALLOC_PRESENT.to_le_bytes());
+        Ok(())
+    }
+
+    fn table(&mut self, decl: &Table, value: &Value, offset: usize, path: &str) -> Result<()> {
+        let object = as_object(value, path)?;
+        let fields = table_fields(decl);
+        let mut present = Vec::new();
+        for (name, field_value) in object {
+            let field = fields
+                .iter()
+                .find(|(_, field_name, _)| field_name.as_str() == name)
+                .ok_or_else(|| invalid(format!("unknown member `{}`", name), path))?;
+            if !field_value.is_null() {
+                present.push((field.0, field_value, field));
+            }
+        }
+        present.sort_by_key(|(ordinal, _, _)| *ordinal);
+        let count = present.last().map_or(0, |(ordinal, _, _)| *ordinal) as usize;
+        self.vector_header(count, None, offset, path)?;
+        self.enter(path)?;
+        let body = self.alloc(count * ENVELOPE_SIZE as usize);
+        for (ordinal, field_value, (_, name, r#type)) in present {
+            let envelope = body + (ordinal as usize - 1) * ENVELOPE_SIZE as usize;
+            self.envelope(r#type, field_value, envelope, &join(path, name))?;
+        }
+        self.leave();
+        Ok(())
+    }
+
+    fn xunion(
+        &mut self,
+        decl: &XUnion,
+        nullable: bool,
+        value: &Value,
+        offset: usize,
+        path: &str,
+    ) -> Result<()> {
+        if value.is_null() {
+            if nullable {
+                return Ok(());
+            }
+            return Err(error(ErrorKind::UnexpectedNull, path, None));
+        }
+        let (name, member_value) = single_member(value, path)?;
+        let member = decl
+            .members
+            .iter()
+            .find(|member| *member.name == *name)
+            .ok_or_else(|| invalid(format!("unknown member `{}`", name), path))?;
+        let ordinal = member.ordinal.ok_or_else(|| unknown_layout("xunion ordinal", path))?;
+        self.write(offset, &(ordinal as u32).to_le_bytes());
+        self.envelope(&member.r#type, member_value, offset + 8, &join(path, name))
+    }
+}
+
+fn number_value(value: i128) -> Value {
+    if value < 0 {
+        (value as i64).into()
+    } else {
+        (value as u64).into()
+    }
+}
+
+/// Encodes a request or response message with the given header from a JSON object keyed by
+/// parameter name.
+pub fn encode_message<L: DeclLookup + ?Sized>(
+    lookup: &L,
+    header: &TransactionHeader,
+    message: &MethodReqRes,
+    value: &Value,
+) -> std::result::Result<Message, Error> {
+    let mut encoder = Encoder::new(lookup);
+    let size = message_size(lookup, message)? as usize;
+    encoder.bytes.resize(size, 0);
+    encoder.write(0, &header.encode());
+    let params = message.parameters.iter().map(|param| (&param.name, *param.offset, &param.r#type));
+    encoder.members(params, value, 0, HEADER_SIZE as usize, "")?;
+    Ok(Message { bytes: encoder.bytes, handles: encoder.handles })
+}
+
+/// Encodes a standalone value of `r#type`, such as a struct or table.
+pub fn encode_value<L: DeclLookup + ?Sized>(
+    lookup: &L,
+    r#type: &Type,
+    value: &Value,
+) -> std::result::Result<Message, Error> {
+    let mut encoder = Encoder::new(lookup);
+    let (size, _) = encoder.shape(r#type, "")?;
+    let offset = encoder.alloc(size);
+    encoder.inline(r#type, value, offset, "")?;
+    Ok(Message { bytes: encoder.bytes, handles: encoder.handles })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{fixtures, wire::decode::decode_value},
+        serde_json::json,
+    };
+
+    fn encode(r#type: Value, value: Value) -> std::result::Result<Message, ErrorKind> {
+        let library = fixtures::library("example");
+        encode_value(&library, &fixtures::r#type(r#type), &value).map_err(|err| err.kind)
+    }
+
+    /// Encodes `value` and decodes it back.
+    fn round_trip(r#type: Value, value: Value) -> Value {
+        let library = fixtures::library("example");
+        let r#type = fixtures::r#type(r#type);
+        let message = encode_value(&library, &r#type, &value).unwrap();
+        decode_value(&library, &r#type, &message.bytes, &message.handles).unwrap().value
+    }
+
+    fn invalid_value(result: std::result::Result<Message, ErrorKind>) -> String {
+        match result {
+            Err(ErrorKind::InvalidValue { reason }) => reason,
+            other => panic!("expected an invalid value, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn encodes_struct_with_padding() {
+        let point = json!({"kind": "identifier", "identifier": "test.example/Point"});
+        let message = encode(point, json!({"x": 7, "y": 9})).unwrap();
+        assert_eq!(message.bytes, [7, 0, 0, 0, 9, 0, 0, 0]);
+    }
+
+    #[test]
+    fn round_trips_strings_tables_and_xunions() {
+        let string = json!({"kind": "string", "maybe_element_count": 10});
+        assert_eq!(round_trip(string, json!("hello")), json!("hello"));
+        let settings = json!({"kind": "identifier", "identifier": "test.example/Settings"});
+        let value = json!({"enabled": true, "label": "on"});
+        assert_eq!(round_trip(settings, value.clone()), value);
+        let xunion = json!({"kind": "identifier", "identifier": "test.example/Value"});
+        let value = json!({"point": {"x": 1, "y": 2}});
+        assert_eq!(round_trip(xunion, value.clone()), value);
+    }
+
+    #[test]
+    fn encodes_enums_by_name_or_value() {
+        let color = json!({"kind": "identifier", "identifier": "test.example/Color"});
+        assert_eq!(encode(color.clone(), json!("Green")).unwrap().bytes, [2, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(encode(color.clone(), json!(1)).unwrap().bytes, [1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(encode(color, json!(3)).err(), Some(ErrorKind::InvalidEnumValue { value: 3 }));
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        let point = json!({"kind": "identifier", "identifier": "test.example/Point"});
+        assert_eq!(
+            invalid_value(encode(point.clone(), json!({"x": 256, "y": 0}))),
+            "256 is out of range for UInt8"
+        );
+        assert_eq!(invalid_value(encode(point.clone(), json!({"x": 1}))), "missing member");
+        assert_eq!(
+            invalid_value(encode(point, json!({"x": 1, "y": 2, "z": 3}))),
+            "unknown member `z`"
+        );
+        let string = json!({"kind": "string", "maybe_element_count": 2});
+        assert_eq!(
+            encode(string, json!("abc")).err(),
+            Some(ErrorKind::ExceedsBound { count: 3, bound: 2 })
+        );
+        assert_eq!(
+            encode(json!({"kind": "string"}), Value::Null).err(),
+            Some(ErrorKind::UnexpectedNull)
+        );
+        let handle = json!({"kind": "handle", "subtype": "vmo"});
+        assert_eq!(invalid_value(encode(handle, json!(0))), "expected a handle, got 0");
+    }
+}
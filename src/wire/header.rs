@@ -0,0 +1,92 @@
+use {
+    super::{Error, ErrorKind},
+    crate::layout::HEADER_SIZE,
+};
+
+/// The magic number of the initial version of the wire format.
+pub const MAGIC_NUMBER_INITIAL: u8 = 1;
+
+/// The header at the start of every transactional message.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub struct TransactionHeader {
+    /// The transaction id, which pairs a response with its request. Zero for one-way requests and
+    /// events.
+    pub txid: u32,
+    pub flags: [u8; 3],
+    pub magic: u8,
+    pub ordinal: u64,
+}
+
+impl TransactionHeader {
+    /// Creates a header with the current magic number and no flags.
+    pub fn new(txid: u32, ordinal: u64) -> Self {
+        TransactionHeader { txid, flags: [0; 3], magic: MAGIC_NUMBER_INITIAL, ordinal }
+    }
+
+    /// Reads the header from the start of `bytes`, checking its magic number.
+    pub fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < HEADER_SIZE as usize {
+            return Err(Error {
+                kind: ErrorKind::OutOfBounds,
+                path: "header".to_string(),
+                offset: Some(0),
+            });
+        }
+        let mut txid = [0; 4];
+        txid.copy_from_slice(&bytes[0..4]);
+        let mut flags = [0; 3];
+        flags.copy_from_slice(&bytes[4..7]);
+        let mut ordinal = [0; 8];
+        ordinal.copy_from_slice(&bytes[8..16]);
+        let header = TransactionHeader {
+            txid: u32::from_le_bytes(txid),
+            flags,
+            magic: bytes[7],
+            ordinal: u64::from_le_bytes(ordinal),
+        };
+        if header.magic != MAGIC_NUMBER_INITIAL {
+            let kind = ErrorKind::UnsupportedMagic { magic: header.magic };
+            return Err(Error { kind, path: "header.magic".to_string(), offset: Some(7) });
+        }
+        Ok(header)
+    }
+
+    pub fn encode(&self) -> [u8; HEADER_SIZE as usize] {
+        let mut bytes = [0; HEADER_SIZE as usize];
+        bytes[0..4].copy_from_slice(&self.txid.to_le_bytes());
+        bytes[4..7].copy_from_slice(&self.flags);
+        bytes[7] = self.magic;
+        bytes[8..16].copy_from_slice(&self.ordinal.to_le_bytes());
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_header() {
+        let header =
+            TransactionHeader { txid: 0x0102_0304, flags: [5, 6, 7], magic: 1, ordinal: 1234 };
+        let bytes = header.encode();
+        assert_eq!(bytes, [4, 3, 2, 1, 5, 6, 7, 1, 0xd2, 0x04, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(TransactionHeader::decode(&bytes), Ok(header));
+    }
+
+    #[test]
+    fn rejects_unsupported_magic() {
+        let mut bytes = TransactionHeader::new(1, 2).encode();
+        bytes[7] = 2;
+        let err = TransactionHeader::decode(&bytes).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnsupportedMagic { magic: 2 });
+        assert_eq!((err.path.as_str(), err.offset), ("header.magic", Some(7)));
+    }
+
+    #[test]
+    fn rejects_short_header() {
+        let bytes = TransactionHeader::new(1, 2).encode();
+        let err = TransactionHeader::decode(&bytes[..15]).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::OutOfBounds);
+    }
+}
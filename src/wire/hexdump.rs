@@ -0,0 +1,193 @@
+use {
+    super::{decode::Decoder, Error, HandleInfo, Region, RegionKind},
+    crate::{visit::DeclLookup, MethodReqRes, Type},
+    std::fmt::Write,
+};
+
+const BYTES_PER_ROW: usize = 16;
+
+fn describe(region: &Region) -> String {
+    match (region.path.is_empty(), region.kind) {
+        (true, kind) => kind.to_string(),
+        (false, RegionKind::Value) | (false, RegionKind::Header) => region.path.clone(),
+        (false, kind) => format!("{} ({})", region.path, kind),
+    }
+}
+
+/// Formats `bytes` as a hex dump, one labeled region per line. Regions longer than a row wrap
+/// onto continuation lines, and bytes not covered by any region are labeled `?`.
+pub fn hexdump(bytes: &[u8], regions: &[Region]) -> String {
+    let mut regions: Vec<&Region> = regions.iter().collect();
+    regions.sort_by_key(|region| (region.offset, region.len));
+
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    let mut push = |start: usize, end: usize, label: String| {
+        let end = end.min(bytes.len());
+        for (row, chunk) in bytes[start..end].chunks(BYTES_PER_ROW).enumerate() {
+            let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let label = if row == 0 { label.as_str() } else { "..." };
+            lines.push((start + row * BYTES_PER_ROW, hex.join(" "), label.to_string()));
+        }
+    };
+    for region in regions {
+        if region.offset < offset || region.offset >= bytes.len() {
+            continue;
+        }
+        if region.offset > offset {
+            push(offset, region.offset, "?".to_string());
+        }
+        push(region.offset, region.offset + region.len, describe(region));
+        offset = region.offset + region.len;
+    }
+    if offset < bytes.len() {
+        push(offset, bytes.len(), "?".to_string());
+    }
+
+    let width = lines.iter().map(|(_, hex, _)| hex.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    for (offset, hex, label) in lines {
+        writeln!(out, "{:08x}  {:width$}  {}", offset, hex, label, width = width).unwrap();
+    }
+    out
+}
+
+fn dump(bytes: &[u8], regions: &[Region], result: Result<(), Error>) -> String {
+    let mut out = hexdump(bytes, regions);
+    if let Err(err) = result {
+        writeln!(out, "error: {}", err).unwrap();
+    }
+    out
+}
+
+/// Decodes a request or response message and formats it as an annotated hex dump. If decoding
+/// fails, the bytes decoded so far are labeled and the error is appended.
+pub fn hexdump_message<L: DeclLookup + ?Sized>(
+    lookup: &L,
+    message: &MethodReqRes,
+    bytes: &[u8],
+    handles: &[HandleInfo],
+) -> String {
+    let mut decoder = Decoder::new(lookup, bytes, handles);
+    let result = decoder.message(message).and_then(|_| decoder.finish());
+    dump(bytes, &decoder.regions, result)
+}
+
+/// Decodes a standalone value of `r#type` and formats it as an annotated hex dump.
+pub fn hexdump_value<L: DeclLookup + ?Sized>(
+    lookup: &L,
+    r#type: &Type,
+    bytes: &[u8],
+    handles: &[HandleInfo],
+) -> String {
+    let mut decoder = Decoder::new(lookup, bytes, handles);
+    let result = decoder.top_level(r#type).and_then(|_| decoder.finish());
+    dump(bytes, &decoder.regions, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            fixtures,
+            wire::{encode_message, encode_value, RegionKind, TransactionHeader},
+        },
+        serde_json::json,
+    };
+
+    fn region(offset: usize, len: usize, path: &str, kind: RegionKind) -> Region {
+        Region { offset, len, path: path.to_string(), kind }
+    }
+
+    #[test]
+    fn labels_regions_and_uncovered_bytes() {
+        let bytes: Vec<u8> = (0..20).collect();
+        let regions = [
+            region(8, 4, "items", RegionKind::Count),
+            region(2, 2, "x", RegionKind::Value),
+            region(4, 4, "", RegionKind::Padding),
+        ];
+        assert_eq!(
+            hexdump(&bytes, &regions),
+            "\
+00000000  00 01                    ?
+00000002  02 03                    x
+00000004  04 05 06 07              padding
+00000008  08 09 0a 0b              items (count)
+0000000c  0c 0d 0e 0f 10 11 12 13  ?
+"
+        );
+    }
+
+    #[test]
+    fn wraps_long_regions() {
+        let bytes = [0xab; 20];
+        assert_eq!(
+            hexdump(&bytes, &[region(0, 20, "data", RegionKind::Value)]),
+            "\
+00000000  ab ab ab ab ab ab ab ab ab ab ab ab ab ab ab ab  data
+00000010  ab ab ab ab                                      ...
+"
+        );
+    }
+
+    #[test]
+    fn attributes_padding_to_the_preceding_member() {
+        let library = fixtures::library("example");
+        let node =
+            fixtures::r#type(json!({"kind": "identifier", "identifier": "test.example/Node"}));
+        let value = json!({"a": 1, "children": [], "b": 2, "color": "RED"});
+        let message = encode_value(&library, &node, &value).unwrap();
+        assert_eq!(
+            hexdump_value(&library, &node, &message.bytes, &message.handles),
+            "\
+00000000  01                       a
+00000001  00 00 00 00 00 00 00     a (padding)
+00000008  00 00 00 00 00 00 00 00  children (count)
+00000010  ff ff ff ff ff ff ff ff  children (presence)
+00000018  02 00                    b
+0000001a  00 00                    b (padding)
+0000001c  01 00 00 00              color
+"
+        );
+    }
+
+    #[test]
+    fn dumps_message() {
+        let library = fixtures::library("example");
+        let request = library.protocols[0].methods[0].request.as_ref().unwrap();
+        let value = json!({"value": "hi", "p": {"x": 1, "y": 2}});
+        let header = TransactionHeader::new(1, 1234);
+        let message = encode_message(&library, &header, request, &value).unwrap();
+        assert_eq!(
+            hexdump_message(&library, request, &message.bytes, &[]),
+            "\
+00000000  01 00 00 00              header.txid
+00000004  00 00 00                 header.flags
+00000007  01                       header.magic
+00000008  d2 04 00 00 00 00 00 00  header.ordinal
+00000010  02 00 00 00 00 00 00 00  value (count)
+00000018  ff ff ff ff ff ff ff ff  value (presence)
+00000020  01                       p.x
+00000021  00 00 00                 p.x (padding)
+00000024  02 00 00 00              p.y
+00000028  68 69                    value
+0000002a  00 00 00 00 00 00        value (padding)
+"
+        );
+    }
+
+    #[test]
+    fn appends_decode_error() {
+        let library = fixtures::library("example");
+        let request = library.protocols[0].methods[0].request.as_ref().unwrap();
+        let value = json!({"value": "hi", "p": {"x": 1, "y": 2}});
+        let header = TransactionHeader::new(1, 1234);
+        let message = encode_message(&library, &header, request, &value).unwrap();
+        let dump = hexdump_message(&library, request, &message.bytes[..36], &[]);
+        assert!(dump.starts_with("00000000  01 00 00 00 "), "{}", dump);
+        assert!(dump.contains("00000010  02 00 00 00 00 00 00 00 ff ff ff ff ff ff ff ff  ?\n"));
+        assert!(dump.ends_with("error: message is too short at offset 0x0\n"), "{}", dump);
+    }
+}
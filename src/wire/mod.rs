@@ -0,0 +1,275 @@
+//! Encoding and decoding of FIDL wire-format messages against the IR.
+//!
+//! Values are represented as JSON: structs, tables, unions, and xunions are objects keyed by
+//! member name, enums are member names, bits and other numbers are numbers, and handles are
+//! their handle values. Absent nullable values are `null`.
+
+use {
+    crate::{HandleRights, HandleSubtype},
+    serde::{Deserialize, Serialize},
+    std::fmt,
+};
+
+mod decode;
+pub use decode::{decode_message, decode_value, Decoded};
+
+mod encode;
+pub use encode::{encode_message, encode_value};
+
+mod hexdump;
+pub use hexdump::{hexdump, hexdump_message, hexdump_value};
+
+mod header;
+pub use header::{TransactionHeader, MAGIC_NUMBER_INITIAL};
+
+mod dispatch;
+pub use dispatch::{identify, Identified, MessageKind, MethodRef, OrdinalIndex};
+
+/// The maximum depth of out-of-line objects a message may contain.
+pub const MAX_DEPTH: u32 = 32;
+
+/// Information about a handle transferred along with a message, as reported by the kernel.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct HandleInfo {
+    pub handle: u32,
+    /// The Zircon object type of the handle, or `0` if it isn't known.
+    #[serde(default)]
+    pub obj_type: u32,
+    /// The handle's rights, or `SAME_RIGHTS` if they aren't known.
+    #[serde(default = "same_rights")]
+    pub rights: HandleRights,
+}
+
+fn same_rights() -> HandleRights {
+    HandleRights::SAME_RIGHTS
+}
+
+impl HandleInfo {
+    /// Creates handle info for a handle whose type and rights aren't known.
+    pub fn unknown(handle: u32) -> Self {
+        HandleInfo { handle, obj_type: 0, rights: HandleRights::SAME_RIGHTS }
+    }
+}
+
+/// An encoded message and the handles sent with it.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Message {
+    pub bytes: Vec<u8>,
+    pub handles: Vec<HandleInfo>,
+}
+
+/// What a labeled range of bytes in a message holds.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub enum RegionKind {
+    /// A field of the transactional message header.
+    Header,
+    /// A primitive value, or the bytes of a string.
+    Value,
+    /// The element count of a vector, string, or table.
+    Count,
+    /// An out-of-line presence marker, or the presence of a handle.
+    Presence,
+    /// The ordinal of an xunion or the tag of a union.
+    Tag,
+    /// The byte and handle counts of an envelope.
+    Envelope,
+    /// The contents of an envelope whose ordinal isn't known.
+    Unknown,
+    Padding,
+}
+
+impl fmt::Display for RegionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            RegionKind::Header => "header",
+            RegionKind::Value => "value",
+            RegionKind::Count => "count",
+            RegionKind::Presence => "presence",
+            RegionKind::Tag => "tag",
+            RegionKind::Envelope => "envelope",
+            RegionKind::Unknown => "unknown",
+            RegionKind::Padding => "padding",
+        })
+    }
+}
+
+/// A labeled range of bytes in a message.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Region {
+    pub offset: usize,
+    pub len: usize,
+    /// The path of the field the bytes belong to, e.g. `items[2].name`.
+    pub path: String,
+    pub kind: RegionKind,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ErrorKind {
+    /// The message ended before the value did.
+    OutOfBounds,
+    /// Bytes were left over after the value.
+    ExtraBytes {
+        count: usize,
+    },
+    /// Handles were left over after the value.
+    ExtraHandles {
+        count: usize,
+    },
+    /// The value needed more handles than the message carried.
+    MissingHandle,
+    NonZeroPadding,
+    /// A presence marker was neither present nor absent.
+    InvalidPresence,
+    /// A non-nullable value was absent.
+    UnexpectedNull,
+    InvalidBool {
+        value: u8,
+    },
+    InvalidUtf8,
+    /// A string or vector had more elements than its bound.
+    ExceedsBound {
+        count: u64,
+        bound: u64,
+    },
+    InvalidEnumValue {
+        value: i128,
+    },
+    InvalidBitsValue {
+        value: u64,
+    },
+    InvalidUnionTag {
+        tag: u32,
+    },
+    /// An envelope's byte or handle counts didn't match its contents.
+    EnvelopeMismatch,
+    DepthExceeded,
+    /// A handle's object type didn't match the declaration.
+    WrongHandleType {
+        expected: HandleSubtype,
+        actual: u32,
+    },
+    /// A handle lacked rights required by the declaration.
+    MissingRights {
+        missing: HandleRights,
+    },
+    /// The IR didn't have enough information to interpret the value, such as a declaration
+    /// from a library that wasn't provided.
+    UnknownLayout {
+        what: String,
+    },
+    /// The header's magic number isn't one this library understands.
+    UnsupportedMagic {
+        magic: u8,
+    },
+    /// No known method has the header's ordinal.
+    UnknownOrdinal {
+        ordinal: u64,
+    },
+    /// The value couldn't be encoded because it didn't match the type.
+    InvalidValue {
+        reason: String,
+    },
+}
+
+/// An error encountered while encoding or decoding a message.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    /// The path of the field being processed.
+    pub path: String,
+    /// The offset in the message at which the error was found, if any.
+    pub offset: Option<usize>,
+}
+
+impl ErrorKind {
+    /// Returns a stable name for the kind of error, such as `EXCEEDS_BOUND`, for use in test
+    /// expectations.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::OutOfBounds => "OUT_OF_BOUNDS",
+            ErrorKind::ExtraBytes { .. } => "EXTRA_BYTES",
+            ErrorKind::ExtraHandles { .. } => "EXTRA_HANDLES",
+            ErrorKind::MissingHandle => "MISSING_HANDLE",
+            ErrorKind::NonZeroPadding => "NON_ZERO_PADDING",
+            ErrorKind::InvalidPresence => "INVALID_PRESENCE",
+            ErrorKind::UnexpectedNull => "UNEXPECTED_NULL",
+            ErrorKind::InvalidBool { .. } => "INVALID_BOOL",
+            ErrorKind::InvalidUtf8 => "INVALID_UTF8",
+            ErrorKind::ExceedsBound { .. } => "EXCEEDS_BOUND",
+            ErrorKind::InvalidEnumValue { .. } => "INVALID_ENUM_VALUE",
+            ErrorKind::InvalidBitsValue { .. } => "INVALID_BITS_VALUE",
+            ErrorKind::InvalidUnionTag { .. } => "INVALID_UNION_TAG",
+            ErrorKind::EnvelopeMismatch => "ENVELOPE_MISMATCH",
+            ErrorKind::DepthExceeded => "DEPTH_EXCEEDED",
+            ErrorKind::WrongHandleType { .. } => "WRONG_HANDLE_TYPE",
+            ErrorKind::MissingRights { .. } => "MISSING_RIGHTS",
+            ErrorKind::UnknownLayout { .. } => "UNKNOWN_LAYOUT",
+            ErrorKind::UnsupportedMagic { .. } => "UNSUPPORTED_MAGIC",
+            ErrorKind::UnknownOrdinal { .. } => "UNKNOWN_ORDINAL",
+            ErrorKind::InvalidValue { .. } => "INVALID_VALUE",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::OutOfBounds => write!(f, "message is too short"),
+            ErrorKind::ExtraBytes { count } => write!(f, "{} unused bytes", count),
+            ErrorKind::ExtraHandles { count } => write!(f, "{} unused handles", count),
+            ErrorKind::MissingHandle => write!(f, "not enough handles"),
+            ErrorKind::NonZeroPadding => write!(f, "padding is not zero"),
+            ErrorKind::InvalidPresence => write!(f, "invalid presence marker"),
+            ErrorKind::UnexpectedNull => write!(f, "non-nullable value is absent"),
+            ErrorKind::InvalidBool { value } => write!(f, "invalid bool {:#x}", value),
+            ErrorKind::InvalidUtf8 => write!(f, "string is not valid UTF-8"),
+            ErrorKind::ExceedsBound { count, bound } => {
+                write!(f, "{} elements exceeds the bound of {}", count, bound)
+            }
+            ErrorKind::InvalidEnumValue { value } => write!(f, "unknown enum value {}", value),
+            ErrorKind::InvalidBitsValue { value } => write!(f, "unknown bits in {:#x}", value),
+            ErrorKind::InvalidUnionTag { tag } => write!(f, "invalid union tag {}", tag),
+            ErrorKind::EnvelopeMismatch => write!(f, "envelope counts don't match its contents"),
+            ErrorKind::DepthExceeded => {
+                write!(f, "out-of-line objects nest deeper than {}", MAX_DEPTH)
+            }
+            ErrorKind::WrongHandleType { expected, actual } => {
+                write!(f, "expected a {:?} handle, got object type {}", expected, actual)
+            }
+            ErrorKind::MissingRights { missing } => {
+                write!(f, "handle is missing rights {:?}", missing)
+            }
+            ErrorKind::UnknownLayout { what } => write!(f, "unknown layout of {}", what),
+            ErrorKind::UnsupportedMagic { magic } => {
+                write!(f, "unsupported magic number {:#x}", magic)
+            }
+            ErrorKind::UnknownOrdinal { ordinal } => write!(f, "unknown ordinal {:#x}", ordinal),
+            ErrorKind::InvalidValue { reason } => write!(f, "invalid value: {}", reason),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.kind)?;
+        } else {
+            write!(f, "`{}`: {}", self.path, self.kind)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {:#x}", offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Appends a member name to a field path.
+pub(crate) fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}